@@ -0,0 +1,386 @@
+//! Hashing of EIP-712 typed data given as JSON, as accepted by `eth_signTypedData_v4`
+
+use ethers_core::{
+    types::{
+        transaction::eip712::{EIP712Domain, Eip712},
+        Address, I256, U256,
+    },
+    utils::keccak256,
+};
+use serde::Deserialize;
+use serde_json::{value::RawValue, Map, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+/// The name of the struct type of the domain
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// The fields the domain may have, in the order of their encoding
+const DOMAIN_FIELDS: [(&str, &str); 5] = [
+    ("name", "string"),
+    ("version", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+    ("salt", "bytes32"),
+];
+
+/// A field of a struct type, e.g. `{ "name": "owner", "type": "address" }`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Eip712Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// Typed data to hash or sign, e.g. a permit or an off-chain order.
+///
+/// If the `EIP712Domain` type is not given, it is inferred from the fields of the domain.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    /// The struct types, by name
+    pub types: BTreeMap<String, Vec<Eip712Field>>,
+    /// The type of the message
+    pub primary_type: String,
+    /// The values of the fields of the domain
+    pub domain: Map<String, Value>,
+    /// The values of the fields of the message
+    pub message: Map<String, Value>,
+}
+
+impl FromStr for TypedData {
+    type Err = TypedDataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |err: serde_json::Error| TypedDataError(format!("Invalid typed data: {}", err));
+        let json = serde_json::from_str(s).and_then(parse_json).map_err(invalid)?;
+        let mut typed_data: TypedData = serde_json::from_value(json).map_err(invalid)?;
+        if !typed_data.types.contains_key(DOMAIN_TYPE) {
+            let fields = DOMAIN_FIELDS
+                .iter()
+                .filter(|(name, _)| typed_data.domain.contains_key(*name))
+                .map(|(name, ty)| Eip712Field { name: name.to_string(), ty: ty.to_string() })
+                .collect();
+            typed_data.types.insert(DOMAIN_TYPE.to_string(), fields);
+        }
+        Ok(typed_data)
+    }
+}
+
+impl TypedData {
+    /// Returns the encoding of the struct type `ty`, e.g. `Mail(Person from,Person to)Person(..)`
+    pub fn encode_type(&self, ty: &str) -> Result<String, TypedDataError> {
+        let mut referenced = BTreeSet::new();
+        self.find_referenced_types(ty, &mut referenced)?;
+        referenced.remove(ty);
+
+        std::iter::once(ty)
+            .chain(referenced.iter().map(String::as_str))
+            .map(|ty| {
+                let fields = self.fields(ty)?;
+                let fields: Vec<String> =
+                    fields.iter().map(|field| format!("{} {}", field.ty, field.name)).collect();
+                Ok(format!("{}({})", ty, fields.join(",")))
+            })
+            .collect()
+    }
+
+    /// Returns the hash of the struct of type `ty`
+    pub fn hash_struct(
+        &self,
+        ty: &str,
+        data: &Map<String, Value>,
+    ) -> Result<[u8; 32], TypedDataError> {
+        let mut encoded = keccak256(self.encode_type(ty)?).to_vec();
+        for field in self.fields(ty)? {
+            let value = data.get(&field.name).unwrap_or(&Value::Null);
+            encoded.extend(self.encode_value(&field.ty, value)?);
+        }
+        Ok(keccak256(encoded))
+    }
+
+    fn fields(&self, ty: &str) -> Result<&[Eip712Field], TypedDataError> {
+        self.types
+            .get(ty)
+            .map(Vec::as_slice)
+            .ok_or_else(|| TypedDataError(format!("Unknown type `{}`", ty)))
+    }
+
+    /// Adds `ty` and the struct types its fields use, recursively, to `found`
+    fn find_referenced_types(
+        &self,
+        ty: &str,
+        found: &mut BTreeSet<String>,
+    ) -> Result<(), TypedDataError> {
+        if !found.insert(ty.to_string()) {
+            return Ok(())
+        }
+        for field in self.fields(ty)? {
+            let inner = field.ty.split('[').next().unwrap_or(&field.ty);
+            if self.types.contains_key(inner) {
+                self.find_referenced_types(inner, found)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the 32 byte encoding of a value of type `ty`
+    fn encode_value(&self, ty: &str, value: &Value) -> Result<[u8; 32], TypedDataError> {
+        let mismatch = || TypedDataError(format!("Can't encode `{}` as type `{}`", value, ty));
+
+        // Arrays are encoded as the hash of the concatenated encodings of their items
+        if let Some(inner) = ty.strip_suffix(']') {
+            let (inner, size) = inner.rsplit_once('[').ok_or_else(mismatch)?;
+            let values = value.as_array().ok_or_else(mismatch)?;
+            if !size.is_empty() && size.parse() != Ok(values.len()) {
+                return Err(mismatch())
+            }
+            let mut encoded = Vec::with_capacity(values.len() * 32);
+            for value in values {
+                encoded.extend(self.encode_value(inner, value)?);
+            }
+            return Ok(keccak256(encoded))
+        }
+
+        if self.types.contains_key(ty) {
+            return self.hash_struct(ty, value.as_object().ok_or_else(mismatch)?)
+        }
+
+        let mut word = [0u8; 32];
+        match ty {
+            "string" => return Ok(keccak256(value.as_str().ok_or_else(mismatch)?)),
+            "bytes" => return Ok(keccak256(decode_hex(value).ok_or_else(mismatch)?)),
+            "bool" => {
+                let b = match value {
+                    Value::Bool(b) => *b,
+                    Value::String(s) => s.parse().map_err(|_| mismatch())?,
+                    _ => return Err(mismatch()),
+                };
+                word[31] = b as u8;
+            }
+            "address" => {
+                let address: Address =
+                    value.as_str().and_then(|s| s.parse().ok()).ok_or_else(mismatch)?;
+                word[12..].copy_from_slice(address.as_bytes());
+            }
+            _ if ty.starts_with("uint") => {
+                parse_uint(value).ok_or_else(mismatch)?.to_big_endian(&mut word)
+            }
+            _ if ty.starts_with("int") => {
+                parse_int(value).ok_or_else(mismatch)?.into_raw().to_big_endian(&mut word)
+            }
+            _ if ty.starts_with("bytes") => {
+                let size: usize = ty["bytes".len()..].parse().map_err(|_| mismatch())?;
+                let bytes = decode_hex(value).filter(|bytes| bytes.len() <= size && size <= 32);
+                let bytes = bytes.ok_or_else(mismatch)?;
+                word[..bytes.len()].copy_from_slice(&bytes);
+            }
+            _ => return Err(TypedDataError(format!("Unknown type `{}`", ty))),
+        }
+        Ok(word)
+    }
+}
+
+impl Eip712 for TypedData {
+    type Error = TypedDataError;
+
+    /// The domain separator is computed from the JSON instead, as the domain may have any subset
+    /// of the fields, which [EIP712Domain] can't represent
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Err(TypedDataError("The domain of typed data is only available as JSON".to_string()))
+    }
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        self.hash_struct(DOMAIN_TYPE, &self.domain)
+    }
+
+    /// The types are only known at runtime, see [TypedData::encode_type]
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Err(TypedDataError("The types of typed data are only known at runtime".to_string()))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        self.hash_struct(&self.primary_type, &self.message)
+    }
+}
+
+/// An error in the types or values of typed data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedDataError(pub String);
+
+impl fmt::Display for TypedDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TypedDataError {}
+
+/// Converts raw JSON to a value, keeping integers that don't fit into 64 bits, like amounts of
+/// tokens, as decimal strings instead of rounding them to floats
+fn parse_json(raw: &RawValue) -> serde_json::Result<Value> {
+    let text = raw.get().trim();
+    Ok(match text.as_bytes().first().copied() {
+        Some(b'[') => Value::Array(
+            serde_json::from_str::<Vec<&RawValue>>(text)?
+                .into_iter()
+                .map(parse_json)
+                .collect::<serde_json::Result<_>>()?,
+        ),
+        Some(b'{') => Value::Object(
+            serde_json::from_str::<BTreeMap<String, &RawValue>>(text)?
+                .into_iter()
+                .map(|(key, raw)| Ok((key, parse_json(raw)?)))
+                .collect::<serde_json::Result<_>>()?,
+        ),
+        _ => {
+            let value: Value = serde_json::from_str(text)?;
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            if value.is_f64() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                Value::String(text.to_string())
+            } else {
+                value
+            }
+        }
+    })
+}
+
+/// Decodes a `0x` prefixed hex string
+fn decode_hex(value: &Value) -> Option<Vec<u8>> {
+    hex::decode(value.as_str()?.strip_prefix("0x")?).ok()
+}
+
+/// Parses a number, a decimal string or a `0x` prefixed hex string
+fn parse_uint(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(number) => number.as_u64().map(U256::from),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(digits) => U256::from_str_radix(digits, 16).ok(),
+            None => U256::from_dec_str(s).ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Parses a number or a decimal string, which may be negative, or a `0x` prefixed hex string of
+/// the two's complement
+fn parse_int(value: &Value) -> Option<I256> {
+    match value {
+        Value::Number(number) => I256::from_dec_str(&number.to_string()).ok(),
+        Value::String(s) if s.starts_with("0x") => parse_uint(value).map(I256::from_raw),
+        Value::String(s) => I256::from_dec_str(s).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example of EIP-712
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Person": [
+                { "name": "name", "type": "string" },
+                { "name": "wallet", "type": "address" }
+            ],
+            "Mail": [
+                { "name": "from", "type": "Person" },
+                { "name": "to", "type": "Person" },
+                { "name": "contents", "type": "string" }
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+            "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    fn can_hash_typed_data() {
+        let typed_data: TypedData = MAIL.parse().unwrap();
+        assert_eq!(
+            typed_data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            hex::encode(typed_data.domain_separator().unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(typed_data.struct_hash().unwrap()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(typed_data.encode_eip712().unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+
+    #[test]
+    fn can_infer_domain_type() {
+        let mut json: Value = serde_json::from_str(MAIL).unwrap();
+        json["types"].as_object_mut().unwrap().remove(DOMAIN_TYPE);
+        let typed_data: TypedData = json.to_string().parse().unwrap();
+        assert_eq!(
+            hex::encode(typed_data.domain_separator().unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+
+        json["message"]["to"]["wallet"] = "Bob".into();
+        let typed_data: TypedData = json.to_string().parse().unwrap();
+        assert!(typed_data.struct_hash().is_err());
+    }
+
+    #[test]
+    fn can_hash_large_integers() {
+        let permit = |value: &str, delta: &str| {
+            format!(
+                r#"{{
+                    "types": {{
+                        "Permit": [
+                            {{ "name": "owner", "type": "address" }},
+                            {{ "name": "value", "type": "uint256" }},
+                            {{ "name": "delta", "type": "int256" }}
+                        ]
+                    }},
+                    "primaryType": "Permit",
+                    "domain": {{ "name": "Token", "chainId": 1 }},
+                    "message": {{
+                        "owner": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                        "value": {value},
+                        "delta": {delta}
+                    }}
+                }}"#,
+                value = value,
+                delta = delta
+            )
+        };
+
+        // Integers above 64 bits are encoded exactly, the same as the decimal strings
+        let typed_data: TypedData =
+            permit("1000000000000000000001", "-1000000000000000000001").parse().unwrap();
+        let quoted: TypedData =
+            permit(r#""1000000000000000000001""#, r#""-1000000000000000000001""#).parse().unwrap();
+        assert_eq!(typed_data.message["value"], "1000000000000000000001");
+        assert_eq!(typed_data.struct_hash().unwrap(), quoted.struct_hash().unwrap());
+    }
+}
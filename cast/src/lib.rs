@@ -7,7 +7,7 @@ use ethers_core::{
         token::{LenientTokenizer, Tokenizer},
         Abi, AbiParser, Token,
     },
-    types::{Chain, *},
+    types::{transaction::eip712::Eip712, Chain, *},
     utils::{self, get_contract_address, keccak256, parse_units},
 };
 
@@ -21,6 +21,7 @@ use tx::{TxBuilderOutput, TxBuilderPeekOutput};
 
 use foundry_utils::{encode_args, to_table};
 
+pub mod eip712;
 mod tx;
 
 // TODO: CastContract with common contract initializers? Same for CastProviders?
@@ -1103,6 +1104,29 @@ impl SimpleCast {
         Ok(format!("0x{}", hash))
     }
 
+    /// Returns the EIP-712 hash of typed data given as JSON, which is the digest that is signed
+    ///
+    /// ```
+    /// use cast::SimpleCast as Cast;
+    ///
+    /// fn main() -> eyre::Result<()> {
+    ///     let json = r#"{
+    ///         "types": { "Message": [{ "name": "contents", "type": "string" }] },
+    ///         "primaryType": "Message",
+    ///         "domain": { "name": "Cast", "chainId": 1 },
+    ///         "message": { "contents": "Hello" }
+    ///     }"#;
+    ///     assert_eq!(Cast::eip712_hash(json)?.len(), 66);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn eip712_hash(json: &str) -> Result<String> {
+        let typed_data: eip712::TypedData = json.parse()?;
+        let hash: String = typed_data.encode_eip712()?.to_hex();
+        Ok(format!("0x{}", hash))
+    }
+
     /// Converts ENS names to their namehash representation
     /// [Namehash reference](https://docs.ens.domains/contract-api-reference/name-processing#hashing-names)
     /// [namehash-rust reference](https://github.com/InstateDev/namehash-rust/blob/master/src/lib.rs)
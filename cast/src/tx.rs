@@ -1,8 +1,8 @@
 use ethers_core::{
     abi::Function,
     types::{
-        transaction::eip2718::TypedTransaction, Chain, Eip1559TransactionRequest, NameOrAddress,
-        TransactionRequest, H160, U256,
+        transaction::eip2718::TypedTransaction, Bytes, Chain, Eip1559TransactionRequest,
+        NameOrAddress, TransactionRequest, H160, U256,
     },
 };
 use ethers_providers::Middleware;
@@ -14,7 +14,7 @@ use futures::future::join_all;
 use crate::strip_0x;
 
 pub struct TxBuilder<'a, M: Middleware> {
-    to: H160,
+    to: Option<H160>,
     chain: Chain,
     tx: TypedTransaction,
     func: Option<Function>,
@@ -61,7 +61,29 @@ impl<'a, M: Middleware> TxBuilder<'a, M> {
             Eip1559TransactionRequest::new().from(from_addr).to(to_addr).into()
         };
 
-        Ok(Self { to: to_addr, chain, tx, func: None, etherscan_api_key: None, provider })
+        Ok(Self { to: Some(to_addr), chain, tx, func: None, etherscan_api_key: None, provider })
+    }
+
+    /// Create a new TxBuilder for a transaction that deploys a contract
+    /// `provider` - provider to use
+    /// `from` - 'from' field. Could be an ENS name
+    /// `chain` - chain to construct the tx for
+    /// `legacy` - use type 1 transaction
+    pub async fn new_create<F: Into<NameOrAddress>>(
+        provider: &'a M,
+        from: F,
+        chain: Chain,
+        legacy: bool,
+    ) -> Result<TxBuilder<'a, M>> {
+        let from_addr = resolve_ens(provider, from).await?;
+
+        let tx: TypedTransaction = if chain.is_legacy() || legacy {
+            TransactionRequest::new().from(from_addr).into()
+        } else {
+            Eip1559TransactionRequest::new().from(from_addr).into()
+        };
+
+        Ok(Self { to: None, chain, tx, func: None, etherscan_api_key: None, provider })
     }
 
     /// Set gas for tx
@@ -120,6 +142,20 @@ impl<'a, M: Middleware> TxBuilder<'a, M> {
         self
     }
 
+    /// Set data, which is the calldata or the init code of a contract
+    pub fn set_data(&mut self, v: Bytes) -> &mut Self {
+        self.tx.set_data(v);
+        self
+    }
+
+    /// Set data, if `v` is not None
+    pub fn data(&mut self, v: Option<Bytes>) -> &mut Self {
+        if let Some(value) = v {
+            self.set_data(value);
+        }
+        self
+    }
+
     /// Set etherscan API key. Used to look up function signature buy name
     pub fn set_etherscan_api_key(&mut self, v: String) -> &mut Self {
         self.etherscan_api_key = Some(v);
@@ -156,7 +192,7 @@ impl<'a, M: Middleware> TxBuilder<'a, M> {
         } else {
             get_func_etherscan(
                 sig,
-                self.to,
+                self.to.ok_or_else(|| eyre!("Can not look up `{}` without a `to` address", sig))?,
                 &args,
                 self.chain,
                 self.etherscan_api_key.as_ref().expect("Must set ETHERSCAN_API_KEY"),
@@ -307,6 +343,18 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn builder_new_create() -> eyre::Result<()> {
+        let provider = MyProvider {};
+        let mut builder = TxBuilder::new_create(&provider, "a.eth", Chain::Mainnet, false).await?;
+        builder.data(Some(vec![0x60, 0x00].into()));
+        let (tx, _) = builder.build();
+        assert_eq!(*tx.from().unwrap(), H160::from_str(ADDR_1).unwrap());
+        assert_eq!(tx.to(), None);
+        assert_eq!(tx.data().unwrap().to_vec(), vec![0x60, 0x00]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn builder_fields() -> eyre::Result<()> {
         let provider = MyProvider {};
@@ -6,7 +6,7 @@ mod utils;
 
 use cast::{Cast, SimpleCast, TxBuilder};
 mod opts;
-use cast::InterfacePath;
+use cast::{eip712::TypedData, InterfacePath};
 use ethers::{
     contract::BaseContract,
     core::{
@@ -383,11 +383,11 @@ async fn main() -> eyre::Result<()> {
             println!("{}", encoded);
         }
         Subcommands::FourByte { selector } => {
-            let sigs = foundry_utils::fourbyte(&selector).await?;
-            sigs.iter().for_each(|sig| println!("{}", sig.0));
+            let sigs = utils::signature_db().identify_function(&selector).await?;
+            sigs.iter().for_each(|sig| println!("{}", sig));
         }
         Subcommands::FourByteDecode { calldata, id } => {
-            let sigs = foundry_utils::fourbyte_possible_sigs(&calldata, id).await?;
+            let sigs = utils::signature_db().identify_calldata(&calldata, id).await?;
             sigs.iter().enumerate().for_each(|(i, sig)| println!("{}) \"{}\"", i + 1, sig));
 
             let sig = match sigs.len() {
@@ -409,8 +409,8 @@ async fn main() -> eyre::Result<()> {
             tokens.for_each(|t| println!("{}", t));
         }
         Subcommands::FourByteEvent { topic } => {
-            let sigs = foundry_utils::fourbyte_event(&topic).await?;
-            sigs.iter().for_each(|sig| println!("{}", sig.0));
+            let sigs = utils::signature_db().identify_event(&topic).await?;
+            sigs.iter().for_each(|sig| println!("{}", sig));
         }
 
         Subcommands::PrettyCalldata { calldata, offline } => {
@@ -418,7 +418,9 @@ async fn main() -> eyre::Result<()> {
                 eprintln!("Expected calldata hex string, received \"{}\"", calldata);
                 std::process::exit(0)
             }
-            let pretty_data = foundry_utils::pretty_calldata(&calldata, offline).await?;
+            let pretty_data =
+                foundry_utils::pretty_calldata(&calldata, offline, &mut utils::signature_db())
+                    .await?;
             println!("{}", pretty_data);
         }
         Subcommands::Age { block, rpc_url } => {
@@ -446,6 +448,9 @@ async fn main() -> eyre::Result<()> {
         Subcommands::Keccak { data } => {
             println!("{}", SimpleCast::keccak(&data)?);
         }
+        Subcommands::Eip712Hash { path } => {
+            println!("{}", SimpleCast::eip712_hash(&std::fs::read_to_string(path)?)?);
+        }
 
         Subcommands::Interface {
             path_or_address,
@@ -569,6 +574,8 @@ async fn main() -> eyre::Result<()> {
             println!("0x{}", hex::encode(selector));
         }
         Subcommands::FindBlock(cmd) => cmd.run()?.await?,
+        // The executor blocks on requests to the forked chain, so it must not run on the runtime
+        Subcommands::Run(cmd) => tokio::task::spawn_blocking(move || cmd.run()).await??,
         Subcommands::Wallet { command } => match command {
             WalletSubcommands::New { path, password, unsafe_password } => {
                 let mut rng = thread_rng();
@@ -673,7 +680,7 @@ async fn main() -> eyre::Result<()> {
                 };
                 println!("Address: {}", SimpleCast::checksum_address(&addr)?);
             }
-            WalletSubcommands::Sign { message, wallet } => {
+            WalletSubcommands::Sign { message, typed_data, wallet } => {
                 // TODO: Figure out better way to get wallet only.
                 let wallet = EthereumOpts {
                     wallet,
@@ -687,10 +694,35 @@ async fn main() -> eyre::Result<()> {
                 .await?
                 .unwrap();
 
-                let sig = match wallet {
-                    WalletType::Ledger(wallet) => wallet.signer().sign_message(&message).await?,
-                    WalletType::Local(wallet) => wallet.signer().sign_message(&message).await?,
-                    WalletType::Trezor(wallet) => wallet.signer().sign_message(&message).await?,
+                let sig = match (message, typed_data) {
+                    (_, Some(path)) => {
+                        let typed_data: TypedData = std::fs::read_to_string(path)?.parse()?;
+                        match wallet {
+                            WalletType::Ledger(wallet) => {
+                                wallet.signer().sign_typed_data(&typed_data).await?
+                            }
+                            WalletType::Local(wallet) => {
+                                wallet.signer().sign_typed_data(&typed_data).await?
+                            }
+                            WalletType::Trezor(wallet) => {
+                                wallet.signer().sign_typed_data(&typed_data).await?
+                            }
+                        }
+                    }
+                    (message, None) => {
+                        let message = message.unwrap_or_default();
+                        match wallet {
+                            WalletType::Ledger(wallet) => {
+                                wallet.signer().sign_message(&message).await?
+                            }
+                            WalletType::Local(wallet) => {
+                                wallet.signer().sign_message(&message).await?
+                            }
+                            WalletType::Trezor(wallet) => {
+                                wallet.signer().sign_message(&message).await?
+                            }
+                        }
+                    }
                 };
                 println!("Signature: 0x{}", sig);
             }
@@ -6,3 +6,4 @@
 //! [`foundry_config::Config`].
 
 pub mod find_block;
+pub mod run;
@@ -0,0 +1,146 @@
+//! cast run subcommand
+
+use crate::{cmd::Cmd, utils};
+use clap::Parser;
+use ethers::{
+    prelude::Middleware,
+    providers::Provider,
+    types::{Transaction, H256, U256},
+};
+use eyre::Result;
+use forge::{
+    executor::{builder::Backend, opts::EvmOpts, ExecutorBuilder},
+    trace::CallTraceDecoder,
+};
+use foundry_config::Config;
+use foundry_utils::RuntimeOrHandle;
+use revm::{CreateScheme, TransactTo, TxEnv};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+use ui::{TUIExitReason, Tui, Ui};
+
+#[derive(Debug, Clone, Parser)]
+pub struct RunArgs {
+    #[clap(help = "The transaction hash")]
+    tx: String,
+    #[clap(long, env = "ETH_RPC_URL")]
+    rpc_url: String,
+    #[clap(long, short, help = "Open the transaction in the debugger")]
+    debug: bool,
+}
+
+impl Cmd for RunArgs {
+    type Output = ();
+
+    fn run(self) -> Result<Self::Output> {
+        let rt = RuntimeOrHandle::new();
+        let provider = Provider::try_from(self.rpc_url.as_str())?;
+
+        let tx_hash = H256::from_str(&self.tx)?;
+        let tx = rt
+            .block_on(provider.get_transaction(tx_hash))?
+            .ok_or_else(|| eyre::eyre!("transaction {:?} not found", tx_hash))?;
+        let block_number = tx
+            .block_number
+            .ok_or_else(|| eyre::eyre!("transaction {:?} is not mined yet", tx_hash))?
+            .as_u64();
+        let block = rt
+            .block_on(provider.get_block_with_txs(block_number))?
+            .ok_or_else(|| eyre::eyre!("block {} not found", block_number))?;
+        let chain_id = rt.block_on(provider.get_chainid())?.as_u64();
+
+        // The state is forked at the parent block, and the transactions are executed in the
+        // environment of their own block
+        let mut evm_opts = EvmOpts {
+            fork_url: Some(self.rpc_url.clone()),
+            fork_block_number: Some(block_number.saturating_sub(1)),
+            ..Default::default()
+        };
+        evm_opts.env.chain_id = Some(chain_id);
+
+        let mut env = evm_opts.evm_env();
+        env.block.number = block_number.into();
+        env.block.timestamp = block.timestamp;
+        env.block.coinbase = block.author;
+        env.block.difficulty = block.difficulty;
+        env.block.basefee = block.base_fee_per_gas.unwrap_or_default();
+        env.block.gas_limit = block.gas_limit;
+
+        let db =
+            Backend::new(utils::get_fork(&evm_opts, &Config::load().rpc_storage_caching), &env);
+        let mut builder =
+            ExecutorBuilder::new().with_config(env).with_gas_limit(block.gas_limit).with_tracing();
+        if self.debug {
+            builder = builder.with_debugger();
+        }
+        let mut executor = builder.build(db);
+
+        // Replay the transactions that precede the target transaction in its block
+        for preceding in block.transactions.iter().take_while(|preceding| preceding.hash != tx.hash)
+        {
+            executor.transact_committing(tx_env(preceding)?)?;
+        }
+
+        let result = executor.transact(tx_env(&tx)?)?;
+        let mut decoder = CallTraceDecoder::new_with_labels(result.labels)
+            .with_signatures(Arc::new(utils::signature_db()));
+        let mut trace = result.traces.expect("we should have collected traces");
+        if let Some(etherscan_identifier) = utils::get_etherscan_identifier(&evm_opts) {
+            decoder.identify(&trace, &etherscan_identifier);
+        }
+
+        if self.debug {
+            let debug = result.debug.expect("we should have collected debug info");
+            let flattened = debug.flatten(0);
+            let external_contracts =
+                utils::etherscan_sources(&evm_opts, flattened.iter().map(|(address, ..)| *address));
+            let tui = Tui::new(
+                flattened,
+                0,
+                decoder.contracts,
+                BTreeMap::new(),
+                BTreeMap::new(),
+                external_contracts,
+            )?;
+            match tui.start().expect("Failed to start tui") {
+                TUIExitReason::CharExit => return Ok(()),
+            }
+        }
+
+        decoder.decode(&mut trace);
+        println!("Traces:");
+        println!("{}", trace);
+        println!();
+
+        if result.reverted {
+            println!("Transaction failed.");
+        } else {
+            println!("Transaction successfully executed.");
+        }
+        println!("Gas used: {}", result.gas);
+        Ok(())
+    }
+}
+
+/// Builds the environment of a mined transaction
+fn tx_env(tx: &Transaction) -> Result<TxEnv> {
+    if tx.gas > U256::from(u64::MAX) {
+        eyre::bail!("gas limit {} of transaction {:?} is too large", tx.gas, tx.hash)
+    }
+    if tx.chain_id.map_or(false, |id| id > U256::from(u64::MAX)) {
+        eyre::bail!("chain id of transaction {:?} is too large", tx.hash)
+    }
+    Ok(TxEnv {
+        caller: tx.from,
+        transact_to: tx
+            .to
+            .map(TransactTo::Call)
+            .unwrap_or(TransactTo::Create(CreateScheme::Create)),
+        data: tx.input.0.clone(),
+        value: tx.value,
+        gas_limit: tx.gas.as_u64(),
+        gas_price: tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default(),
+        gas_priority_fee: tx.max_priority_fee_per_gas,
+        chain_id: tx.chain_id.map(|id| id.as_u64()),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,321 @@
+//! Sending the transactions of a script and keeping track of their receipts
+
+use ansi_term::Colour;
+use cast::TxBuilder;
+use ethers::{
+    prelude::{LocalWallet, Middleware, PendingTransaction, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Chain, NameOrAddress, TransactionReceipt,
+        TxHash, U256, U64,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A transaction of a script, and what is known about it on chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastedTransaction {
+    /// The transaction as it was recorded by the script
+    pub transaction: TypedTransaction,
+    /// The hash of the transaction, once it was sent
+    pub hash: Option<TxHash>,
+    /// The nonce of the transaction
+    pub nonce: Option<U256>,
+    /// The address of the contract deployed by the transaction
+    pub contract_address: Option<Address>,
+    /// The status of the receipt, once the transaction was mined
+    pub status: Option<U64>,
+    /// The gas used by the transaction, once it was mined
+    pub gas_used: Option<U256>,
+}
+
+impl BroadcastedTransaction {
+    pub fn new(transaction: TypedTransaction) -> Self {
+        Self {
+            nonce: transaction.nonce().copied(),
+            transaction,
+            hash: None,
+            contract_address: None,
+            status: None,
+            gas_used: None,
+        }
+    }
+
+    /// Whether the transaction was mined
+    pub fn is_confirmed(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// Whether the transaction was mined and succeeded
+    pub fn is_success(&self) -> bool {
+        self.status == Some(U64::one())
+    }
+
+    fn set_receipt(&mut self, receipt: TransactionReceipt) {
+        self.hash = Some(receipt.transaction_hash);
+        self.contract_address = receipt.contract_address;
+        self.status = receipt.status;
+        self.gas_used = receipt.gas_used;
+    }
+}
+
+/// The transactions of a script run on a single chain.
+///
+/// The sequence is written to `broadcast/<script>/<chain id>/run-latest.json` in the project root
+/// whenever a transaction is sent or mined, so that a deployment that stopped halfway can be
+/// resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptSequence {
+    pub chain: u64,
+    pub timestamp: u64,
+    pub transactions: Vec<BroadcastedTransaction>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl ScriptSequence {
+    pub fn new(
+        transactions: impl IntoIterator<Item = TypedTransaction>,
+        root: &Path,
+        script: &Path,
+        chain: u64,
+    ) -> Self {
+        Self {
+            chain,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("time went backwards")
+                .as_secs(),
+            transactions: transactions.into_iter().map(BroadcastedTransaction::new).collect(),
+            path: Self::get_path(root, script, chain),
+        }
+    }
+
+    /// Loads the sequence of the latest run of the script on the chain
+    pub fn load(root: &Path, script: &Path, chain: u64) -> eyre::Result<Self> {
+        let path = Self::get_path(root, script, chain);
+        let file = std::fs::File::open(&path).map_err(|err| {
+            eyre::eyre!("Could not read the transactions of a previous run at {:?}: {}", path, err)
+        })?;
+        let mut sequence: Self = serde_json::from_reader(file)?;
+        sequence.path = path;
+        Ok(sequence)
+    }
+
+    /// Writes the sequence to its file
+    pub fn save(&self) -> eyre::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the path of the file of a script's sequence on a chain
+    pub fn get_path(root: &Path, script: &Path, chain: u64) -> PathBuf {
+        root.join("broadcast")
+            .join(script.file_name().unwrap_or_else(|| script.as_os_str()))
+            .join(chain.to_string())
+            .join("run-latest.json")
+    }
+}
+
+/// Sends the transactions of the sequence that were not mined yet, one after the other, and
+/// records their receipts.
+///
+/// Transactions that were sent before but not mined yet are waited for, unless the node does not
+/// know them anymore, in which case they were dropped and are sent again. Transactions from the
+/// address of one of the `script_wallets` are signed with it, all others by the signer of the
+/// `provider`.
+pub async fn send_transactions<M: Middleware>(
+    provider: &M,
+    sequence: &mut ScriptSequence,
+    legacy: bool,
+    script_wallets: &[LocalWallet],
+) -> eyre::Result<()>
+where
+    M::Error: 'static,
+{
+    let unconfirmed = sequence.transactions.iter().filter(|tx| !tx.is_confirmed()).count();
+    if unconfirmed == 0 {
+        println!("All transactions were mined already.");
+        return Ok(())
+    }
+    println!("Sending {} transaction(s)...", unconfirmed);
+    sequence.save()?;
+
+    for idx in 0..sequence.transactions.len() {
+        let tx = &mut sequence.transactions[idx];
+        if tx.is_confirmed() {
+            if !tx.is_success() {
+                eyre::bail!("Transaction {:?} failed", tx.hash.unwrap_or_default())
+            }
+            continue
+        }
+
+        // The transaction might have been mined after it was sent
+        if let Some(hash) = tx.hash {
+            if let Some(receipt) = provider.get_transaction_receipt(hash).await? {
+                tx.set_receipt(receipt);
+                sequence.save()?;
+                print_receipt(&sequence.transactions[idx])?;
+                continue
+            }
+        }
+
+        let pending = match tx.hash {
+            // Sending the transaction again would fail, since its nonce is already used
+            Some(hash) if provider.get_transaction(hash).await?.is_some() => {
+                println!("Waiting for pending transaction {:?}...", hash);
+                PendingTransaction::new(hash, provider.provider())
+            }
+            _ => {
+                let pending =
+                    send_transaction(provider, tx, sequence.chain, legacy, script_wallets).await?;
+                tx.hash = Some(*pending);
+                sequence.save()?;
+                pending
+            }
+        };
+
+        let hash = *pending;
+        let receipt = pending
+            .await?
+            .ok_or_else(|| eyre::eyre!("Transaction {:?} was dropped from the mempool", hash))?;
+        sequence.transactions[idx].set_receipt(receipt);
+        sequence.save()?;
+        print_receipt(&sequence.transactions[idx])?;
+    }
+
+    println!("{}", Colour::Green.paint("All transactions were mined successfully."));
+    println!("Transactions saved to: {:?}", sequence.path);
+    Ok(())
+}
+
+/// Signs and sends a transaction, building it again from the recorded one with the current gas
+/// prices of the chain, unless gas prices were recorded
+async fn send_transaction<'a, M: Middleware>(
+    provider: &'a M,
+    tx: &BroadcastedTransaction,
+    chain_id: u64,
+    legacy: bool,
+    script_wallets: &[LocalWallet],
+) -> eyre::Result<PendingTransaction<'a, M::Provider>>
+where
+    M::Error: 'static,
+{
+    // The chain is only used to pick the type of the transaction, so unknown chains are treated
+    // like mainnet
+    let chain = Chain::try_from(chain_id).unwrap_or(Chain::Mainnet);
+
+    let from = *tx.transaction.from().expect("broadcast transactions have a sender");
+    let mut builder = match tx.transaction.to() {
+        Some(NameOrAddress::Address(to)) => {
+            TxBuilder::new(provider, from, *to, chain, legacy).await?
+        }
+        Some(NameOrAddress::Name(name)) => {
+            TxBuilder::new(provider, from, name.as_str(), chain, legacy).await?
+        }
+        None => TxBuilder::new_create(provider, from, chain, legacy).await?,
+    };
+    // The recorded gas limit is kept, so that the gas is not estimated again with a gas price
+    // that may be below the base fee
+    builder
+        .value(tx.transaction.value().copied())
+        .nonce(tx.nonce)
+        .gas(tx.transaction.gas().copied())
+        .gas_price(tx.transaction.gas_price())
+        .data(tx.transaction.data().cloned());
+    let (mut typed_tx, _) = builder.build();
+    if let (TypedTransaction::Eip1559(built), TypedTransaction::Eip1559(recorded)) =
+        (&mut typed_tx, &tx.transaction)
+    {
+        built.max_priority_fee_per_gas = recorded.max_priority_fee_per_gas;
+    }
+    typed_tx.set_chain_id(chain_id);
+    provider.fill_transaction(&mut typed_tx, None).await?;
+
+    Ok(match script_wallets.iter().find(|wallet| wallet.address() == from) {
+        Some(wallet) => {
+            let signature = wallet.sign_transaction_sync(&typed_tx);
+            provider.send_raw_transaction(typed_tx.rlp_signed(&signature)).await?
+        }
+        None => provider.send_transaction(typed_tx, None).await?,
+    })
+}
+
+/// Prints the receipt of a mined transaction, or fails if the transaction failed
+fn print_receipt(tx: &BroadcastedTransaction) -> eyre::Result<()> {
+    let hash = tx.hash.unwrap_or_default();
+    println!("Transaction: {:?}", hash);
+    if let Some(address) = tx.contract_address {
+        println!("Contract Address: {:?}", address);
+    }
+    println!("Gas used: {}", tx.gas_used.unwrap_or_default());
+
+    if !tx.is_success() {
+        eyre::bail!("Transaction {:?} failed", hash)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{providers::Provider, types::TransactionRequest};
+
+    #[test]
+    fn can_save_and_load_sequences() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let script = Path::new("script/Deploy.s.sol");
+        let tx: TypedTransaction =
+            TransactionRequest::new().from(Address::random()).nonce(3).data(vec![1, 2]).into();
+
+        let mut sequence = ScriptSequence::new(vec![tx.clone(), tx], root, script, 1);
+        assert_eq!(sequence.path, root.join("broadcast/Deploy.s.sol/1/run-latest.json"));
+        assert_eq!(sequence.transactions[0].nonce, Some(3.into()));
+
+        sequence.transactions[0].hash = Some(TxHash::random());
+        sequence.transactions[0].status = Some(U64::one());
+        sequence.save().unwrap();
+
+        let loaded = ScriptSequence::load(root, script, 1).unwrap();
+        assert_eq!(loaded.path, sequence.path);
+        assert!(loaded.transactions[0].is_success());
+        assert_eq!(loaded.transactions[0].hash, sequence.transactions[0].hash);
+        assert!(!loaded.transactions[1].is_confirmed());
+        assert!(ScriptSequence::load(root, script, 2).is_err());
+    }
+
+    #[test]
+    fn keeps_the_recorded_gas_limit() {
+        let (provider, mock) = Provider::mocked();
+        let hash = TxHash::random();
+        mock.push(hash).unwrap();
+
+        // A creation with a gas price below the base fee, which would fail if the gas was
+        // estimated again
+        let recorded: TypedTransaction = TransactionRequest::new()
+            .from(Address::random())
+            .nonce(3)
+            .gas(100_000)
+            .gas_price(1)
+            .data(vec![1, 2])
+            .into();
+        let tx = BroadcastedTransaction::new(recorded.clone());
+
+        let pending =
+            crate::utils::block_on(send_transaction(&provider, &tx, 1, true, &[])).unwrap();
+        assert_eq!(*pending, hash);
+
+        let mut sent = recorded;
+        sent.set_chain_id(1);
+        mock.assert_request("eth_sendTransaction", [sent]).unwrap();
+    }
+}
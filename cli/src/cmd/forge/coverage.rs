@@ -0,0 +1,117 @@
+//! Coverage command
+
+use crate::{
+    cmd::{
+        forge::test::{self, TestOutcome},
+        Cmd,
+    },
+    compile::ProjectCompiler,
+    utils,
+};
+use clap::{Parser, ValueHint};
+use ethers::prelude::artifacts::CompactContractBytecode;
+use eyre::Context;
+use forge::{
+    coverage::{CoverageReport, HitMaps},
+    executor::inspector::CheatsConfig,
+    MultiContractRunnerBuilder,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Parser)]
+pub struct CoverageArgs {
+    /// All test arguments are supported
+    #[clap(flatten)]
+    test: test::TestArgs,
+
+    #[clap(
+        help = "Output file for the LCOV report.",
+        default_value = "lcov.info",
+        long,
+        value_hint = ValueHint::FilePath,
+        value_name = "REPORT_FILE"
+    )]
+    report_file: PathBuf,
+}
+
+impl Cmd for CoverageArgs {
+    type Output = TestOutcome;
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        let (config, evm_opts) = self.test.config_and_evm_opts()?;
+
+        // Coverage is mapped back to the sources using the source maps of every contract, which
+        // cached artifacts may not have, so the project is always compiled from scratch
+        let project = config.ephemeral_no_artifacts_project()?;
+        let output = ProjectCompiler::default().compile(&project)?;
+        let root = project.paths.root.clone();
+
+        let evm_spec = utils::evm_spec(&config.evm_version);
+        let mut runner = MultiContractRunnerBuilder::default()
+            .fuzzer(test::fuzzer(&config))
+            .initial_balance(evm_opts.initial_balance)
+            .evm_spec(evm_spec)
+            .sender(evm_opts.sender)
+            .with_fork(utils::get_fork(&evm_opts, &config.rpc_storage_caching))
+            .with_fork_cache(utils::get_fork_cache(&evm_opts, &config.rpc_storage_caching))
+            .with_fuzz_corpus_dir(Some(config.fuzz_corpus_dir.clone()))
+            .invariant_depth(config.invariant_depth as usize)
+            .with_cheats_config(CheatsConfig::new(&config, &evm_opts))
+            .set_coverage(true)
+            .build(&root, output.clone(), evm_opts)?;
+
+        let results = runner.test(self.test.filter(), None, true)?;
+        // The setup of a suite is only counted once, however many tests it has
+        let hit_maps = results
+            .values()
+            .flat_map(|suite| {
+                std::iter::once(&suite.coverage)
+                    .chain(suite.test_results.values().map(|result| &result.coverage))
+            })
+            .fold(None, |hit_maps, coverage| HitMaps::merge_opt(hit_maps, coverage.clone()))
+            .unwrap_or_default();
+
+        // Only the project's own contracts are reported, not its tests or dependencies
+        let (artifacts, sources) = output.into_artifacts_with_sources();
+        let sources = sources
+            .into_ids()
+            .map(|(id, path)| (id, PathBuf::from(path)))
+            .filter(|(_, path)| is_source(&project.paths.sources, &root, path))
+            .map(|(id, path)| {
+                let content = fs::read_to_string(root.join(&path))
+                    .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+                let path = path.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(path);
+                Ok((id, (path, content)))
+            })
+            .collect::<eyre::Result<BTreeMap<_, _>>>()?;
+
+        let mut report = CoverageReport::new(sources);
+        for (id, artifact) in artifacts {
+            if is_source(&project.paths.sources, &root, &id.source) {
+                let contract: CompactContractBytecode = artifact.into();
+                report.add_contract(&id, contract.into(), &hit_maps);
+            }
+        }
+
+        let outcome = TestOutcome::new(results, self.test.allow_failure());
+        println!("{}", outcome.summary());
+        println!("{}", report);
+
+        let file = fs::File::create(&self.report_file)
+            .wrap_err_with(|| format!("Failed to create {}", self.report_file.display()))?;
+        report.write_lcov(BufWriter::new(file))?;
+        println!("Wrote LCOV report to {}", self.report_file.display());
+
+        Ok(outcome)
+    }
+}
+
+/// Whether `path` is a file in the project's source directory
+fn is_source(sources: &Path, root: &Path, path: &Path) -> bool {
+    root.join(path).starts_with(sources)
+}
@@ -1,14 +1,20 @@
 //! Create command
 
 use crate::{
-    cmd::{forge::build::BuildArgs, Cmd},
+    cmd::{
+        forge::{
+            broadcast::{self, ScriptSequence},
+            build::BuildArgs,
+        },
+        Cmd,
+    },
     opts::{EthereumOpts, WalletType},
     utils::parse_u256,
 };
 use ethers::{
     abi::{Abi, Constructor, Token},
     prelude::{artifacts::BytecodeObject, ContractFactory, Http, Middleware, Provider},
-    types::{transaction::eip2718::TypedTransaction, Chain, U256},
+    types::{transaction::eip2718::TypedTransaction, Address, Chain, U256},
 };
 
 use eyre::Result;
@@ -17,7 +23,10 @@ use std::fs;
 
 use crate::{compile, opts::forge::ContractInfo};
 use clap::{Parser, ValueHint};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 #[derive(Debug, Clone, Parser)]
 pub struct CreateArgs {
@@ -62,6 +71,13 @@ pub struct CreateArgs {
 
     #[clap(long = "value", help = "value to send with the contract creation tx", env = "ETH_VALUE", parse(try_from_str = parse_u256))]
     value: Option<U256>,
+
+    #[clap(
+        long,
+        help = "wait for the creation tx of the previous run on the chain, or send it again if it was dropped, instead of creating a new one",
+        long_help = "wait for the creation tx of the previous run on the chain, or send it again if it was dropped, instead of creating a new one. the tx and its receipt are saved to `broadcast/<contract name>/<chain id>/run-latest.json`"
+    )]
+    resume: bool,
 }
 
 impl Cmd for CreateArgs {
@@ -83,6 +99,7 @@ impl Cmd for CreateArgs {
 
         // Add arguments to constructor
         let provider = Provider::<Http>::try_from(self.eth.rpc_url()?)?;
+        let root = project.root().clone();
         let params = match abi.constructor {
             Some(ref v) => {
                 let constructor_args =
@@ -106,13 +123,13 @@ impl Cmd for CreateArgs {
         if let Some(signer) = rt.block_on(self.eth.signer_with(chain_id, provider))? {
             match signer {
                 WalletType::Ledger(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.deploy(&root, abi, bin, params, signer))?;
                 }
                 WalletType::Local(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.deploy(&root, abi, bin, params, signer))?;
                 }
                 WalletType::Trezor(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.deploy(&root, abi, bin, params, signer))?;
                 }
             }
         } else {
@@ -124,16 +141,32 @@ impl Cmd for CreateArgs {
 }
 
 impl CreateArgs {
+    /// Deploys the contract, saving the creation tx and its receipt like the transactions of a
+    /// script, so that a deployment that was not mined can be resumed
     async fn deploy<M: Middleware + 'static>(
         self,
+        root: &Path,
         abi: Abi,
         bin: BytecodeObject,
         args: Vec<Token>,
         provider: M,
-    ) -> Result<()> {
+    ) -> Result<()>
+    where
+        M::Error: 'static,
+    {
         let chain = provider.get_chainid().await?.as_u64();
         let deployer_address =
             provider.default_sender().expect("no sender address set for provider");
+        let is_legacy =
+            self.legacy || Chain::try_from(chain).map(|x| Chain::is_legacy(&x)).unwrap_or_default();
+        let contract = Path::new(&self.contract.name);
+
+        if self.resume {
+            let mut sequence = ScriptSequence::load(root, contract, chain)?;
+            broadcast::send_transactions(&provider, &mut sequence, is_legacy, &[]).await?;
+            return print_deployment(deployer_address, &sequence)
+        }
+
         let bin = bin.into_bytes().unwrap_or_else(|| {
             panic!("no bytecode found in bin object for {}", self.contract.name)
         });
@@ -141,8 +174,6 @@ impl CreateArgs {
         let factory = ContractFactory::new(abi, bin, provider.clone());
 
         let deployer = factory.deploy_tokens(args)?;
-        let is_legacy =
-            self.legacy || Chain::try_from(chain).map(|x| Chain::is_legacy(&x)).unwrap_or_default();
         let mut deployer = if is_legacy { deployer.legacy() } else { deployer };
 
         // fill tx first because if you target a lower gas than current base, eth_estimateGas
@@ -174,13 +205,13 @@ impl CreateArgs {
             deployer.tx.set_value(value);
         }
 
-        let (deployed_contract, receipt) = deployer.send_with_receipt().await?;
+        // the nonce is saved so that the tx can be sent again with the same nonce
+        deployer.tx.set_from(deployer_address);
+        deployer.tx.set_nonce(provider.get_transaction_count(deployer_address, None).await?);
 
-        println!("Deployer: {:?}", deployer_address);
-        println!("Deployed to: {:?}", deployed_contract.address());
-        println!("Transaction hash: {:?}", receipt.transaction_hash);
-
-        Ok(())
+        let mut sequence = ScriptSequence::new(vec![deployer.tx], root, contract, chain);
+        broadcast::send_transactions(&*provider, &mut sequence, is_legacy, &[]).await?;
+        print_deployment(deployer_address, &sequence)
     }
 
     fn parse_constructor_args(
@@ -198,3 +229,13 @@ impl CreateArgs {
         parse_tokens(params, true)
     }
 }
+
+/// Prints the address of the contract deployed by the creation tx of the sequence
+fn print_deployment(deployer: Address, sequence: &ScriptSequence) -> Result<()> {
+    let tx =
+        sequence.transactions.first().ok_or_else(|| eyre::eyre!("no creation tx was saved"))?;
+    println!("Deployer: {:?}", deployer);
+    println!("Deployed to: {:?}", tx.contract_address.unwrap_or_default());
+    println!("Transaction hash: {:?}", tx.hash.unwrap_or_default());
+    Ok(())
+}
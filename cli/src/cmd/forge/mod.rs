@@ -38,8 +38,10 @@
 //! ```
 
 pub mod bind;
+pub mod broadcast;
 pub mod build;
 pub mod config;
+pub mod coverage;
 pub mod create;
 pub mod flatten;
 pub mod fmt;
@@ -50,6 +52,7 @@ pub mod remappings;
 pub mod run;
 pub mod snapshot;
 pub mod test;
+pub mod test_report;
 pub mod tree;
 pub mod verify;
 pub mod watch;
@@ -1,33 +1,47 @@
 use crate::{
-    cmd::{forge::build::BuildArgs, Cmd},
+    cmd::{
+        forge::{
+            broadcast::{self, ScriptSequence},
+            build::BuildArgs,
+        },
+        Cmd,
+    },
     compile,
-    opts::evm::EvmArgs,
+    opts::{evm::EvmArgs, Wallet, WalletType},
     utils,
 };
 use ansi_term::Colour;
 use clap::{Parser, ValueHint};
 use ethers::{
     abi::{Abi, RawLog},
-    prelude::ArtifactId,
+    prelude::{ArtifactId, LocalWallet, Middleware, Signer},
+    providers::{Http, Provider},
     solc::{
         artifacts::{CompactContractBytecode, ContractBytecode, ContractBytecodeSome},
         Project,
     },
-    types::{Address, Bytes, U256},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
 };
 use forge::{
     debug::DebugArena,
     decode::decode_console_logs,
     executor::{
-        builder::Backend, opts::EvmOpts, CallResult, DatabaseRef, DeployResult, EvmError, Executor,
-        ExecutorBuilder, RawCallResult,
+        builder::Backend, inspector::CheatsConfig, opts::EvmOpts, CallResult, DatabaseRef,
+        DeployResult, EvmError, Executor, ExecutorBuilder, RawCallResult,
+    },
+    trace::{
+        identifier::LocalTraceIdentifier, CallTraceArena, CallTraceDecoder, TraceFormat, TraceKind,
     },
-    trace::{identifier::LocalTraceIdentifier, CallTraceArena, CallTraceDecoder, TraceKind},
     CALLER,
 };
 use foundry_config::{figment::Figment, Config};
 use foundry_utils::{encode_args, IntoFunction, PostLinkInput};
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::TryFrom,
+    path::PathBuf,
+    sync::Arc,
+};
 use ui::{TUIExitReason, Tui, Ui};
 
 // Loads project's figment and merges the build cli arguments into it
@@ -46,7 +60,7 @@ pub struct RunArgs {
     pub args: Vec<String>,
 
     /// The name of the contract you want to run.
-    #[clap(long, short)]
+    #[clap(long, short, visible_alias = "tc")]
     pub target_contract: Option<String>,
 
     /// The signature of the function you want to call in the contract, or raw calldata.
@@ -57,11 +71,50 @@ pub struct RunArgs {
     #[clap(long)]
     pub debug: bool,
 
+    /// Sign the transactions recorded by the broadcast cheatcodes and send them to the RPC.
+    ///
+    /// The transactions are sent from the account of the wallet, which is also the default sender
+    /// of `vm.broadcast()` and `vm.startBroadcast()`. Transactions broadcast from the address of a
+    /// key remembered with `vm.rememberKey()` are signed with that key instead.
+    #[clap(long, requires = "fork-url")]
+    pub broadcast: bool,
+
+    /// Resume the previous broadcast of the script without running it again, waiting for the
+    /// transactions that are pending and sending those that were not sent or were dropped.
+    ///
+    /// The transactions of each broadcast are saved to
+    /// `broadcast/<script file>/<chain id>/run-latest.json`, along with their receipts. The keys
+    /// remembered by the script are not saved, so only the transactions of the wallet can be
+    /// resumed, and resuming fails if any other transaction was not mined yet.
+    #[clap(long, requires = "fork-url")]
+    pub resume: bool,
+
+    /// Use legacy transactions instead of EIP1559 ones.
+    ///
+    /// This is automatically enabled for common networks without EIP1559.
+    #[clap(long)]
+    pub legacy: bool,
+
+    /// Export the traces of the script to this directory, in the format given by
+    /// `--trace-format`.
+    #[clap(long, value_name = "DIR")]
+    pub export_traces: Option<PathBuf>,
+
+    /// The format of the exported traces.
+    ///
+    /// Supported formats are `json` (a tree of the decoded calls) and `folded` (the gas used by
+    /// each call path as folded stacks, for flamegraphs).
+    #[clap(long, value_name = "FORMAT", default_value = "json", requires = "export-traces")]
+    pub trace_format: TraceFormat,
+
     #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
     pub opts: BuildArgs,
 
     #[clap(flatten, next_help_heading = "EVM OPTIONS")]
     pub evm_opts: EvmArgs,
+
+    #[clap(flatten, next_help_heading = "WALLET OPTIONS")]
+    pub wallet: Wallet,
 }
 
 impl Cmd for RunArgs {
@@ -72,6 +125,29 @@ impl Cmd for RunArgs {
         let verbosity = evm_opts.verbosity;
         let config = Config::from_provider(figment).sanitized();
 
+        if self.resume {
+            let (signer, chain) = self.signer(&evm_opts)?;
+            let mut sequence = ScriptSequence::load(&config.__root.0, &self.path, chain)?;
+
+            // The keys remembered by the script are not saved, so their transactions can't be
+            // signed again
+            let address = signer_address(&signer);
+            if let Some(from) = sequence
+                .transactions
+                .iter()
+                .filter(|tx| !tx.is_confirmed())
+                .filter_map(|tx| tx.transaction.from())
+                .find(|from| **from != address)
+            {
+                eyre::bail!(
+                    "Can't resume the transactions sent from {:?}, only those of the wallet ({:?}).",
+                    from,
+                    address
+                )
+            }
+            return self.send_transactions(signer, &mut sequence, &[])
+        }
+
         let BuildOutput {
             project,
             contract,
@@ -80,6 +156,15 @@ impl Cmd for RunArgs {
             predeploy_libraries,
         } = self.build(&config, &evm_opts)?;
 
+        let signer = if self.broadcast {
+            if !predeploy_libraries.is_empty() {
+                eyre::bail!("Broadcasting scripts that link libraries is not supported yet.")
+            }
+            Some(self.signer(&evm_opts)?)
+        } else {
+            None
+        };
+
         let known_contracts = highlevel_known_contracts
             .iter()
             .map(|(id, c)| {
@@ -103,12 +188,13 @@ impl Cmd for RunArgs {
         let db = Backend::new(utils::get_fork(&evm_opts, &config.rpc_storage_caching), &env);
 
         let mut builder = ExecutorBuilder::new()
-            .with_cheatcodes(evm_opts.ffi)
+            .with_cheatcodes(CheatsConfig::new(&config, &evm_opts))
             .with_config(env)
             .with_spec(crate::utils::evm_spec(&config.evm_version))
-            .with_gas_limit(evm_opts.gas_limit());
+            .with_gas_limit(evm_opts.gas_limit())
+            .with_fork_cache(utils::get_fork_cache(&evm_opts, &config.rpc_storage_caching));
 
-        if verbosity >= 3 {
+        if verbosity >= 3 || self.export_traces.is_some() {
             builder = builder.with_tracing();
         }
         if self.debug {
@@ -121,8 +207,21 @@ impl Cmd for RunArgs {
             let (address, mut result) =
                 runner.setup(&predeploy_libraries, bytecode, needs_setup)?;
 
+            // The script is called by the account of the wallet, so that it is the sender of the
+            // broadcast transactions by default
+            if let Some((signer, _)) = &signer {
+                runner.sender = signer_address(signer);
+            }
+
             let RunResult {
-                success, gas, logs, traces, debug: run_debug, labeled_addresses, ..
+                success,
+                gas,
+                logs,
+                traces,
+                debug: run_debug,
+                labeled_addresses,
+                transactions,
+                script_wallets,
             } = runner.run(
                 address,
                 if let Some(calldata) = self.sig.strip_prefix("0x") {
@@ -139,16 +238,32 @@ impl Cmd for RunArgs {
             result.traces.extend(traces);
             result.debug = run_debug;
             result.labeled_addresses.extend(labeled_addresses);
+            result.transactions = transactions;
+            result.script_wallets = script_wallets;
 
             result
         };
 
         // Identify addresses in each trace
         let local_identifier = LocalTraceIdentifier::new(&known_contracts);
-        let mut decoder = CallTraceDecoder::new_with_labels(result.labeled_addresses.clone());
+        let mut decoder = CallTraceDecoder::new_with_labels(result.labeled_addresses.clone())
+            .with_signatures(Arc::new(utils::signature_db()));
         for (_, trace) in &mut result.traces {
             decoder.identify(trace, &local_identifier);
         }
+        if let Some(etherscan_identifier) = utils::get_etherscan_identifier(&evm_opts) {
+            for (_, trace) in &mut result.traces {
+                decoder.identify(trace, &etherscan_identifier);
+            }
+        }
+
+        if let Some(dir) = &self.export_traces {
+            let name = self.path.file_name().unwrap_or_else(|| self.path.as_os_str());
+            for (kind, trace) in &mut result.traces {
+                decoder.decode(trace);
+                utils::export_trace(dir, &name.to_string_lossy(), kind, self.trace_format, trace)?;
+            }
+        }
 
         if self.debug {
             let source_code: BTreeMap<u32, String> = sources
@@ -170,15 +285,29 @@ impl Cmd for RunArgs {
 
             let calls: Vec<DebugArena> = result.debug.expect("we should have collected debug info");
             let flattened = calls.last().expect("we should have collected debug info").flatten(0);
+            let highlevel_known_contracts: BTreeMap<String, ContractBytecodeSome> =
+                highlevel_known_contracts
+                    .into_iter()
+                    .map(|(id, artifact)| (id.name, artifact))
+                    .collect();
+
+            // Forked contracts that are not part of the project
+            let external_contracts = utils::etherscan_sources(
+                &evm_opts,
+                flattened.iter().map(|(address, ..)| *address).filter(|address| {
+                    !decoder
+                        .contracts
+                        .get(address)
+                        .map_or(false, |name| highlevel_known_contracts.contains_key(name))
+                }),
+            );
             let tui = Tui::new(
                 flattened,
                 0,
                 decoder.contracts,
-                highlevel_known_contracts
-                    .into_iter()
-                    .map(|(id, artifact)| (id.name, artifact))
-                    .collect(),
+                highlevel_known_contracts,
                 source_code,
+                external_contracts,
             )?;
             match tui.start().expect("Failed to start tui") {
                 TUIExitReason::CharExit => return Ok(()),
@@ -223,11 +352,42 @@ impl Cmd for RunArgs {
                     println!("  {}", log);
                 }
             }
+
+            let transactions = result.transactions.unwrap_or_default();
+            match signer {
+                Some((signer, chain)) if !transactions.is_empty() => {
+                    if !result.success {
+                        eyre::bail!("The script failed, not broadcasting any transactions.")
+                    }
+
+                    println!();
+                    let mut sequence =
+                        ScriptSequence::new(transactions, &config.__root.0, &self.path, chain);
+                    self.send_transactions(signer, &mut sequence, &result.script_wallets)?;
+                }
+                None if !transactions.is_empty() => {
+                    println!();
+                    println!(
+                        "The script recorded {} transaction(s). Use --broadcast to send them.",
+                        transactions.len()
+                    );
+                }
+                _ => {}
+            }
         }
         Ok(())
     }
 }
 
+/// Returns the address of the account that signs with the wallet
+fn signer_address(signer: &WalletType) -> Address {
+    match signer {
+        WalletType::Local(signer) => signer.address(),
+        WalletType::Ledger(signer) => signer.address(),
+        WalletType::Trezor(signer) => signer.address(),
+    }
+}
+
 struct ExtraLinkingInfo<'a> {
     no_target_name: bool,
     target_fname: String,
@@ -245,6 +405,41 @@ pub struct BuildOutput {
 }
 
 impl RunArgs {
+    /// Returns the signer of the wallet, which sends transactions to the endpoint the script is
+    /// forked from, and the ID of its chain
+    fn signer(&self, evm_opts: &EvmOpts) -> eyre::Result<(WalletType, u64)> {
+        let provider = Provider::<Http>::try_from(
+            evm_opts.fork_url.as_deref().expect("broadcasting requires --fork-url"),
+        )?;
+        let chain = utils::block_on(provider.get_chainid())?;
+        let signer = utils::block_on(self.wallet.signer(chain, provider))?;
+        Ok((signer, chain.as_u64()))
+    }
+
+    /// Sends the transactions of the sequence that were not mined yet with the signer, or with the
+    /// wallets of the keys remembered by the script
+    fn send_transactions(
+        &self,
+        signer: WalletType,
+        sequence: &mut ScriptSequence,
+        script_wallets: &[LocalWallet],
+    ) -> eyre::Result<()> {
+        let legacy = self.legacy;
+        utils::block_on(async move {
+            match signer {
+                WalletType::Local(signer) => {
+                    broadcast::send_transactions(&signer, sequence, legacy, script_wallets).await
+                }
+                WalletType::Ledger(signer) => {
+                    broadcast::send_transactions(&signer, sequence, legacy, script_wallets).await
+                }
+                WalletType::Trezor(signer) => {
+                    broadcast::send_transactions(&signer, sequence, legacy, script_wallets).await
+                }
+            }
+        })
+    }
+
     /// Compiles the file with auto-detection and compiler params.
     pub fn build(&self, config: &Config, evm_opts: &EvmOpts) -> eyre::Result<BuildOutput> {
         let target_contract = dunce::canonicalize(&self.path)?;
@@ -298,7 +493,7 @@ impl RunArgs {
                 if extra.no_target_name {
                     if id.source == std::path::Path::new(&extra.target_fname) {
                         if extra.matched {
-                            eyre::bail!("Multiple contracts in the target path. Please specify the contract name with `-t ContractName`")
+                            eyre::bail!("Multiple contracts in the target path. Please specify the contract name with `--tc ContractName`")
                         }
                         *extra.dependencies = dependencies;
                         *extra.contract = contract.clone();
@@ -338,6 +533,8 @@ struct RunResult {
     pub debug: Option<Vec<DebugArena>>,
     pub gas: u64,
     pub labeled_addresses: BTreeMap<Address, String>,
+    pub transactions: Option<VecDeque<TypedTransaction>>,
+    pub script_wallets: Vec<LocalWallet>,
 }
 
 struct Runner<DB: DatabaseRef> {
@@ -423,6 +620,8 @@ impl<DB: DatabaseRef> Runner<DB> {
                             success: !reverted,
                             debug: vec![constructor_debug, debug].into_iter().collect(),
                             gas,
+                            transactions: None,
+                            script_wallets: Vec::new(),
                         },
                     )
                 }
@@ -438,14 +637,26 @@ impl<DB: DatabaseRef> Runner<DB> {
                     debug: vec![constructor_debug].into_iter().collect(),
                     gas: 0,
                     labeled_addresses: Default::default(),
+                    transactions: None,
+                    script_wallets: Vec::new(),
                 },
             )
         })
     }
 
     pub fn run(&mut self, address: Address, calldata: Bytes) -> eyre::Result<RunResult> {
-        let RawCallResult { reverted, gas, stipend, logs, traces, labels, debug, .. } =
-            self.executor.call_raw(self.sender, address, calldata.0, 0.into())?;
+        let RawCallResult {
+            reverted,
+            gas,
+            stipend,
+            logs,
+            traces,
+            labels,
+            debug,
+            transactions,
+            script_wallets,
+            ..
+        } = self.executor.call_raw(self.sender, address, calldata.0, 0.into())?;
         Ok(RunResult {
             success: !reverted,
             gas: gas.overflowing_sub(stipend).0,
@@ -453,6 +664,21 @@ impl<DB: DatabaseRef> Runner<DB> {
             traces: traces.map(|traces| vec![(TraceKind::Execution, traces)]).unwrap_or_default(),
             debug: vec![debug].into_iter().collect(),
             labeled_addresses: labels,
+            transactions,
+            script_wallets,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_target_contract_with_wallet() {
+        let args =
+            RunArgs::parse_from(["foundry-cli", "script/Deploy.sol", "-t", "Deploy", "--trezor"]);
+        assert_eq!(args.target_contract, Some("Deploy".to_string()));
+        assert!(args.wallet.trezor);
+    }
+}
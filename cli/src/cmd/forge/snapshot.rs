@@ -168,6 +168,8 @@ impl SnapshotConfig {
     fn apply(&self, outcome: TestOutcome) -> Vec<Test> {
         let mut tests = outcome
             .into_tests()
+            // Invariant tests do not track the gas of their calls
+            .filter(|test| !test.result.is_invariant())
             .filter(|test| self.is_in_gas_range(test.gas_used()))
             .collect::<Vec<_>>();
 
@@ -1,7 +1,7 @@
 //! Test command
 use crate::{
     cmd::{
-        forge::{build::BuildArgs, run::RunArgs},
+        forge::{build::BuildArgs, run::RunArgs, test_report::ReportFormat},
         Cmd,
     },
     compile::ProjectCompiler,
@@ -11,21 +11,26 @@ use crate::{
 };
 use ansi_term::Colour;
 use clap::{AppSettings, Parser};
-use ethers::solc::FileFilter;
+use ethers::solc::{artifacts::StorageLayout, FileFilter};
 use forge::{
     decode::decode_console_logs,
-    executor::opts::EvmOpts,
+    executor::{inspector::CheatsConfig, opts::EvmOpts},
+    fuzz::CounterExample,
     gas_report::GasReport,
-    trace::{identifier::LocalTraceIdentifier, CallTraceDecoder, TraceKind},
+    trace::{
+        identifier::{EtherscanIdentifier, LocalTraceIdentifier},
+        CallTraceDecoder, TraceFormat, TraceKind,
+    },
     MultiContractRunner, MultiContractRunnerBuilder, SuiteResult, TestFilter, TestKind,
 };
 use foundry_config::{figment::Figment, Config};
+use proptest::test_runner::TestRunner;
 use regex::Regex;
 use std::{
     collections::BTreeMap,
     path::{Path, PathBuf},
     str::FromStr,
-    sync::mpsc::channel,
+    sync::{mpsc::channel, Arc},
     thread,
     time::Duration,
 };
@@ -168,6 +173,25 @@ pub struct TestArgs {
     #[clap(long, short)]
     json: bool,
 
+    /// Output test results in the given format.
+    ///
+    /// Supported formats are `json`, `junit` (JUnit XML) and `tap` (Test Anything Protocol).
+    #[clap(long, value_name = "FORMAT", conflicts_with = "json")]
+    format: Option<ReportFormat>,
+
+    /// Export the traces of the tests to this directory, in the format given by `--trace-format`.
+    ///
+    /// The traces of each test contract are written to a subdirectory named after the contract.
+    #[clap(long, value_name = "DIR", conflicts_with_all = &["json", "format"])]
+    export_traces: Option<PathBuf>,
+
+    /// The format of the exported traces.
+    ///
+    /// Supported formats are `json` (a tree of the decoded calls) and `folded` (the gas used by
+    /// each call path as folded stacks, for flamegraphs).
+    #[clap(long, value_name = "FORMAT", default_value = "json", requires = "export-traces")]
+    trace_format: TraceFormat,
+
     #[clap(flatten, next_help_heading = "EVM OPTIONS")]
     evm_opts: EvmArgs,
 
@@ -186,6 +210,11 @@ impl TestArgs {
         &self.filter
     }
 
+    /// Returns whether the process should exit with code 0 even if the tests fail
+    pub fn allow_failure(&self) -> bool {
+        self.allow_failure
+    }
+
     /// Returns the currently configured [Config] and the extracted [EvmOpts] from that config
     pub fn config_and_evm_opts(&self) -> eyre::Result<(Config, EvmOpts)> {
         // merge all configs
@@ -241,7 +270,7 @@ pub struct TestOutcome {
 }
 
 impl TestOutcome {
-    fn new(results: BTreeMap<String, SuiteResult>, allow_failure: bool) -> Self {
+    pub fn new(results: BTreeMap<String, SuiteResult>, allow_failure: bool) -> Self {
         Self { results, allow_failure }
     }
 
@@ -341,17 +370,6 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
     // Merge all configs
     let (config, mut evm_opts) = args.config_and_evm_opts()?;
 
-    // Setup the fuzzer
-    // TODO: Add CLI Options to modify the persistence
-    let cfg = proptest::test_runner::Config {
-        failure_persistence: None,
-        cases: config.fuzz_runs,
-        max_local_rejects: config.fuzz_max_local_rejects,
-        max_global_rejects: config.fuzz_max_global_rejects,
-        ..Default::default()
-    };
-    let fuzzer = proptest::test_runner::TestRunner::new(cfg);
-
     // Set up the project
     let project = config.project()?;
     let compiler = ProjectCompiler::default();
@@ -363,18 +381,25 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
 
     // Determine print verbosity and executor verbosity
     let verbosity = evm_opts.verbosity;
-    if args.gas_report && evm_opts.verbosity < 3 {
+    if (args.gas_report || args.export_traces.is_some()) && evm_opts.verbosity < 3 {
         evm_opts.verbosity = 3;
     }
 
+    let etherscan_identifier = utils::get_etherscan_identifier(&evm_opts);
+    let storage_layouts = Arc::new(utils::storage_layouts(&output));
+
     // Prepare the test builder
     let evm_spec = crate::utils::evm_spec(&config.evm_version);
     let mut runner = MultiContractRunnerBuilder::default()
-        .fuzzer(fuzzer)
+        .fuzzer(fuzzer(&config))
         .initial_balance(evm_opts.initial_balance)
         .evm_spec(evm_spec)
         .sender(evm_opts.sender)
         .with_fork(utils::get_fork(&evm_opts, &config.rpc_storage_caching))
+        .with_fork_cache(utils::get_fork_cache(&evm_opts, &config.rpc_storage_caching))
+        .with_fuzz_corpus_dir(Some(config.fuzz_corpus_dir.clone()))
+        .invariant_depth(config.invariant_depth as usize)
+        .with_cheats_config(CheatsConfig::new(&config, &evm_opts))
         .build(project.paths.root, output, evm_opts)?;
 
     if args.debug.is_some() {
@@ -394,12 +419,15 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
                     // Build debugger args if this is a fuzz test
                     let sig = match test_kind {
                         TestKind::Fuzz(cases) => {
-                            if let Some(counterexample) = counterexample {
+                            if let Some(CounterExample::Single(counterexample)) = counterexample {
                                 counterexample.calldata.to_string()
                             } else {
                                 cases.cases().first().expect("no fuzz cases run").calldata.to_string()
                             }
                         },
+                        TestKind::Invariant { .. } => {
+                            eyre::bail!("Invariant tests can not be debugged")
+                        },
                         _ => sig,
                     };
 
@@ -410,8 +438,14 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
                         sig,
                         args: Vec::new(),
                         debug: true,
+                        broadcast: false,
+                        resume: false,
+                        legacy: false,
+                        export_traces: None,
+                        trace_format: TraceFormat::Json,
                         opts: args.opts,
                         evm_opts: args.evm_opts,
+                        wallet: Default::default(),
                     };
                     debugger.run()?;
 
@@ -425,34 +459,55 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
             }
     } else {
         let TestArgs { filter, .. } = args;
+        let format = if args.json { Some(ReportFormat::Json) } else { args.format };
         test(
             runner,
             verbosity,
             filter,
-            args.json,
+            format,
             args.allow_failure,
             include_fuzz_tests,
             (args.gas_report, config.gas_reports),
+            etherscan_identifier,
+            storage_layouts,
+            args.export_traces.map(|dir| (dir, args.trace_format)),
         )
     }
 }
 
+/// Sets up the fuzzer from the config
+pub fn fuzzer(config: &Config) -> TestRunner {
+    // Failures are persisted in the `fuzz_corpus_dir` instead
+    let cfg = proptest::test_runner::Config {
+        failure_persistence: None,
+        cases: config.fuzz_runs,
+        max_local_rejects: config.fuzz_max_local_rejects,
+        max_global_rejects: config.fuzz_max_global_rejects,
+        ..Default::default()
+    };
+    TestRunner::new(cfg)
+}
+
 /// Runs all the tests
 fn test(
     mut runner: MultiContractRunner,
     verbosity: u8,
     filter: Filter,
-    json: bool,
+    format: Option<ReportFormat>,
     allow_failure: bool,
     include_fuzz_tests: bool,
     (gas_reporting, gas_reports): (bool, Vec<String>),
+    etherscan_identifier: Option<EtherscanIdentifier>,
+    storage_layouts: Arc<BTreeMap<String, StorageLayout>>,
+    export_traces: Option<(PathBuf, TraceFormat)>,
 ) -> eyre::Result<TestOutcome> {
-    if json {
+    if let Some(format) = format {
         let results = runner.test(&filter, None, include_fuzz_tests)?;
-        println!("{}", serde_json::to_string(&results)?);
+        println!("{}", format.render(&results)?);
         Ok(TestOutcome::new(results, allow_failure))
     } else {
         let local_identifier = LocalTraceIdentifier::new(&runner.known_contracts);
+        let signatures = Arc::new(utils::signature_db());
         let (tx, rx) = channel::<(String, SuiteResult)>();
 
         let handle =
@@ -486,12 +541,17 @@ fn test(
                 if !result.traces.is_empty() {
                     // Identify addresses in each trace
                     let mut decoder =
-                        CallTraceDecoder::new_with_labels(result.labeled_addresses.clone());
+                        CallTraceDecoder::new_with_labels(result.labeled_addresses.clone())
+                            .with_signatures(signatures.clone())
+                            .with_storage_layouts(storage_layouts.clone());
 
                     // Decode the traces
                     let mut decoded_traces = Vec::new();
                     for (kind, trace) in &mut result.traces {
                         decoder.identify(trace, &local_identifier);
+                        if let Some(etherscan_identifier) = &etherscan_identifier {
+                            decoder.identify(trace, etherscan_identifier);
+                        }
 
                         let should_include = match kind {
                             // At verbosity level 3, we only display traces for failed tests
@@ -507,12 +567,17 @@ fn test(
                             _ => false,
                         };
 
-                        // We decode the trace if we either need to build a gas report or we need
-                        // to print it
-                        if should_include || gas_reporting {
+                        // We decode the trace if we either need to build a gas report, print it
+                        // or export it
+                        if should_include || gas_reporting || export_traces.is_some() {
                             decoder.decode(trace);
                         }
 
+                        if let Some((dir, format)) = &export_traces {
+                            let dir = dir.join(utils::get_contract_name(&contract_name));
+                            utils::export_trace(&dir, name, kind, *format, trace)?;
+                        }
+
                         if should_include {
                             decoded_traces.push(trace.to_string());
                         }
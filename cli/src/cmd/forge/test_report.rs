@@ -0,0 +1,285 @@
+//! Machine readable reports of test results
+
+use forge::{decode::decode_console_logs, SuiteResult, TestKindGas, TestResult};
+use std::{collections::BTreeMap, fmt::Write, str::FromStr, time::Duration};
+
+/// The formats test results can be printed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// The raw test results, serialized to JSON
+    Json,
+    /// A JUnit XML report
+    Junit,
+    /// A report in the Test Anything Protocol (TAP) format, version 13
+    Tap,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "junit" => Ok(ReportFormat::Junit),
+            "tap" => Ok(ReportFormat::Tap),
+            _ => Err(format!("Unrecognized format `{}`", s)),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// Renders the results of all test suites, keyed by the suite name
+    pub fn render(&self, results: &BTreeMap<String, SuiteResult>) -> eyre::Result<String> {
+        Ok(match self {
+            ReportFormat::Json => serde_json::to_string(results)?,
+            ReportFormat::Junit => junit(results),
+            ReportFormat::Tap => tap(results),
+        })
+    }
+}
+
+/// Renders the test results as a JUnit XML report.
+///
+/// Each test contract is a `<testsuite>` and each test function a `<testcase>`. The gas used by
+/// a test is reported as a property of the test case, and any decoded logs are its `system-out`.
+fn junit(results: &BTreeMap<String, SuiteResult>) -> String {
+    let tests: usize = results.values().map(|suite| suite.len()).sum();
+    let failures: usize = results.values().map(failures).sum();
+    let duration = results.values().map(|suite| suite.duration).sum();
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        r#"<testsuites name="forge" tests="{}" failures="{}" errors="0" time="{}">"#,
+        tests,
+        failures,
+        seconds(duration)
+    );
+
+    for (suite_name, suite) in results {
+        let _ = writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="0" time="{}">"#,
+            xml_escape(suite_name),
+            suite.len(),
+            failures(suite),
+            seconds(suite.duration)
+        );
+
+        for (test_name, result) in &suite.test_results {
+            let _ = writeln!(
+                out,
+                r#"    <testcase name="{}" classname="{}" time="{}">"#,
+                xml_escape(test_name),
+                xml_escape(suite_name),
+                seconds(result.duration)
+            );
+
+            out.push_str("      <properties>\n");
+            for (name, value) in gas_properties(&result.kind.gas_used()) {
+                let _ = writeln!(
+                    out,
+                    r#"        <property name="{}" value="{}"/>"#,
+                    name,
+                    xml_escape(&value)
+                );
+            }
+            out.push_str("      </properties>\n");
+
+            if !result.success {
+                let message = failure_message(result);
+                let _ = writeln!(
+                    out,
+                    r#"      <failure message="{}">{}</failure>"#,
+                    xml_escape(&message),
+                    xml_escape(&failure_details(result))
+                );
+            }
+
+            let logs = decode_console_logs(&result.logs);
+            if !logs.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "      <system-out>{}</system-out>",
+                    xml_escape(&logs.join("\n"))
+                );
+            }
+
+            out.push_str("    </testcase>\n");
+        }
+
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>");
+    out
+}
+
+/// Renders the test results in the TAP format.
+///
+/// Every test is a test point named `<suite>:<test>`. Its duration, gas, failure reason,
+/// counterexample and decoded logs are included as a YAML diagnostic block.
+fn tap(results: &BTreeMap<String, SuiteResult>) -> String {
+    let tests: usize = results.values().map(|suite| suite.len()).sum();
+
+    let mut out = String::new();
+    out.push_str("TAP version 13\n");
+    let _ = writeln!(out, "1..{}", tests);
+
+    let all_tests = results.iter().flat_map(|(suite_name, suite)| {
+        suite.test_results.iter().map(move |(test_name, result)| (suite_name, test_name, result))
+    });
+    for (idx, (suite_name, test_name, result)) in all_tests.enumerate() {
+        let status = if result.success { "ok" } else { "not ok" };
+        let _ = writeln!(out, "{} {} - {}:{}", status, idx + 1, suite_name, test_name);
+
+        // Strings are written as JSON strings, which are valid YAML scalars
+        out.push_str("  ---\n");
+        let _ = writeln!(out, "  duration_ms: {}", result.duration.as_millis());
+        for (name, value) in gas_properties(&result.kind.gas_used()) {
+            let _ = writeln!(out, "  {}: {}", name, value);
+        }
+        if !result.success {
+            let _ = writeln!(out, "  message: {}", yaml_string(&failure_message(result)));
+        }
+        if let Some(counterexample) = &result.counterexample {
+            let _ = writeln!(out, "  counterexample: {}", yaml_string(&counterexample.to_string()));
+        }
+        let logs = decode_console_logs(&result.logs);
+        if !logs.is_empty() {
+            out.push_str("  logs:\n");
+            for log in logs {
+                let _ = writeln!(out, "    - {}", yaml_string(&log));
+            }
+        }
+        out.push_str("  ...\n");
+    }
+
+    out
+}
+
+/// Returns the number of failed tests in a suite
+fn failures(suite: &SuiteResult) -> usize {
+    suite.test_results.values().filter(|result| !result.success).count()
+}
+
+/// Returns the gas used by a test as a list of named values
+fn gas_properties(gas: &TestKindGas) -> Vec<(&'static str, String)> {
+    match gas {
+        TestKindGas::Standard(gas) => vec![("gas", gas.to_string())],
+        TestKindGas::Fuzz { runs, mean, median } => vec![
+            ("runs", runs.to_string()),
+            ("mean_gas", mean.to_string()),
+            ("median_gas", median.to_string()),
+        ],
+        TestKindGas::Invariant { runs, calls, reverts } => vec![
+            ("runs", runs.to_string()),
+            ("calls", calls.to_string()),
+            ("reverts", reverts.to_string()),
+        ],
+    }
+}
+
+/// Returns a one line description of why a test failed
+fn failure_message(result: &TestResult) -> String {
+    result.reason.clone().unwrap_or_else(|| "Test failed".to_string())
+}
+
+/// Returns the failure reason of a test and its counterexample, if any
+fn failure_details(result: &TestResult) -> String {
+    let mut details = failure_message(result);
+    if let Some(counterexample) = &result.counterexample {
+        let _ = write!(details, "\nCounterexample: {}", counterexample);
+    }
+    details
+}
+
+/// Formats a duration in seconds, as used by JUnit
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// Escapes a string for use in XML attributes and text
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes a string for use as a YAML scalar
+fn yaml_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings can always be serialized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forge::TestKind;
+
+    fn results() -> BTreeMap<String, SuiteResult> {
+        let result = |success: bool, reason: Option<&str>| TestResult {
+            success,
+            reason: reason.map(str::to_string),
+            counterexample: None,
+            logs: Vec::new(),
+            kind: TestKind::Standard(1234),
+            traces: Vec::new(),
+            labeled_addresses: BTreeMap::new(),
+            coverage: None,
+            duration: Duration::from_millis(5),
+        };
+
+        BTreeMap::from([(
+            "src/Test.t.sol:Test".to_string(),
+            SuiteResult::new(
+                Duration::from_millis(10),
+                BTreeMap::from([
+                    ("testPass()".to_string(), result(true, None)),
+                    ("testFail()".to_string(), result(false, Some("a < b & \"c\""))),
+                ]),
+                None,
+            ),
+        )])
+    }
+
+    #[test]
+    fn can_parse_report_format() {
+        assert_eq!(ReportFormat::from_str("junit"), Ok(ReportFormat::Junit));
+        assert_eq!(ReportFormat::from_str("tap"), Ok(ReportFormat::Tap));
+        assert_eq!(ReportFormat::from_str("json"), Ok(ReportFormat::Json));
+        assert!(ReportFormat::from_str("xml").is_err());
+    }
+
+    #[test]
+    fn can_render_junit() {
+        let report = junit(&results());
+        assert!(report.contains(r#"<testsuites name="forge" tests="2" failures="1""#));
+        assert!(report.contains(
+            r#"<testcase name="testPass()" classname="src/Test.t.sol:Test" time="0.005">"#
+        ));
+        assert!(report.contains(r#"<property name="gas" value="1234"/>"#));
+        assert!(report.contains(r#"<failure message="a &lt; b &amp; &quot;c&quot;">"#));
+    }
+
+    #[test]
+    fn can_render_tap() {
+        let report = tap(&results());
+        assert!(report.starts_with("TAP version 13\n1..2\n"));
+        assert!(report.contains("not ok 1 - src/Test.t.sol:Test:testFail()"));
+        assert!(report.contains("ok 2 - src/Test.t.sol:Test:testPass()"));
+        assert!(report.contains(r#"  message: "a < b & \"c\"""#));
+        assert!(report.contains("  gas: 1234"));
+    }
+}
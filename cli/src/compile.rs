@@ -91,6 +91,7 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
         } else {
             // print the compiler output / warnings
             println!("{}", output);
+            cache_signatures(&output);
 
             // print any sizes or names
             if print_names {
@@ -155,6 +156,9 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
     if output.has_compiler_errors() {
         eyre::bail!(output.to_string())
     }
+    if !output.is_unchanged() {
+        cache_signatures(&output);
+    }
 
     Ok(output)
 }
@@ -167,5 +171,20 @@ pub fn compile_files(project: &Project, files: Vec<PathBuf>) -> eyre::Result<Pro
         eyre::bail!(output.to_string())
     }
     println!("{}", output);
+    cache_signatures(&output);
     Ok(output)
 }
+
+/// Adds the signatures of the compiled contracts to the signature database, so that calls, events
+/// and errors of the project can be decoded by other commands, too
+fn cache_signatures(output: &ProjectCompileOutput) {
+    let mut db = crate::utils::signature_db();
+    for (_, artifact) in output.artifacts() {
+        if let Some(abi) = &artifact.abi {
+            db.insert_abi(&abi.abi);
+        }
+    }
+    if let Err(err) = db.save() {
+        tracing::warn!(target: "forge_compile", %err, "failed to cache signatures");
+    }
+}
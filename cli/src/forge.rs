@@ -78,6 +78,7 @@ fn main() -> eyre::Result<()> {
         Subcommands::Clean { root } => {
             let config = utils::load_config_with_root(root);
             config.project()?.cleanup()?;
+            config.clean_fuzz_corpus()?;
         }
         Subcommands::Snapshot(cmd) => {
             if cmd.is_watch() {
@@ -86,6 +87,10 @@ fn main() -> eyre::Result<()> {
                 cmd.run()?;
             }
         }
+        Subcommands::Coverage(cmd) => {
+            let outcome = cmd.run()?;
+            outcome.ensure_ok()?;
+        }
         // Subcommands::Fmt(cmd) => {
         //     cmd.run()?;
         // }
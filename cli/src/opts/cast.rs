@@ -7,7 +7,10 @@ use ethers::{
 };
 
 use super::{ClapChain, EthereumOpts, Wallet};
-use crate::{cmd::cast::find_block::FindBlockArgs, utils::parse_u256};
+use crate::{
+    cmd::cast::{find_block::FindBlockArgs, run::RunArgs},
+    utils::parse_u256,
+};
 
 #[derive(Debug, Subcommand)]
 #[clap(about = "Perform Ethereum RPC calls from the comfort of your command line.")]
@@ -406,6 +409,14 @@ pub enum Subcommands {
     #[clap(name = "keccak")]
     #[clap(about = "Keccak-256 hashes arbitrary data")]
     Keccak { data: String },
+    #[clap(name = "eip712-hash")]
+    #[clap(about = "Prints the EIP-712 hash of typed data, which is the digest that is signed")]
+    Eip712Hash {
+        #[clap(
+            help = "Path to a JSON file of the typed data, as accepted by eth_signTypedData_v4"
+        )]
+        path: PathBuf,
+    },
     #[clap(name = "resolve-name")]
     #[clap(about = "Returns the address the provided ENS name resolves to")]
     ResolveName {
@@ -511,6 +522,11 @@ pub enum Subcommands {
         about = "Prints the block number closest to the provided timestamp"
     )]
     FindBlock(FindBlockArgs),
+    #[clap(
+        name = "run",
+        about = "Runs a published transaction in a local environment and prints the trace"
+    )]
+    Run(RunArgs),
     #[clap(about = "Generate shell completions script")]
     Completions {
         #[clap(arg_enum)]
@@ -559,8 +575,15 @@ pub enum WalletSubcommands {
     },
     #[clap(name = "sign", about = "Sign the message with provided private key")]
     Sign {
-        #[clap(help = "message to sign")]
-        message: String,
+        #[clap(help = "message to sign", required_unless_present = "typed-data")]
+        message: Option<String>,
+        #[clap(
+            long,
+            value_name = "FILE",
+            help = "Sign the EIP-712 typed data in the JSON file instead of a message",
+            conflicts_with = "message"
+        )]
+        typed_data: Option<PathBuf>,
         #[clap(flatten)]
         wallet: Wallet,
     },
@@ -7,6 +7,7 @@ use crate::cmd::forge::{
     bind::BindArgs,
     build::BuildArgs,
     config,
+    coverage::CoverageArgs,
     create::CreateArgs,
     flatten,
     init::InitArgs,
@@ -97,7 +98,9 @@ pub enum Subcommands {
         shell: clap_complete::Shell,
     },
 
-    #[clap(about = "Removes the build artifacts and cache directories")]
+    #[clap(
+        about = "Removes the build artifacts, cache directories and saved fuzz counterexamples"
+    )]
     Clean {
         #[clap(
             help = "The project's root path, default being the current working directory",
@@ -110,6 +113,9 @@ pub enum Subcommands {
     #[clap(about = "Creates a snapshot of each test's gas usage")]
     Snapshot(snapshot::SnapshotArgs),
 
+    #[clap(about = "Runs the tests and generates a coverage report of the project's contracts")]
+    Coverage(CoverageArgs),
+
     #[clap(about = "Shows the currently set config values")]
     Config(config::ConfigArgs),
 
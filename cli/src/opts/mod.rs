@@ -104,39 +104,7 @@ impl EthereumOpts {
         chain_id: U256,
         provider: Provider<Http>,
     ) -> eyre::Result<Option<WalletType>> {
-        if self.wallet.ledger {
-            let derivation = match &self.wallet.hd_path {
-                Some(hd_path) => LedgerHDPath::Other(hd_path.clone()),
-                None => LedgerHDPath::LedgerLive(self.wallet.mnemonic_index as usize),
-            };
-            let ledger = Ledger::new(derivation, chain_id.as_u64()).await?;
-
-            Ok(Some(WalletType::Ledger(SignerMiddleware::new(provider, ledger))))
-        } else if self.wallet.trezor {
-            let derivation = match &self.wallet.hd_path {
-                Some(hd_path) => TrezorHDPath::Other(hd_path.clone()),
-                None => TrezorHDPath::TrezorLive(self.wallet.mnemonic_index as usize),
-            };
-
-            // cached to ~/.ethers-rs/trezor/cache/trezor.session
-            let trezor = Trezor::new(derivation, chain_id.as_u64(), None).await?;
-
-            Ok(Some(WalletType::Trezor(SignerMiddleware::new(provider, trezor))))
-        } else {
-            let local = self
-                .wallet
-                .private_key()
-                .transpose()
-                .or_else(|| self.wallet.interactive().transpose())
-                .or_else(|| self.wallet.mnemonic().transpose())
-                .or_else(|| self.wallet.keystore().transpose())
-                .transpose()?
-                .ok_or_else(|| eyre::eyre!("error accessing local wallet, did you set a private key, mnemonic or keystore? Run `cast send --help` or `forge create --help` and use the corresponding CLI flag to set your key via --private-key, --mnemonic-path, --interactive, --trezor or --ledger. Alternatively, if you're using a local node with unlocked accounts, set the `ETH_FROM` environment variable to the address of the account you want to use"))?;
-
-            let local = local.with_chain_id(chain_id.as_u64());
-
-            Ok(Some(WalletType::Local(SignerMiddleware::new(provider, local))))
-        }
+        self.wallet.signer(chain_id, provider).await.map(Some)
     }
 
     pub fn rpc_url(&self) -> Result<&str> {
@@ -170,7 +138,7 @@ pub enum WalletType {
     Trezor(SignerMiddleware<Provider<Http>, Trezor>),
 }
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Debug, Clone, Default)]
 #[cfg_attr(not(doc), allow(missing_docs))]
 #[cfg_attr(
     doc,
@@ -203,7 +171,8 @@ pub struct Wallet {
     #[clap(short, long = "ledger", help = "Use your Ledger hardware wallet")]
     pub ledger: bool,
 
-    #[clap(short, long = "trezor", help = "Use your Trezor hardware wallet")]
+    // No short flag, as `-t` is the target contract of `forge run`
+    #[clap(long = "trezor", help = "Use your Trezor hardware wallet")]
     pub trezor: bool,
 
     #[clap(long = "hd-path", help = "Derivation path for your hardware wallet (trezor or ledger)")]
@@ -218,6 +187,43 @@ pub struct Wallet {
 }
 
 impl Wallet {
+    /// Returns a [`SignerMiddleware`] corresponding to the provided private key, mnemonic or hw
+    /// signer
+    pub async fn signer(&self, chain_id: U256, provider: Provider<Http>) -> Result<WalletType> {
+        if self.ledger {
+            let derivation = match &self.hd_path {
+                Some(hd_path) => LedgerHDPath::Other(hd_path.clone()),
+                None => LedgerHDPath::LedgerLive(self.mnemonic_index as usize),
+            };
+            let ledger = Ledger::new(derivation, chain_id.as_u64()).await?;
+
+            Ok(WalletType::Ledger(SignerMiddleware::new(provider, ledger)))
+        } else if self.trezor {
+            let derivation = match &self.hd_path {
+                Some(hd_path) => TrezorHDPath::Other(hd_path.clone()),
+                None => TrezorHDPath::TrezorLive(self.mnemonic_index as usize),
+            };
+
+            // cached to ~/.ethers-rs/trezor/cache/trezor.session
+            let trezor = Trezor::new(derivation, chain_id.as_u64(), None).await?;
+
+            Ok(WalletType::Trezor(SignerMiddleware::new(provider, trezor)))
+        } else {
+            let local = self
+                .private_key()
+                .transpose()
+                .or_else(|| self.interactive().transpose())
+                .or_else(|| self.mnemonic().transpose())
+                .or_else(|| self.keystore().transpose())
+                .transpose()?
+                .ok_or_else(|| eyre::eyre!("error accessing local wallet, did you set a private key, mnemonic or keystore? Run `cast send --help`, `forge create --help` or `forge run --help` and use the corresponding CLI flag to set your key via --private-key, --mnemonic-path, --interactive, --trezor or --ledger. Alternatively, if you're using a local node with unlocked accounts, set the `ETH_FROM` environment variable to the address of the account you want to use"))?;
+
+            let local = local.with_chain_id(chain_id.as_u64());
+
+            Ok(WalletType::Local(SignerMiddleware::new(provider, local)))
+        }
+    }
+
     fn interactive(&self) -> Result<Option<LocalWallet>> {
         Ok(if self.interactive {
             println!("Insert private key:");
@@ -1,13 +1,29 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     future::Future,
     path::{Path, PathBuf},
     str::FromStr,
     time::Duration,
 };
 
-use ethers::{solc::EvmVersion, types::U256};
-use forge::executor::{opts::EvmOpts, Fork, SpecId};
+use ethers::{
+    etherscan::{errors::EtherscanError, Client},
+    solc::{
+        artifacts::{
+            CompilerInput, ContractBytecode, ContractBytecodeSome, Source, Sources, StorageLayout,
+        },
+        EvmVersion, ProjectCompileOutput, Solc,
+    },
+    types::{Address, Chain, U256},
+};
+use forge::{
+    abi::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+    executor::{fork::ForkCache, opts::EvmOpts, Fork, SpecId},
+    trace::{identifier::EtherscanIdentifier, CallTraceArena, TraceFormat, TraceKind},
+};
 use foundry_config::{caching::StorageCachingConfig, Config};
+use foundry_utils::{RuntimeOrHandle, SignatureDb};
+use serde::Deserialize;
 use tracing_error::ErrorLayer;
 use tracing_subscriber::prelude::*;
 
@@ -204,6 +220,219 @@ pub fn get_fork(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> Option<For
     None
 }
 
+/// Returns where the storage of forks created with the `createFork` cheatcode is cached, or `None`
+/// if storage caching is disabled.
+///
+/// As with [get_fork], only the storage of forks pinned to a block whose endpoint and chain are
+/// enabled in the `config` is cached.
+///
+/// See also [Config::foundry_block_cache_file()]
+pub fn get_fork_cache(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> Option<ForkCache> {
+    if evm_opts.no_storage_caching {
+        // storage caching explicitly opted out of
+        return None
+    }
+    Some(ForkCache { dir: Config::foundry_cache_dir()?, rules: config.clone() })
+}
+
+/// Writes a trace in the given format to `<dir>/<name>.<kind>.<extension>`, e.g.
+/// `traces/testFoo().execution.json`
+pub fn export_trace(
+    dir: &Path,
+    name: &str,
+    kind: &TraceKind,
+    format: TraceFormat,
+    trace: &CallTraceArena,
+) -> eyre::Result<()> {
+    let kind = match kind {
+        TraceKind::Deployment => "deployment",
+        TraceKind::Setup => "setup",
+        TraceKind::Execution => "execution",
+    };
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join(format!("{}.{}.{}", name, kind, format.extension())),
+        format.render(trace),
+    )?;
+    Ok(())
+}
+
+/// Returns the storage layouts of the compiled contracts by contract name.
+///
+/// The layouts are only part of the compiler output if `storageLayout` is in `extra_output`.
+pub fn storage_layouts(output: &ProjectCompileOutput) -> BTreeMap<String, StorageLayout> {
+    output
+        .artifacts()
+        .filter_map(|(name, artifact)| Some((name, artifact.storage_layout.clone()?)))
+        .collect()
+}
+
+/// Loads the database of known signatures from [Config::foundry_signatures_cache_file()]
+pub fn signature_db() -> SignatureDb {
+    Config::foundry_signatures_cache_file().map(SignatureDb::load).unwrap_or_default()
+}
+
+/// Returns an identifier that looks up the unknown addresses in the traces of a fork on Etherscan.
+///
+/// This is `None` if no fork is used, if `ETHERSCAN_API_KEY` is not set or if the chain of the
+/// fork is not supported by Etherscan. The looked up contracts are cached in
+/// [Config::foundry_etherscan_cache_dir()], unless storage caching is disabled.
+pub fn get_etherscan_identifier(evm_opts: &EvmOpts) -> Option<EtherscanIdentifier> {
+    let (chain_id, chain, api_key) = etherscan_fork(evm_opts)?;
+    let cache_dir = if evm_opts.no_storage_caching {
+        None
+    } else {
+        Config::foundry_etherscan_cache_dir(chain_id)
+    };
+    match EtherscanIdentifier::new(chain, &api_key, cache_dir) {
+        Ok(identifier) => Some(identifier),
+        Err(err) => {
+            tracing::warn!(%err, "failed to set up the Etherscan identifier");
+            None
+        }
+    }
+}
+
+/// Returns the ID and the chain of the fork, and the Etherscan API key.
+///
+/// This is `None` if no fork is used, if `ETHERSCAN_API_KEY` is not set or if the chain of the
+/// fork is not supported by Etherscan.
+fn etherscan_fork(evm_opts: &EvmOpts) -> Option<(u64, Chain, String)> {
+    evm_opts.fork_url.as_ref()?;
+    let api_key = foundry_utils::etherscan_api_key().ok()?;
+    let chain_id = evm_opts.get_chain_id();
+    let chain = Chain::try_from(chain_id).ok()?;
+    Some((chain_id, chain, api_key))
+}
+
+/// Fetches the verified sources of the contracts at `addresses` from Etherscan and compiles them
+/// with the settings they were verified with, so that the debugger can show the sources of the
+/// contracts of a fork that are not part of the project.
+///
+/// The sources are keyed by their index in the source maps of the compiled contract. Contracts
+/// that are not verified or can not be compiled are skipped.
+pub fn etherscan_sources(
+    evm_opts: &EvmOpts,
+    addresses: impl IntoIterator<Item = Address>,
+) -> BTreeMap<Address, (ContractBytecodeSome, BTreeMap<u32, String>)> {
+    let (_, chain, api_key) = match etherscan_fork(evm_opts) {
+        Some(fork) => fork,
+        None => return BTreeMap::new(),
+    };
+    let client = match Client::new(chain, api_key) {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::warn!(%err, "failed to set up the Etherscan client");
+            return BTreeMap::new()
+        }
+    };
+
+    // The cheatcode and console addresses and the precompiles are not contracts on chain
+    let addresses: BTreeSet<Address> = addresses
+        .into_iter()
+        .filter(|address| {
+            *address != CHEATCODE_ADDRESS &&
+                *address != HARDHAT_CONSOLE_ADDRESS &&
+                *address > Address::from_low_u64_be(9)
+        })
+        .collect();
+    if addresses.is_empty() {
+        return BTreeMap::new()
+    }
+    println!("Fetching the sources of {} contract(s) from Etherscan...", addresses.len());
+
+    let runtime = RuntimeOrHandle::new();
+    addresses
+        .into_iter()
+        .filter_map(|address| match compile_etherscan_contract(&client, &runtime, address) {
+            Ok(contract) => contract.map(|contract| (address, contract)),
+            Err(err) => {
+                tracing::warn!(target: "etherscan", ?address, %err, "failed to compile sources");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Fetches the verified sources of the contract at `address` and compiles them, see
+/// [etherscan_sources]
+fn compile_etherscan_contract(
+    client: &Client,
+    runtime: &RuntimeOrHandle,
+    address: Address,
+) -> eyre::Result<Option<(ContractBytecodeSome, BTreeMap<u32, String>)>> {
+    let metadata = match runtime.block_on(client.contract_source_code(address)) {
+        Ok(source) => match source.items.into_iter().next() {
+            Some(metadata) if !metadata.source_code.is_empty() => metadata,
+            _ => return Ok(None),
+        },
+        Err(EtherscanError::ContractCodeNotVerified(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let sources = parse_etherscan_sources(&metadata.contract_name, &metadata.source_code)?;
+    // The version is e.g. `v0.8.10+commit.fc410830`
+    let version = metadata.compiler_version.trim_start_matches('v');
+    let solc = Solc::find_or_install_svm_version(version.split('+').next().unwrap_or(version))?;
+
+    let mut input = CompilerInput::with_sources(sources.clone())
+        .into_iter()
+        .find(|input| input.language == "Solidity")
+        .ok_or_else(|| eyre::eyre!("no Solidity sources"))?;
+    input.settings.optimizer.enabled = Some(metadata.optimization_used == "1");
+    input.settings.optimizer.runs = metadata.runs.parse().ok();
+    // Etherscan uses `Default` for the default EVM version of the compiler
+    input.settings.evm_version = metadata.evm_version.to_lowercase().parse().ok();
+
+    let output = solc.compile(&input)?;
+    if output.has_error() {
+        eyre::bail!("the verified sources of {:?} failed to compile", address)
+    }
+
+    let contract = match output
+        .contracts
+        .into_values()
+        .find_map(|mut contracts| contracts.remove(&metadata.contract_name))
+    {
+        Some(contract) => ContractBytecode::from(contract),
+        None => return Ok(None),
+    };
+    if contract.abi.is_none() || contract.bytecode.is_none() || contract.deployed_bytecode.is_none()
+    {
+        return Ok(None)
+    }
+    let source_code = output
+        .sources
+        .into_iter()
+        .filter_map(|(path, file)| {
+            Some((file.id, sources.get(Path::new(&path))?.content.to_string()))
+        })
+        .collect();
+
+    Ok(Some((contract.unwrap(), source_code)))
+}
+
+/// Parses the verified sources of a contract, which are either a single file, a JSON object of
+/// files or a standard JSON input wrapped in an extra pair of braces
+fn parse_etherscan_sources(contract_name: &str, source_code: &str) -> eyre::Result<Sources> {
+    #[derive(Deserialize)]
+    struct StandardJsonInput {
+        sources: Sources,
+    }
+
+    let source_code = source_code.trim();
+    if let Some(input) = source_code.strip_prefix("{{").and_then(|s| s.strip_suffix("}}")) {
+        Ok(serde_json::from_str::<StandardJsonInput>(&format!("{{{}}}", input))?.sources)
+    } else if source_code.starts_with('{') {
+        Ok(serde_json::from_str(source_code)?)
+    } else {
+        Ok(Sources::from([(
+            PathBuf::from(format!("{}.sol", contract_name)),
+            Source { content: source_code.to_string() },
+        )]))
+    }
+}
+
 /// Conditionally print a message
 ///
 /// This macro accepts a predicate and the message to print if the predicate is tru
@@ -225,6 +454,25 @@ pub(crate) use p_println;
 mod tests {
     use super::*;
 
+    #[test]
+    fn can_parse_etherscan_sources() {
+        let single = parse_etherscan_sources("Foo", "contract Foo {}").unwrap();
+        assert_eq!(single[Path::new("Foo.sol")].content, "contract Foo {}");
+
+        let files = r#"{
+            "src/Foo.sol": {"content": "import './Bar.sol';"},
+            "src/Bar.sol": {"content": "contract Bar {}"}
+        }"#;
+        let multiple = parse_etherscan_sources("Foo", files).unwrap();
+        assert_eq!(multiple.len(), 2);
+        assert_eq!(multiple[Path::new("src/Bar.sol")].content, "contract Bar {}");
+
+        let standard_json = format!(r#"{{{{"language": "Solidity", "sources": {}}}}}"#, files);
+        let standard_json = parse_etherscan_sources("Foo", &standard_json).unwrap();
+        assert_eq!(standard_json.len(), 2);
+        assert_eq!(standard_json[Path::new("src/Foo.sol")].content, "import './Bar.sol';");
+    }
+
     #[test]
     fn foundry_path_ext_works() {
         let p = Path::new("contracts/MyTest.t.sol");
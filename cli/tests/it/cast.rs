@@ -26,3 +26,87 @@ casttest!(finds_block, |_: TestProject, mut cmd: TestCommand| {
     // Expect successful block query
     assert!(output.contains("6574364"), "{}", output);
 });
+
+// tests that `cast run` replays a mined transaction
+casttest!(runs_transaction, |_: TestProject, mut cmd: TestCommand| {
+    // Skip fork tests if the RPC url is not set.
+    if std::env::var("ETH_RPC_URL").is_err() {
+        eprintln!("Skipping test runs_transaction. ETH_RPC_URL is not set.");
+        return
+    };
+
+    // Construct args
+    // The first transaction on mainnet, a plain transfer in block 46147
+    let tx = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060".to_string();
+    let eth_rpc_url = env::var("ETH_RPC_URL").unwrap();
+
+    // Call `cast run`
+    cmd.args(["run", "--rpc-url", eth_rpc_url.as_str(), &tx]);
+    let output = cmd.stdout_lossy();
+    println!("{}", output);
+
+    // Expect the transaction to be replayed
+    assert!(output.contains("Transaction successfully executed."), "{}", output);
+    assert!(output.contains("Gas used:"), "{}", output);
+});
+
+// tests that `cast eip712-hash` and `cast wallet sign --typed-data` match the example of EIP-712
+casttest!(signs_typed_data, |prj: TestProject, mut cmd: TestCommand| {
+    let path = prj.create_file(
+        "mail.json",
+        r#"{
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        }"#,
+    );
+    let path = path.to_str().unwrap();
+
+    cmd.args(["eip712-hash", path]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+    );
+
+    // The key of the example is the hash of "cow"
+    cmd.cast_fuse().args([
+        "wallet",
+        "sign",
+        "--typed-data",
+        path,
+        "--private-key",
+        "0xc85ef7d79691fe79573b1a7064c19c1a9819ebdbd1faaab1a8ec92344438aaf4",
+    ]);
+    let output = cmd.stdout_lossy();
+    assert!(
+        output.contains("0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b915621c"),
+        "{}",
+        output
+    );
+});
@@ -51,6 +51,8 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         names: true,
         sizes: true,
         fuzz_runs: 1000,
+        fuzz_corpus_dir: "test-cache/fuzz".into(),
+        invariant_depth: 30,
         fuzz_max_local_rejects: 2000,
         fuzz_max_global_rejects: 100203,
         ffi: true,
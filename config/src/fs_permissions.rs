@@ -0,0 +1,182 @@
+//! Support types for configuring the file system access of cheatcodes
+
+use ethers_solc::utils::canonicalize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The paths the file system cheatcodes (`readFile`, `writeFile` etc.) are allowed to access, and
+/// how.
+///
+/// No path is accessible by default.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FsPermissions {
+    /// The permission of every path
+    pub permissions: Vec<PathPermission>,
+}
+
+impl FsPermissions {
+    /// Creates the permissions of the given paths
+    pub fn new(permissions: impl IntoIterator<Item = PathPermission>) -> Self {
+        Self { permissions: permissions.into_iter().collect() }
+    }
+
+    /// Whether `path` may be accessed with the `access` kind.
+    ///
+    /// This is the case if `path` is in one of the permitted paths and that permission allows
+    /// `access`. `path` should be absolute and normalized, like the permitted paths after
+    /// [Self::join_all].
+    pub fn is_path_allowed(&self, path: impl AsRef<Path>, access: FsAccessKind) -> bool {
+        let path = path.as_ref();
+        self.permissions.iter().any(|permission| {
+            path.starts_with(&permission.path) && permission.access.allows(access)
+        })
+    }
+
+    /// Joins all relative paths with the given root, and canonicalizes the paths that exist
+    pub fn join_all(&mut self, root: impl AsRef<Path>) {
+        let root = root.as_ref();
+        for permission in &mut self.permissions {
+            let path = root.join(&permission.path);
+            permission.path = canonicalize(&path).unwrap_or(path);
+        }
+    }
+}
+
+/// The permission of a path and all paths within it, e.g.
+/// `{ access = "read", path = "./test/fixtures" }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathPermission {
+    /// How the path may be accessed
+    pub access: FsAccessPermission,
+    /// The path, relative to the project root
+    pub path: PathBuf,
+}
+
+impl PathPermission {
+    /// Permission to read and write the path
+    pub fn read_write(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::ReadWrite, path: path.into() }
+    }
+
+    /// Permission to only read the path
+    pub fn read(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::Read, path: path.into() }
+    }
+
+    /// Permission to only write the path
+    pub fn write(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::Write, path: path.into() }
+    }
+}
+
+/// How a file is accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsAccessKind {
+    /// Reading a file
+    Read,
+    /// Writing or removing a file
+    Write,
+}
+
+/// How a permitted path may be accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsAccessPermission {
+    /// Reading and writing, `read-write`
+    ReadWrite,
+    /// Only reading, `read`
+    Read,
+    /// Only writing, `write`
+    Write,
+}
+
+impl FsAccessPermission {
+    /// Whether the permission allows the `access` kind
+    pub fn allows(&self, access: FsAccessKind) -> bool {
+        match self {
+            FsAccessPermission::ReadWrite => true,
+            FsAccessPermission::Read => access == FsAccessKind::Read,
+            FsAccessPermission::Write => access == FsAccessKind::Write,
+        }
+    }
+}
+
+impl FromStr for FsAccessPermission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-write" => Ok(FsAccessPermission::ReadWrite),
+            "read" => Ok(FsAccessPermission::Read),
+            "write" => Ok(FsAccessPermission::Write),
+            _ => Err(format!("Unknown file system access `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for FsAccessPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsAccessPermission::ReadWrite => f.write_str("read-write"),
+            FsAccessPermission::Read => f.write_str("read"),
+            FsAccessPermission::Write => f.write_str("write"),
+        }
+    }
+}
+
+impl Serialize for FsAccessPermission {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FsAccessPermission {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_check_permissions() {
+        let mut permissions = FsPermissions::new([
+            PathPermission::read("./fixtures"),
+            PathPermission::read_write("./out/vectors"),
+        ]);
+        permissions.join_all("/project");
+
+        assert!(permissions.is_path_allowed("/project/fixtures/tree.json", FsAccessKind::Read));
+        assert!(!permissions.is_path_allowed("/project/fixtures/tree.json", FsAccessKind::Write));
+        assert!(permissions.is_path_allowed("/project/out/vectors/1.txt", FsAccessKind::Write));
+        assert!(!permissions.is_path_allowed("/project/out/other.txt", FsAccessKind::Read));
+        assert!(!permissions.is_path_allowed("/project/fixtures2/tree.json", FsAccessKind::Read));
+    }
+
+    #[test]
+    fn can_parse_permissions() {
+        #[derive(Deserialize)]
+        struct Config {
+            fs_permissions: FsPermissions,
+        }
+
+        let config: Config =
+            toml::from_str(r#"fs_permissions = [{ access = "read", path = "./fixtures" }]"#)
+                .unwrap();
+        assert_eq!(config.fs_permissions, FsPermissions::new([PathPermission::read("./fixtures")]));
+        assert!("execute".parse::<FsAccessPermission>().is_err());
+    }
+}
@@ -18,7 +18,7 @@ pub use figment;
 use semver::Version;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::caching::StorageCachingConfig;
+use crate::{caching::StorageCachingConfig, fs_permissions::FsPermissions};
 use ethers_core::types::{Address, U256};
 pub use ethers_solc::artifacts::OptimizerDetails;
 use ethers_solc::{
@@ -41,6 +41,8 @@ pub use crate::utils::*;
 
 pub mod caching;
 
+pub mod fs_permissions;
+
 /// Foundry configuration
 ///
 /// # Defaults
@@ -138,8 +140,17 @@ pub struct Config {
     pub ignored_error_codes: Vec<SolidityErrorCode>,
     /// The number of test cases that must execute for each property test
     pub fuzz_runs: u32,
+    /// The directory in which the counterexamples of failed fuzz tests are saved.
+    ///
+    /// Saved counterexamples are replayed before any new cases are generated.
+    pub fuzz_corpus_dir: PathBuf,
+    /// The maximum number of calls in each sequence of calls executed by an invariant test
+    pub invariant_depth: u32,
     /// Whether to allow ffi cheatcodes in test
     pub ffi: bool,
+    /// The paths the file system cheatcodes are allowed to access, and how, e.g.
+    /// `fs_permissions = [{ access = "read", path = "./test/fixtures" }]`
+    pub fs_permissions: FsPermissions,
     /// The address which will be executing all tests
     pub sender: Address,
     /// The tx.origin value during EVM execution
@@ -364,6 +375,8 @@ impl Config {
             self.remappings.into_iter().map(|r| RelativeRemapping::new(r.into(), &root)).collect();
 
         self.cache_path = p(&root, &self.cache_path);
+        self.fuzz_corpus_dir = p(&root, &self.fuzz_corpus_dir);
+        self.fs_permissions.join_all(&root);
 
         self
     }
@@ -409,6 +422,14 @@ impl Config {
         self.create_project(false, true)
     }
 
+    /// Removes all counterexamples saved in the [`Self::fuzz_corpus_dir`]
+    pub fn clean_fuzz_corpus(&self) -> std::io::Result<()> {
+        if self.fuzz_corpus_dir.exists() {
+            std::fs::remove_dir_all(&self.fuzz_corpus_dir)?;
+        }
+        Ok(())
+    }
+
     fn create_project(&self, cached: bool, no_artifacts: bool) -> Result<Project, SolcError> {
         let mut project = Project::builder()
             .artifacts(self.configured_artifacts_handler())
@@ -699,6 +720,7 @@ impl Config {
                 );
         }
         s = s.replace("[rpc_storage_caching]", &format!("[{}.rpc_storage_caching]", self.profile));
+        s = s.replace("[[fs_permissions]]", &format!("[[{}.fs_permissions]]", self.profile));
 
         Ok(format!(
             r#"[{}]
@@ -740,6 +762,18 @@ impl Config {
         )
     }
 
+    /// Returns the path to the cache file of the known function, error and event signatures
+    /// `~/.foundry/cache/signatures.json`
+    pub fn foundry_signatures_cache_file() -> Option<PathBuf> {
+        Some(Config::foundry_cache_dir()?.join("signatures.json"))
+    }
+
+    /// Returns the path to the cache dir of the contracts looked up on Etherscan on the `chain`
+    /// `~/.foundry/cache/etherscan/<chain>`
+    pub fn foundry_etherscan_cache_dir(chain_id: impl Into<Chain>) -> Option<PathBuf> {
+        Some(Config::foundry_cache_dir()?.join("etherscan").join(chain_id.into().to_string()))
+    }
+
     #[doc = r#"Returns the path to `foundry`'s data directory inside the user's data directory
     |Platform | Value                                 | Example                          |
     | ------- | ------------------------------------- | -------------------------------- |
@@ -901,9 +935,12 @@ impl Default for Config {
             names: false,
             sizes: false,
             fuzz_runs: 256,
+            fuzz_corpus_dir: "cache/fuzz".into(),
+            invariant_depth: 15,
             fuzz_max_local_rejects: 1024,
             fuzz_max_global_rejects: 65536,
             ffi: false,
+            fs_permissions: Default::default(),
             sender: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             tx_origin: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             initial_balance: U256::from(0xffffffffffffffffffffffffu128),
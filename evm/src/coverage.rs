@@ -0,0 +1,129 @@
+use bytes::Bytes;
+use ethers::types::H256;
+use revm::opcode;
+use std::collections::BTreeMap;
+
+/// The hit maps of all contracts that were executed, keyed by the hash of their bytecode.
+///
+/// Creation code and runtime code have separate hit maps.
+#[derive(Default, Debug, Clone)]
+pub struct HitMaps(pub BTreeMap<H256, HitMap>);
+
+impl HitMaps {
+    /// Merges the hits of another set of hit maps into this one.
+    pub fn merge(&mut self, other: HitMaps) {
+        for (code_hash, hit_map) in other.0 {
+            match self.0.get_mut(&code_hash) {
+                Some(existing) => existing.merge(hit_map),
+                None => {
+                    self.0.insert(code_hash, hit_map);
+                }
+            }
+        }
+    }
+
+    /// Merges two optional sets of hit maps, e.g. the coverage of two calls.
+    pub fn merge_opt(a: Option<HitMaps>, b: Option<HitMaps>) -> Option<HitMaps> {
+        match (a, b) {
+            (Some(mut a), Some(b)) => {
+                a.merge(b);
+                Some(a)
+            }
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// The number of times each instruction and branch of a single piece of bytecode was executed.
+#[derive(Default, Debug, Clone)]
+pub struct HitMap {
+    /// The bytecode that was executed
+    pub bytecode: Bytes,
+    /// The number of times each instruction was executed, keyed by its program counter
+    pub hits: BTreeMap<usize, u64>,
+    /// The number of times each `JUMPI` jumped and did not jump, keyed by its program counter
+    pub branches: BTreeMap<usize, (u64, u64)>,
+}
+
+impl HitMap {
+    pub fn new(bytecode: Bytes) -> Self {
+        Self { bytecode, ..Default::default() }
+    }
+
+    /// Records a hit of the instruction at `pc`.
+    pub fn hit(&mut self, pc: usize) {
+        *self.hits.entry(pc).or_default() += 1;
+    }
+
+    /// Records whether the `JUMPI` at `pc` jumped or not.
+    pub fn branch(&mut self, pc: usize, taken: bool) {
+        let (jumped, not_jumped) = self.branches.entry(pc).or_default();
+        if taken {
+            *jumped += 1;
+        } else {
+            *not_jumped += 1;
+        }
+    }
+
+    /// Returns the number of times the instruction at `pc` was executed.
+    pub fn hits(&self, pc: usize) -> u64 {
+        self.hits.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of times the `JUMPI` at `pc` jumped and did not jump.
+    pub fn branch_hits(&self, pc: usize) -> (u64, u64) {
+        self.branches.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Merges the hits of another hit map for the same bytecode into this one.
+    pub fn merge(&mut self, other: HitMap) {
+        for (pc, hits) in other.hits {
+            *self.hits.entry(pc).or_default() += hits;
+        }
+        for (pc, (jumped, not_jumped)) in other.branches {
+            let entry = self.branches.entry(pc).or_default();
+            entry.0 += jumped;
+            entry.1 += not_jumped;
+        }
+    }
+}
+
+/// An instruction in a piece of bytecode.
+#[derive(Debug, Clone, Copy)]
+pub struct BytecodeInstruction {
+    /// The program counter of the instruction
+    pub pc: usize,
+    /// The instruction counter of the instruction.
+    ///
+    /// Unlike the program counter, the instruction counter does not count push bytes. It is used
+    /// to index Solidity source maps.
+    pub ic: usize,
+    /// The opcode of the instruction
+    pub opcode: u8,
+}
+
+impl BytecodeInstruction {
+    /// Whether the instruction is a conditional jump, i.e. a branch.
+    pub fn is_branch(&self) -> bool {
+        self.opcode == opcode::JUMPI
+    }
+}
+
+/// Returns an iterator over the instructions of `code`.
+pub fn instructions(code: &[u8]) -> impl Iterator<Item = BytecodeInstruction> + '_ {
+    let mut pc = 0;
+    let mut ic = 0;
+    std::iter::from_fn(move || {
+        let opcode = *code.get(pc)?;
+        let instruction = BytecodeInstruction { pc, ic, opcode };
+
+        // Skip the push bytes
+        if (opcode::PUSH1..=opcode::PUSH32).contains(&opcode) {
+            pc += (opcode - opcode::PUSH1 + 1) as usize;
+        }
+        pc += 1;
+        ic += 1;
+
+        Some(instruction)
+    })
+}
@@ -61,14 +61,15 @@ impl DebugArena {
     /// - The address of the contract being executed
     /// - A [Vec] of debug steps along that contract's execution path
     /// - An enum denoting the type of call this is
+    /// - The depth of the call
     ///
     /// This makes it easy to pretty print the execution steps.
-    pub fn flatten(&self, entry: usize) -> Vec<(Address, Vec<DebugStep>, CallKind)> {
+    pub fn flatten(&self, entry: usize) -> Vec<(Address, Vec<DebugStep>, CallKind, usize)> {
         let node = &self.arena[entry];
 
         let mut flattened = vec![];
         if !node.steps.is_empty() {
-            flattened.push((node.address, node.steps.clone(), node.kind));
+            flattened.push((node.address, node.steps.clone(), node.kind, node.depth));
         }
         flattened.extend(node.children.iter().flat_map(|child| self.flatten(*child)));
 
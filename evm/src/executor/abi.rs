@@ -22,6 +22,9 @@ ethers::contract::abigen!(
             ffi(string[])(bytes)
             addr(uint256)(address)
             sign(uint256,bytes32)(uint8,bytes32,bytes32)
+            deriveKey(string,uint32)(uint256)
+            deriveKey(string,string,uint32)(uint256)
+            rememberKey(uint256)(address)
             prank(address)
             startPrank(address)
             prank(address,address)
@@ -34,15 +37,60 @@ ethers::contract::abigen!(
             expectRevert(bytes4)
             record()
             accesses(address)(bytes32[],bytes32[])
+            recordLogs()
+            getRecordedLogs()((bytes32[],bytes,address)[])
             expectEmit(bool,bool,bool,bool)
             mockCall(address,bytes,bytes)
+            mockCall(address,uint256,bytes,bytes)
+            mockCallRevert(address,bytes,bytes)
+            mockCallRevert(address,uint256,bytes,bytes)
             clearMockedCalls()
             expectCall(address,bytes)
+            expectCall(address,uint256,bytes)
+            expectCall(address,uint256,uint64,bytes)
+            expectCall(address,bytes,uint64)
+            expectCall(address,uint256,bytes,uint64)
+            expectCall(address,uint256,uint64,bytes,uint64)
             getCode(string)
+            readFile(string)(string)
+            readLine(string)(string)
+            writeFile(string,string)
+            removeFile(string)
+            setEnv(string,string)
+            envBool(string)(bool)
+            envUint(string)(uint256)
+            envInt(string)(int256)
+            envAddress(string)(address)
+            envBytes32(string)(bytes32)
+            envString(string)(string)
+            envBytes(string)(bytes)
+            envBool(string,string)(bool[])
+            envUint(string,string)(uint256[])
+            envInt(string,string)(int256[])
+            envAddress(string,string)(address[])
+            envBytes32(string,string)(bytes32[])
+            envString(string,string)(string[])
+            envBytes(string,string)(bytes[])
+            parseJson(string)(bytes)
+            parseJson(string,string)(bytes)
+            parseJson(string,string,string)(bytes)
             label(address,string)
             assume(bool)
             setNonce(address,uint64)
             getNonce(address)
+            snapshot()(uint256)
+            revertTo(uint256)(bool)
+            createFork(string)(uint256)
+            createFork(string,uint256)(uint256)
+            selectFork(uint256)
+            rollFork(uint256)
+            rollFork(uint256,uint256)
+            broadcast()
+            broadcast(address)
+            startBroadcast()
+            startBroadcast(address)
+            stopBroadcast()
+            breakpoint(string)
     ]"#,
 );
 pub use hevm_mod::{HEVMCalls, HEVM_ABI};
@@ -6,8 +6,8 @@ use revm::{
 use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
 
 use super::{
-    fork::SharedBackend,
-    inspector::{Cheatcodes, InspectorStackConfig},
+    fork::{ForkCache, MultiFork, SharedBackend},
+    inspector::{Cheatcodes, CheatsConfig, InspectorStackConfig},
     Executor,
 };
 
@@ -25,6 +25,8 @@ pub struct ExecutorBuilder {
     /// The configuration used to build an [InspectorStack].
     inspector_config: InspectorStackConfig,
     gas_limit: Option<U256>,
+    /// Where the storage of forks created at runtime is cached.
+    fork_cache: Option<ForkCache>,
 }
 
 /// Represents a _fork_ of a live chain whose data is available only via the `url` endpoint.
@@ -80,7 +82,7 @@ impl Fork {
 }
 /// Variants of a [revm::Database]
 #[derive(Debug, Clone)]
-pub enum Backend {
+pub enum BackendDatabase {
     /// Simple in memory [revm::Database]
     Simple(EmptyDB),
     /// A [revm::Database] that forks of a remote location and can have multiple consumers of the
@@ -88,48 +90,87 @@ pub enum Backend {
     Forked(SharedBackend),
 }
 
+impl DatabaseRef for BackendDatabase {
+    fn basic(&self, address: H160) -> AccountInfo {
+        match self {
+            BackendDatabase::Simple(inner) => inner.basic(address),
+            BackendDatabase::Forked(inner) => inner.basic(address),
+        }
+    }
+
+    fn code_by_hash(&self, address: H256) -> bytes::Bytes {
+        match self {
+            BackendDatabase::Simple(inner) => inner.code_by_hash(address),
+            BackendDatabase::Forked(inner) => inner.code_by_hash(address),
+        }
+    }
+
+    fn storage(&self, address: H160, index: U256) -> U256 {
+        match self {
+            BackendDatabase::Simple(inner) => inner.storage(address, index),
+            BackendDatabase::Forked(inner) => inner.storage(address, index),
+        }
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        match self {
+            BackendDatabase::Simple(inner) => inner.block_hash(number),
+            BackendDatabase::Forked(inner) => inner.block_hash(number),
+        }
+    }
+}
+
+/// The [revm::Database] used by the executor.
+///
+/// Reads are served by the fork selected with the `selectFork` cheatcode, if any, and by the
+/// database the backend was created with otherwise.
+#[derive(Debug, Clone)]
+pub struct Backend {
+    /// The database the backend was created with
+    pub db: BackendDatabase,
+    /// The forks created at runtime
+    pub forks: MultiFork,
+}
+
 impl Backend {
     /// Instantiates a new backend union based on whether there was or not a fork url specified
     pub fn new(fork: Option<Fork>, env: &Env) -> Self {
         if let Some(fork) = fork {
-            Backend::Forked(fork.spawn_backend(env))
+            Backend {
+                db: BackendDatabase::Forked(fork.spawn_backend(env)),
+                forks: Default::default(),
+            }
         } else {
             Self::simple()
         }
     }
 
     pub fn simple() -> Self {
-        Backend::Simple(EmptyDB())
+        Backend { db: BackendDatabase::Simple(EmptyDB()), forks: Default::default() }
     }
 }
 
 impl DatabaseRef for Backend {
     fn basic(&self, address: H160) -> AccountInfo {
-        match self {
-            Backend::Simple(inner) => inner.basic(address),
-            Backend::Forked(inner) => inner.basic(address),
-        }
+        self.forks.with_active(|fork| fork.basic(address)).unwrap_or_else(|| self.db.basic(address))
     }
 
     fn code_by_hash(&self, address: H256) -> bytes::Bytes {
-        match self {
-            Backend::Simple(inner) => inner.code_by_hash(address),
-            Backend::Forked(inner) => inner.code_by_hash(address),
-        }
+        self.forks
+            .with_active(|fork| fork.code_by_hash(address))
+            .unwrap_or_else(|| self.db.code_by_hash(address))
     }
 
     fn storage(&self, address: H160, index: U256) -> U256 {
-        match self {
-            Backend::Simple(inner) => inner.storage(address, index),
-            Backend::Forked(inner) => inner.storage(address, index),
-        }
+        self.forks
+            .with_active(|fork| fork.storage(address, index))
+            .unwrap_or_else(|| self.db.storage(address, index))
     }
 
     fn block_hash(&self, number: U256) -> H256 {
-        match self {
-            Backend::Simple(inner) => inner.block_hash(number),
-            Backend::Forked(inner) => inner.block_hash(number),
-        }
+        self.forks
+            .with_active(|fork| fork.block_hash(number))
+            .unwrap_or_else(|| self.db.block_hash(number))
     }
 }
 
@@ -141,8 +182,8 @@ impl ExecutorBuilder {
 
     /// Enables cheatcodes on the executor.
     #[must_use]
-    pub fn with_cheatcodes(mut self, ffi: bool) -> Self {
-        self.inspector_config.cheatcodes = Some(Cheatcodes::new(ffi, self.env.block.clone()));
+    pub fn with_cheatcodes(mut self, config: CheatsConfig) -> Self {
+        self.inspector_config.cheatcodes = Some(Cheatcodes::new(config, self.env.block.clone()));
         self
     }
 
@@ -160,6 +201,13 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Enables coverage collection
+    #[must_use]
+    pub fn with_coverage(mut self) -> Self {
+        self.inspector_config.coverage = true;
+        self
+    }
+
     /// Sets the EVM spec to use
     #[must_use]
     pub fn with_spec(mut self, spec: SpecId) -> Self {
@@ -176,6 +224,13 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Sets where the storage of forks created with the `createFork` cheatcode is cached.
+    #[must_use]
+    pub fn with_fork_cache(mut self, fork_cache: Option<ForkCache>) -> Self {
+        self.fork_cache = fork_cache;
+        self
+    }
+
     /// Configure the execution environment (gas limit, chain spec, ...)
     #[must_use]
     pub fn with_config(mut self, env: Env) -> Self {
@@ -185,8 +240,16 @@ impl ExecutorBuilder {
     }
 
     /// Builds the executor as configured.
-    pub fn build(self, db: impl Into<Backend>) -> Executor<Backend> {
+    pub fn build(mut self, db: impl Into<Backend>) -> Executor<Backend> {
         let gas_limit = self.gas_limit.unwrap_or(self.env.block.gas_limit);
-        Executor::new(db.into(), self.env, self.inspector_config, gas_limit)
+
+        // Every executor gets its own set of forks, which is shared with the cheatcode inspector
+        let mut db = db.into();
+        db.forks = MultiFork::new(self.fork_cache);
+        if let Some(cheatcodes) = &mut self.inspector_config.cheatcodes {
+            cheatcodes.forks = db.forks.clone();
+        }
+
+        Executor::new(db, self.env, self.inspector_config, gas_limit)
     }
 }
@@ -6,3 +6,6 @@ pub use init::environment;
 
 mod cache;
 pub use cache::{BlockchainDb, BlockchainDbMeta, JsonBlockCacheDB};
+
+mod multi;
+pub use multi::{CreatedFork, ForkCache, ForkState, ForkedDb, MultiFork};
@@ -0,0 +1,269 @@
+//! Support for multiple forks that can be created and selected at runtime
+use super::{environment, SharedBackend};
+use crate::executor::{Fork, StateChangeset};
+use bytes::Bytes;
+use ethers::{
+    providers::Provider,
+    types::{Address, Chain, H256, U256},
+};
+use foundry_config::caching::StorageCachingConfig;
+use foundry_utils::RuntimeOrHandle;
+use parking_lot::RwLock;
+use revm::{
+    db::{CacheDB, DatabaseCommit, DatabaseRef},
+    AccountInfo, Env,
+};
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
+
+/// A fork created with the `createFork` cheatcode
+#[derive(Debug, Clone)]
+pub struct CreatedFork {
+    /// The URL of the node the fork reads its state from
+    pub url: String,
+    /// The execution environment of the fork, i.e. its block and chain id
+    pub env: Env,
+    /// The state of the fork.
+    ///
+    /// Changes made to non-persistent accounts while the fork is selected are committed here.
+    pub db: CacheDB<SharedBackend>,
+}
+
+/// Where the storage of forks created at runtime is cached, and for which forks
+#[derive(Debug, Clone)]
+pub struct ForkCache {
+    /// The directory in which the storage is cached, by chain and block
+    pub dir: PathBuf,
+    /// The endpoints and chains whose storage is cached, as for the fork of the command line
+    pub rules: StorageCachingConfig,
+}
+
+/// The forks of a [MultiFork] and which of them is selected
+#[derive(Debug, Clone, Default)]
+pub struct ForkState {
+    /// All created forks, indexed by their ID
+    forks: Vec<CreatedFork>,
+    /// The ID of the selected fork, if any
+    active: Option<usize>,
+    /// Accounts that keep their state when switching between forks
+    persistent: BTreeSet<Address>,
+}
+
+/// A set of forks that can be switched between at runtime.
+///
+/// This is a cheap handle that is shared between the executor's database, which reads from the
+/// selected fork, and the cheatcode inspector, which creates and selects forks.
+#[derive(Debug, Clone, Default)]
+pub struct MultiFork {
+    state: Arc<RwLock<ForkState>>,
+    /// Where the storage of forks pinned to a block is cached
+    cache: Option<ForkCache>,
+}
+
+impl MultiFork {
+    /// Creates an empty set of forks that caches storage according to `cache`, if set.
+    ///
+    /// Storage is only cached for forks that are pinned to a block.
+    pub fn new(cache: Option<ForkCache>) -> Self {
+        Self { state: Default::default(), cache }
+    }
+
+    /// Creates a new fork of the chain at `url`, returning its ID.
+    ///
+    /// If no block is given the fork is pinned to the latest block.
+    pub fn create(&self, url: String, block: Option<u64>, env: &Env) -> eyre::Result<U256> {
+        let fork = self.spawn_fork(url, block, env)?;
+        let mut state = self.state.write();
+        state.forks.push(fork);
+        Ok(U256::from(state.forks.len() - 1))
+    }
+
+    /// Selects the fork with the given ID, returning its environment.
+    pub fn select(&self, id: U256) -> eyre::Result<Env> {
+        let mut state = self.state.write();
+        let idx = fork_index(&state, id)?;
+        state.active = Some(idx);
+        Ok(state.forks[idx].env.clone())
+    }
+
+    /// Moves the fork with the given ID to another block, returning its new environment.
+    ///
+    /// Any changes made to the fork are discarded.
+    pub fn roll(&self, id: U256, block: u64, env: &Env) -> eyre::Result<Env> {
+        let url = {
+            let state = self.state.read();
+            state.forks[fork_index(&state, id)?].url.clone()
+        };
+        let fork = self.spawn_fork(url, Some(block), env)?;
+        let fork_env = fork.env.clone();
+
+        let mut state = self.state.write();
+        let idx = fork_index(&state, id)?;
+        state.forks[idx] = fork;
+        Ok(fork_env)
+    }
+
+    /// Returns the ID of the selected fork, if any.
+    pub fn active(&self) -> Option<U256> {
+        self.state.read().active.map(U256::from)
+    }
+
+    /// Marks accounts as persistent, meaning they keep their state when switching between forks.
+    pub fn add_persistent(&self, accounts: impl IntoIterator<Item = Address>) {
+        self.state.write().persistent.extend(accounts)
+    }
+
+    /// Whether the account keeps its state when switching between forks.
+    pub fn is_persistent(&self, account: &Address) -> bool {
+        self.state.read().persistent.contains(account)
+    }
+
+    /// Whether the account is read from and committed to the selected fork, i.e. a fork is
+    /// selected and the account is not persistent.
+    pub fn is_forked(&self, account: &Address) -> bool {
+        let state = self.state.read();
+        state.active.is_some() && !state.persistent.contains(account)
+    }
+
+    /// Inserts the account into the selected fork if it is forked, see [MultiFork::is_forked].
+    ///
+    /// Returns the account if it was not inserted.
+    pub fn insert_account(&self, address: Address, info: AccountInfo) -> Option<AccountInfo> {
+        let mut state = self.state.write();
+        let ForkState { forks, active, persistent } = &mut *state;
+        match active {
+            Some(idx) if !persistent.contains(&address) => {
+                forks[*idx].db.insert_cache(address, info);
+                None
+            }
+            _ => Some(info),
+        }
+    }
+
+    /// Commits the changes to non-persistent accounts to the selected fork.
+    ///
+    /// Returns the changes that were not committed, i.e. all changes if no fork is selected.
+    pub fn commit(&self, changeset: StateChangeset) -> StateChangeset {
+        let mut state = self.state.write();
+        let ForkState { forks, active, persistent } = &mut *state;
+        match active {
+            Some(idx) => {
+                let (persisted, forked): (StateChangeset, StateChangeset) =
+                    changeset.into_iter().partition(|(address, _)| persistent.contains(address));
+                forks[*idx].db.commit(forked);
+                persisted
+            }
+            None => changeset,
+        }
+    }
+
+    /// Returns a copy of the current forks, which can be restored with [MultiFork::restore].
+    pub fn checkpoint(&self) -> ForkState {
+        self.state.read().clone()
+    }
+
+    /// Restores the forks to a previous [MultiFork::checkpoint].
+    pub fn restore(&self, checkpoint: ForkState) {
+        *self.state.write() = checkpoint;
+    }
+
+    /// Reverts the state of the forks to a previous [MultiFork::checkpoint], selecting the fork
+    /// that was selected at the time.
+    ///
+    /// Unlike [MultiFork::restore], forks created after the checkpoint are kept.
+    pub fn revert_to(&self, checkpoint: &ForkState) {
+        let mut state = self.state.write();
+        let mut forks = checkpoint.forks.clone();
+        forks.extend(state.forks.drain(..).skip(checkpoint.forks.len()));
+        state.forks = forks;
+        state.active = checkpoint.active;
+    }
+
+    /// Calls `f` with the state of the selected fork, if any.
+    pub(crate) fn with_active<T>(&self, f: impl FnOnce(&CacheDB<SharedBackend>) -> T) -> Option<T> {
+        let state = self.state.read();
+        state.active.map(|idx| f(&state.forks[idx].db))
+    }
+
+    fn spawn_fork(&self, url: String, block: Option<u64>, env: &Env) -> eyre::Result<CreatedFork> {
+        let provider = Provider::try_from(url.as_str())?;
+        let fork_env = RuntimeOrHandle::new()
+            .block_on(environment(&provider, None, block, env.tx.caller))
+            .map_err(|err| eyre::eyre!("Could not create fork of {}: {}", url, err))?;
+
+        let chain_id = fork_env.cfg.chain_id.as_u64();
+        let block_number = fork_env.block.number.as_u64();
+
+        // Like the storage of a fork configured on the command line, we only cache the storage
+        // of forks that are explicitly pinned to a block
+        let cache_path = block
+            .and(self.cache.as_ref())
+            .filter(|cache| {
+                cache.rules.enable_for_endpoint(&url) && cache.rules.enable_for_chain_id(chain_id)
+            })
+            .map(|cache| {
+                let chain = Chain::try_from(chain_id)
+                    .map(|chain| chain.to_string())
+                    .unwrap_or_else(|_| chain_id.to_string());
+                cache.dir.join(chain).join(block_number.to_string()).join("storage.json")
+            });
+
+        let backend =
+            Fork { cache_path, url: url.clone(), pin_block: Some(block_number), chain_id }
+                .spawn_backend(&fork_env);
+
+        Ok(CreatedFork { url, env: fork_env, db: CacheDB::new(backend) })
+    }
+}
+
+/// The database calls are executed against, given the executor's cache and its forks.
+///
+/// The cache holds the state committed while no fork was selected, e.g. by the constructor and
+/// `setUp` of a test contract, and the state of persistent accounts. While a fork is selected,
+/// all other accounts are read from the database behind the cache instead, which reads from the
+/// selected fork, so they are not shadowed by their state before the fork was selected.
+pub struct ForkedDb<'a, DB: DatabaseRef> {
+    /// The executor's cache
+    pub db: &'a CacheDB<DB>,
+    /// The forks of the executor, if it has cheatcodes enabled
+    pub forks: Option<&'a MultiFork>,
+}
+
+impl<'a, DB: DatabaseRef> ForkedDb<'a, DB> {
+    fn is_forked(&self, address: &Address) -> bool {
+        self.forks.map_or(false, |forks| forks.is_forked(address))
+    }
+}
+
+impl<'a, DB: DatabaseRef> DatabaseRef for ForkedDb<'a, DB> {
+    fn basic(&self, address: Address) -> AccountInfo {
+        if self.is_forked(&address) {
+            self.db.db.basic(address)
+        } else {
+            DatabaseRef::basic(self.db, address)
+        }
+    }
+
+    fn code_by_hash(&self, code_hash: H256) -> Bytes {
+        DatabaseRef::code_by_hash(self.db, code_hash)
+    }
+
+    fn storage(&self, address: Address, index: U256) -> U256 {
+        if self.is_forked(&address) {
+            self.db.db.storage(address, index)
+        } else {
+            DatabaseRef::storage(self.db, address, index)
+        }
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        DatabaseRef::block_hash(self.db, number)
+    }
+}
+
+fn fork_index(state: &ForkState, id: U256) -> eyre::Result<usize> {
+    if id < U256::from(state.forks.len()) {
+        Ok(id.as_usize())
+    } else {
+        eyre::bail!("Fork {} does not exist", id)
+    }
+}
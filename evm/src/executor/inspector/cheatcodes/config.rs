@@ -0,0 +1,90 @@
+use crate::executor::opts::EvmOpts;
+use ethers::solc::utils::canonicalize;
+use foundry_config::{
+    fs_permissions::{FsAccessKind, FsPermissions},
+    Config,
+};
+use std::path::{Component, Path, PathBuf};
+
+/// What the cheatcodes are allowed to do outside of the EVM
+#[derive(Debug, Clone, Default)]
+pub struct CheatsConfig {
+    /// Whether the FFI cheatcode is enabled
+    pub ffi: bool,
+    /// The project root, which relative paths of the file system cheatcodes are relative to
+    pub root: PathBuf,
+    /// The paths the file system cheatcodes are allowed to access
+    pub fs_permissions: FsPermissions,
+}
+
+impl CheatsConfig {
+    /// Extracts the cheatcode settings of the (sanitized) config and the EVM options
+    pub fn new(config: &Config, evm_opts: &EvmOpts) -> Self {
+        Self {
+            ffi: evm_opts.ffi,
+            root: canonicalize(&config.__root.0).unwrap_or_else(|_| config.__root.0.clone()),
+            fs_permissions: config.fs_permissions.clone(),
+        }
+    }
+
+    /// Resolves `path` against the project root and returns it if it may be accessed with the
+    /// `access` kind, or an error otherwise.
+    pub fn ensure_path_allowed(
+        &self,
+        path: impl AsRef<Path>,
+        access: FsAccessKind,
+    ) -> Result<PathBuf, String> {
+        let path = path.as_ref();
+        let resolved = resolve(&self.root.join(path));
+        if resolved.components().any(|component| component == Component::ParentDir) ||
+            !self.fs_permissions.is_path_allowed(&resolved, access)
+        {
+            let access = match access {
+                FsAccessKind::Read => "reading",
+                FsAccessKind::Write => "writing",
+            };
+            return Err(format!(
+                "{:?} is not allowed for {}, see `fs_permissions` in `foundry.toml`",
+                path, access
+            ))
+        }
+        Ok(resolved)
+    }
+}
+
+/// Canonicalizes the path, or its parent directory if the path does not exist yet, so that `..`
+/// and symlinks can't be used to escape a permitted path
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(resolved) = canonicalize(path) {
+        return resolved
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            canonicalize(parent).map(|parent| parent.join(name)).unwrap_or_else(|_| path.into())
+        }
+        _ => path.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_config::fs_permissions::PathPermission;
+
+    #[test]
+    fn can_check_paths() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("fixtures")).unwrap();
+        let mut fs_permissions = FsPermissions::new([PathPermission::read("fixtures")]);
+        fs_permissions.join_all(root.path());
+        let config =
+            CheatsConfig { ffi: false, root: canonicalize(root.path()).unwrap(), fs_permissions };
+
+        assert!(config.ensure_path_allowed("fixtures/tree.json", FsAccessKind::Read).is_ok());
+        assert!(config.ensure_path_allowed("./fixtures/tree.json", FsAccessKind::Write).is_err());
+        assert!(config
+            .ensure_path_allowed("fixtures/../foundry.toml", FsAccessKind::Read)
+            .is_err());
+        assert!(config.ensure_path_allowed("fixtures/a/../../b", FsAccessKind::Read).is_err());
+    }
+}
@@ -45,6 +45,43 @@ fn prank(
     Ok(Bytes::new())
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct Broadcast {
+    /// Address of the contract that initiated the broadcast
+    pub original_caller: Address,
+    /// The address that sends the broadcast transactions
+    pub origin: Address,
+    /// The depth at which the broadcast was started
+    pub depth: u64,
+    /// Whether or not the broadcast stops by itself after the next call
+    pub single_call: bool,
+}
+
+fn broadcast<DB: Database>(
+    state: &mut Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    original_caller: Address,
+    origin: Address,
+    single_call: bool,
+) -> Result<Bytes, Bytes> {
+    if state.broadcast.is_some() {
+        return Err("You have an active broadcast already.".to_string().encode().into())
+    }
+
+    // The nonce of `tx.origin` was incremented by the call to the script, which is not a
+    // transaction that is sent, so we undo it to record the nonces the transactions will have
+    if origin == data.env.tx.caller && !state.corrected_nonce {
+        data.subroutine.load_account(origin, data.db);
+        let account = data.subroutine.state().get_mut(&origin).unwrap();
+        account.info.nonce = account.info.nonce.saturating_sub(1);
+        state.corrected_nonce = true;
+    }
+
+    let depth = data.subroutine.depth();
+    state.broadcast = Some(Broadcast { original_caller, origin, depth, single_call });
+    Ok(Bytes::new())
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RecordAccess {
     pub reads: BTreeMap<Address, Vec<U256>>,
@@ -67,6 +104,54 @@ fn accesses(state: &mut Cheatcodes, address: Address) -> Bytes {
     }
 }
 
+/// The logs recorded since `recordLogs` was called
+#[derive(Clone, Debug, Default)]
+pub struct RecordedLogs {
+    pub entries: Vec<RecordedLog>,
+}
+
+/// A log emitted while recording logs
+#[derive(Clone, Debug)]
+pub struct RecordedLog {
+    /// The address of the contract that emitted the log
+    pub emitter: Address,
+    /// The topics of the log, including the event signature if it is not anonymous
+    pub topics: Vec<H256>,
+    /// The data of the log
+    pub data: Vec<u8>,
+}
+
+fn start_record_logs(state: &mut Cheatcodes) {
+    state.recorded_logs = Some(Default::default());
+}
+
+/// Returns the logs recorded since recording started or since they were last returned, as an
+/// array of `(bytes32[] topics, bytes data, address emitter)` tuples
+fn get_recorded_logs(state: &mut Cheatcodes) -> Bytes {
+    let entries = state
+        .recorded_logs
+        .as_mut()
+        .map(|recorded_logs| std::mem::take(&mut recorded_logs.entries))
+        .unwrap_or_default();
+    let logs = entries
+        .into_iter()
+        .map(|log| {
+            Token::Tuple(vec![
+                Token::Array(
+                    log.topics
+                        .into_iter()
+                        .map(|topic| Token::FixedBytes(topic.as_bytes().to_vec()))
+                        .collect(),
+                ),
+                Token::Bytes(log.data),
+                Token::Address(log.emitter),
+            ])
+        })
+        .collect();
+
+    abi::encode(&[Token::Array(logs)]).into()
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
@@ -151,11 +236,30 @@ pub fn apply<DB: Database>(
             state.prank = None;
             Ok(Bytes::new())
         }
+        HEVMCalls::Broadcast0(_) => {
+            let origin = data.env.tx.caller;
+            broadcast(state, data, caller, origin, true)
+        }
+        HEVMCalls::Broadcast1(inner) => broadcast(state, data, caller, inner.0, true),
+        HEVMCalls::StartBroadcast0(_) => {
+            let origin = data.env.tx.caller;
+            broadcast(state, data, caller, origin, false)
+        }
+        HEVMCalls::StartBroadcast1(inner) => broadcast(state, data, caller, inner.0, false),
+        HEVMCalls::StopBroadcast(_) => {
+            state.broadcast = None;
+            Ok(Bytes::new())
+        }
         HEVMCalls::Record(_) => {
             start_record(state);
             Ok(Bytes::new())
         }
         HEVMCalls::Accesses(inner) => Ok(accesses(state, inner.0)),
+        HEVMCalls::RecordLogs(_) => {
+            start_record_logs(state);
+            Ok(Bytes::new())
+        }
+        HEVMCalls::GetRecordedLogs(_) => Ok(get_recorded_logs(state)),
         HEVMCalls::SetNonce(inner) => {
             // TODO:  this is probably not a good long-term solution since it might mess up the gas
             // calculations
@@ -3,9 +3,13 @@ use crate::abi::HEVMCalls;
 use bytes::Bytes;
 use ethers::{
     abi::{AbiEncode, RawLog},
-    types::{Address, H160},
+    types::{Address, H160, U256},
 };
-use revm::{return_ok, Database, EVMData, Return};
+use revm::{return_ok, CallInputs, Database, EVMData, Return};
+use std::{collections::BTreeMap, fmt};
+
+/// The gas stipend added to calls that transfer value
+const CALL_STIPEND: u64 = 2300;
 
 /// For some cheatcodes we may internally change the status of the call, i.e. in `expectRevert`.
 /// Solidity will see a successful call and attempt to decode the return data. Therefore, we need
@@ -154,6 +158,228 @@ pub fn handle_expect_emit(state: &mut Cheatcodes, log: RawLog) {
     }
 }
 
+/// A call expected with `expectCall`
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedCallData {
+    /// The expected calldata, or a prefix of it
+    pub calldata: Bytes,
+    /// The expected `msg.value`, if any
+    pub value: Option<U256>,
+    /// The expected gas forwarded to the call, excluding the stipend, if any
+    pub gas: Option<u64>,
+    /// The exact number of matching calls expected, or `None` for at least one
+    pub count: Option<u64>,
+    /// The number of matching calls made so far
+    pub actual_count: u64,
+}
+
+impl ExpectedCallData {
+    fn matches(&self, call: &ActualCall) -> bool {
+        call.calldata.starts_with(&self.calldata) &&
+            self.value.map_or(true, |value| value == call.value) &&
+            self.gas.map_or(true, |gas| gas == call.gas)
+    }
+
+    fn is_satisfied(&self) -> bool {
+        match self.count {
+            Some(count) => self.actual_count == count,
+            None => self.actual_count > 0,
+        }
+    }
+}
+
+impl fmt::Display for ExpectedCallData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "data {}", ethers::types::Bytes::from(self.calldata.clone()))?;
+        if let Some(value) = self.value {
+            write!(f, ", value {}", value)?;
+        }
+        if let Some(gas) = self.gas {
+            write!(f, ", gas {}", gas)?;
+        }
+        Ok(())
+    }
+}
+
+/// A call made to an address that calls are expected to
+#[derive(Clone, Debug)]
+pub struct ActualCall {
+    /// The calldata of the call
+    pub calldata: Bytes,
+    /// The `msg.value` of the call
+    pub value: U256,
+    /// The gas forwarded to the call, excluding the stipend
+    pub gas: u64,
+}
+
+impl ActualCall {
+    pub fn new(call: &CallInputs) -> Self {
+        let value = call.transfer.value;
+        let stipend = if value.is_zero() { 0 } else { CALL_STIPEND };
+        Self { calldata: call.input.clone(), value, gas: call.gas_limit.saturating_sub(stipend) }
+    }
+}
+
+impl fmt::Display for ActualCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "data {}, value {}, gas {}",
+            ethers::types::Bytes::from(self.calldata.clone()),
+            self.value,
+            self.gas
+        )
+    }
+}
+
+/// The calls expected to an address, and the calls that were made to it since the first
+/// expectation
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedCalls {
+    /// The expected calls
+    pub expected: Vec<ExpectedCallData>,
+    /// The calls that were made
+    pub actual: Vec<ActualCall>,
+}
+
+impl ExpectedCalls {
+    /// Counts the call towards the expectations it matches
+    pub fn record(&mut self, call: ActualCall) {
+        for expected in self
+            .expected
+            .iter_mut()
+            .filter(|expected| expected.count.is_some() && expected.matches(&call))
+        {
+            expected.actual_count += 1;
+        }
+        // Expectations of at least one call are each satisfied by a different call, so expecting
+        // the same call twice expects two calls
+        if let Some(expected) = self.expected.iter_mut().find(|expected| {
+            expected.count.is_none() && expected.actual_count == 0 && expected.matches(&call)
+        }) {
+            expected.actual_count += 1;
+        }
+        self.actual.push(call);
+    }
+}
+
+/// Returns an error describing the first expected call that was not made as expected, and the
+/// calls that were made to its address instead
+pub fn handle_expected_calls(
+    expected_calls: &BTreeMap<Address, ExpectedCalls>,
+) -> Result<(), Bytes> {
+    let unsatisfied = expected_calls.iter().find_map(|(address, calls)| {
+        calls
+            .expected
+            .iter()
+            .find(|expected| !expected.is_satisfied())
+            .map(|expected| (address, calls, expected))
+    });
+    let (address, calls, expected) = match unsatisfied {
+        Some(unsatisfied) => unsatisfied,
+        None => return Ok(()),
+    };
+
+    let times =
+        |count: u64| if count == 1 { "1 time".to_string() } else { format!("{} times", count) };
+    let message = match expected.count {
+        Some(count) => format!(
+            "Expected a call to {:?} with {} to be made {}, but it was made {}.",
+            address,
+            expected,
+            times(count),
+            times(expected.actual_count)
+        ),
+        None => format!("Expected a call to {:?} with {}, but got none.", address, expected),
+    };
+    let actual = if calls.actual.is_empty() {
+        format!("No calls were made to {:?}.", address)
+    } else {
+        format!(
+            "The calls made to {:?} were:{}",
+            address,
+            calls.actual.iter().map(|call| format!("\n  {}", call)).collect::<String>()
+        )
+    };
+    Err(format!("{} {}", message, actual).encode().into())
+}
+
+/// The calldata and `msg.value` of the calls that a mock applies to
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MockCallDataContext {
+    /// The calldata, or a prefix of it
+    pub calldata: Bytes,
+    /// The `msg.value`, or `None` for any value
+    pub value: Option<U256>,
+}
+
+impl MockCallDataContext {
+    fn matches(&self, calldata: &[u8], value: U256) -> bool {
+        calldata.starts_with(&self.calldata) && self.value.map_or(true, |mock| mock == value)
+    }
+}
+
+/// What a mocked call returns
+#[derive(Clone, Debug)]
+pub struct MockCallReturnData {
+    /// Whether the call reverts with the data instead of returning it
+    pub reverts: bool,
+    /// The return or revert data
+    pub data: Bytes,
+}
+
+/// Returns the mock of the call, preferring mocks of its exact calldata over mocks of a prefix of
+/// it, and mocks of its `msg.value` over mocks of any value
+pub fn find_mock<'a>(
+    mocks: &'a BTreeMap<MockCallDataContext, MockCallReturnData>,
+    call: &CallInputs,
+) -> Option<&'a MockCallReturnData> {
+    let value = call.transfer.value;
+    let exact = |value: Option<U256>| MockCallDataContext { calldata: call.input.clone(), value };
+    mocks.get(&exact(Some(value))).or_else(|| mocks.get(&exact(None))).or_else(|| {
+        mocks
+            .iter()
+            .filter(|(mock, _)| mock.matches(&call.input, value))
+            // The longest prefix is the most specific one
+            .max_by_key(|(mock, _)| (mock.calldata.len(), mock.value.is_some()))
+            .map(|(_, ret)| ret)
+    })
+}
+
+fn mock_call(
+    state: &mut Cheatcodes,
+    address: Address,
+    calldata: Bytes,
+    value: Option<U256>,
+    data: Bytes,
+    reverts: bool,
+) -> Result<Bytes, Bytes> {
+    state
+        .mocked_calls
+        .entry(address)
+        .or_default()
+        .insert(MockCallDataContext { calldata, value }, MockCallReturnData { reverts, data });
+    Ok(Bytes::new())
+}
+
+fn expect_call(
+    state: &mut Cheatcodes,
+    address: Address,
+    calldata: Bytes,
+    value: Option<U256>,
+    gas: Option<u64>,
+    count: Option<u64>,
+) -> Result<Bytes, Bytes> {
+    state.expected_calls.entry(address).or_default().expected.push(ExpectedCallData {
+        calldata,
+        value,
+        gas,
+        count,
+        actual_count: 0,
+    });
+    Ok(Bytes::new())
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
@@ -175,18 +401,51 @@ pub fn apply<DB: Database>(
             });
             Ok(Bytes::new())
         }
-        HEVMCalls::ExpectCall(inner) => {
-            state.expected_calls.entry(inner.0).or_default().push(inner.1.to_vec().into());
-            Ok(Bytes::new())
+        HEVMCalls::ExpectCall0(inner) => {
+            expect_call(state, inner.0, inner.1.to_vec().into(), None, None, None)
         }
-        HEVMCalls::MockCall(inner) => {
-            state
-                .mocked_calls
-                .entry(inner.0)
-                .or_default()
-                .insert(inner.1.to_vec().into(), inner.2.to_vec().into());
-            Ok(Bytes::new())
+        HEVMCalls::ExpectCall1(inner) => {
+            expect_call(state, inner.0, inner.2.to_vec().into(), Some(inner.1), None, None)
         }
+        HEVMCalls::ExpectCall2(inner) => {
+            expect_call(state, inner.0, inner.3.to_vec().into(), Some(inner.1), Some(inner.2), None)
+        }
+        HEVMCalls::ExpectCall3(inner) => {
+            expect_call(state, inner.0, inner.1.to_vec().into(), None, None, Some(inner.2))
+        }
+        HEVMCalls::ExpectCall4(inner) => {
+            expect_call(state, inner.0, inner.2.to_vec().into(), Some(inner.1), None, Some(inner.3))
+        }
+        HEVMCalls::ExpectCall5(inner) => expect_call(
+            state,
+            inner.0,
+            inner.3.to_vec().into(),
+            Some(inner.1),
+            Some(inner.2),
+            Some(inner.4),
+        ),
+        HEVMCalls::MockCall0(inner) => {
+            mock_call(state, inner.0, inner.1.to_vec().into(), None, inner.2.to_vec().into(), false)
+        }
+        HEVMCalls::MockCall1(inner) => mock_call(
+            state,
+            inner.0,
+            inner.2.to_vec().into(),
+            Some(inner.1),
+            inner.3.to_vec().into(),
+            false,
+        ),
+        HEVMCalls::MockCallRevert0(inner) => {
+            mock_call(state, inner.0, inner.1.to_vec().into(), None, inner.2.to_vec().into(), true)
+        }
+        HEVMCalls::MockCallRevert1(inner) => mock_call(
+            state,
+            inner.0,
+            inner.2.to_vec().into(),
+            Some(inner.1),
+            inner.3.to_vec().into(),
+            true,
+        ),
         HEVMCalls::ClearMockedCalls(_) => {
             state.mocked_calls = Default::default();
             Ok(Bytes::new())
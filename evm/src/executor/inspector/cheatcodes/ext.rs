@@ -1,11 +1,23 @@
+use super::{Cheatcodes, CheatsConfig};
 use crate::abi::HEVMCalls;
 use bytes::Bytes;
 use ethers::{
-    abi::{self, AbiEncode, Token},
+    abi::{
+        self,
+        token::{LenientTokenizer, Tokenizer},
+        AbiEncode, ParamType, Token,
+    },
     prelude::{artifacts::CompactContractBytecode, ProjectPathsConfig},
 };
+use foundry_config::fs_permissions::FsAccessKind;
 use serde::Deserialize;
-use std::{fs::File, io::Read, path::Path, process::Command};
+use std::{
+    env,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+    process::Command,
+};
 
 fn ffi(args: &[String]) -> Result<Bytes, Bytes> {
     let output = Command::new(&args[0])
@@ -76,16 +88,113 @@ fn get_code(path: &str) -> Result<Bytes, Bytes> {
     }
 }
 
-pub fn apply(ffi_enabled: bool, call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
+fn read_file(config: &CheatsConfig, path: &str) -> Result<Bytes, Bytes> {
+    let path = config.ensure_path_allowed(path, FsAccessKind::Read).map_err(|err| err.encode())?;
+    let data = fs::read_to_string(path).map_err(|err| err.to_string().encode())?;
+
+    Ok(abi::encode(&[Token::String(data)]).into())
+}
+
+/// Reads the line after the lines of the file that were already read, or an empty string if all
+/// lines were read
+fn read_line(state: &mut Cheatcodes, path: &str) -> Result<Bytes, Bytes> {
+    let path =
+        state.config.ensure_path_allowed(path, FsAccessKind::Read).map_err(|err| err.encode())?;
+    let data = fs::read_to_string(&path).map_err(|err| err.to_string().encode())?;
+
+    let read_lines = state.read_lines.entry(path).or_default();
+    let line = data.lines().nth(*read_lines).map(|line| {
+        *read_lines += 1;
+        line.to_string()
+    });
+
+    Ok(abi::encode(&[Token::String(line.unwrap_or_default())]).into())
+}
+
+/// Writes the data to the file, creating it if it does not exist and replacing its contents if it
+/// does
+fn write_file(state: &mut Cheatcodes, path: &str, data: &str) -> Result<Bytes, Bytes> {
+    let path =
+        state.config.ensure_path_allowed(path, FsAccessKind::Write).map_err(|err| err.encode())?;
+    fs::write(&path, data).map_err(|err| err.to_string().encode())?;
+    // The file is read from its first line again
+    state.read_lines.remove(&path);
+
+    Ok(Bytes::new())
+}
+
+fn remove_file(state: &mut Cheatcodes, path: &str) -> Result<Bytes, Bytes> {
+    let path =
+        state.config.ensure_path_allowed(path, FsAccessKind::Write).map_err(|err| err.encode())?;
+    fs::remove_file(&path).map_err(|err| err.to_string().encode())?;
+    state.read_lines.remove(&path);
+
+    Ok(Bytes::new())
+}
+
+fn set_env(key: &str, value: &str) -> Result<Bytes, Bytes> {
+    // `set_var` panics on these
+    if key.is_empty() || key.contains(&['=', '\0'][..]) || value.contains('\0') {
+        return Err(format!("Invalid environment variable `{}={}`", key, value).encode().into())
+    }
+    env::set_var(key, value);
+
+    Ok(Bytes::new())
+}
+
+/// Reads the environment variable and parses it as the `ty` type, or as an array of it if the
+/// elements are separated by a `delimiter`
+fn get_env(key: &str, ty: ParamType, delimiter: Option<&str>) -> Result<Bytes, Bytes> {
+    let value = env::var(key)
+        .map_err(|_| format!("Failed to get environment variable `{}`", key).encode())?;
+    let parse = |value: &str| {
+        LenientTokenizer::tokenize(&ty, value.trim()).map_err(|err| {
+            format!("Failed to parse environment variable `{}` as type `{}`: {}", key, ty, err)
+                .encode()
+        })
+    };
+
+    let token = match delimiter {
+        Some(_) if value.trim().is_empty() => Token::Array(Vec::new()),
+        Some(delimiter) => {
+            Token::Array(value.split(delimiter).map(parse).collect::<Result<_, _>>()?)
+        }
+        None => parse(&value)?,
+    };
+    Ok(abi::encode(&[token]).into())
+}
+
+pub fn apply(state: &mut Cheatcodes, call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
     Some(match call {
         HEVMCalls::Ffi(inner) => {
-            if !ffi_enabled {
+            if !state.config.ffi {
                 Err("FFI disabled: run again with `--ffi` if you want to allow tests to call external scripts.".to_string().encode().into())
             } else {
                 ffi(&inner.0)
             }
         }
         HEVMCalls::GetCode(inner) => get_code(&inner.0),
+        HEVMCalls::ReadFile(inner) => read_file(&state.config, &inner.0),
+        HEVMCalls::ReadLine(inner) => read_line(state, &inner.0),
+        HEVMCalls::WriteFile(inner) => write_file(state, &inner.0, &inner.1),
+        HEVMCalls::RemoveFile(inner) => remove_file(state, &inner.0),
+        HEVMCalls::SetEnv(inner) => set_env(&inner.0, &inner.1),
+        HEVMCalls::EnvBool0(inner) => get_env(&inner.0, ParamType::Bool, None),
+        HEVMCalls::EnvUint0(inner) => get_env(&inner.0, ParamType::Uint(256), None),
+        HEVMCalls::EnvInt0(inner) => get_env(&inner.0, ParamType::Int(256), None),
+        HEVMCalls::EnvAddress0(inner) => get_env(&inner.0, ParamType::Address, None),
+        HEVMCalls::EnvBytes320(inner) => get_env(&inner.0, ParamType::FixedBytes(32), None),
+        HEVMCalls::EnvString0(inner) => get_env(&inner.0, ParamType::String, None),
+        HEVMCalls::EnvBytes0(inner) => get_env(&inner.0, ParamType::Bytes, None),
+        HEVMCalls::EnvBool1(inner) => get_env(&inner.0, ParamType::Bool, Some(&inner.1)),
+        HEVMCalls::EnvUint1(inner) => get_env(&inner.0, ParamType::Uint(256), Some(&inner.1)),
+        HEVMCalls::EnvInt1(inner) => get_env(&inner.0, ParamType::Int(256), Some(&inner.1)),
+        HEVMCalls::EnvAddress1(inner) => get_env(&inner.0, ParamType::Address, Some(&inner.1)),
+        HEVMCalls::EnvBytes321(inner) => {
+            get_env(&inner.0, ParamType::FixedBytes(32), Some(&inner.1))
+        }
+        HEVMCalls::EnvString1(inner) => get_env(&inner.0, ParamType::String, Some(&inner.1)),
+        HEVMCalls::EnvBytes1(inner) => get_env(&inner.0, ParamType::Bytes, Some(&inner.1)),
         _ => return None,
     })
 }
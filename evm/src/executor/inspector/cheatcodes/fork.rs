@@ -0,0 +1,109 @@
+use super::Cheatcodes;
+use crate::{abi::HEVMCalls, executor::CHEATCODE_ADDRESS};
+use bytes::Bytes;
+use ethers::{
+    abi::AbiEncode,
+    types::{Address, U256},
+};
+use revm::{Database, EVMData, Env};
+
+/// Switches the EVM to the fork with the given environment.
+///
+/// All accounts in the current transaction that are not persistent are reloaded from the
+/// database. The executor reads accounts that are not persistent from the selected fork rather
+/// than from its cache, see [ForkedDb](crate::executor::fork::ForkedDb), so they get the state
+/// of the fork.
+fn switch_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    caller: Address,
+    env: Env,
+) {
+    // The test contract and the sender must keep their state, otherwise the test could not
+    // continue executing after the switch
+    state.forks.add_persistent([caller, data.env.tx.caller, CHEATCODE_ADDRESS]);
+
+    for (address, account) in data.subroutine.state().iter_mut() {
+        if !state.forks.is_persistent(address) {
+            account.info = data.db.basic(*address);
+            account.storage.clear();
+        }
+    }
+
+    data.env.block = env.block;
+    data.env.cfg.chain_id = env.cfg.chain_id;
+}
+
+fn create_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &EVMData<'_, DB>,
+    url: String,
+    block: Option<U256>,
+) -> Result<Bytes, Bytes> {
+    let block = block.map(block_number).transpose()?;
+    state
+        .forks
+        .create(url, block, data.env)
+        .map(|id| id.encode().into())
+        .map_err(|err| err.to_string().encode().into())
+}
+
+fn select_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    caller: Address,
+    id: U256,
+) -> Result<Bytes, Bytes> {
+    let env = state.forks.select(id).map_err(|err| err.to_string().encode())?;
+    switch_fork(state, data, caller, env);
+    Ok(Bytes::new())
+}
+
+fn roll_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    caller: Address,
+    id: Option<U256>,
+    block: U256,
+) -> Result<Bytes, Bytes> {
+    let active = state.forks.active();
+    let id = match id.or(active) {
+        Some(id) => id,
+        None => return Err("No fork is selected".to_string().encode().into()),
+    };
+
+    let env = state
+        .forks
+        .roll(id, block_number(block)?, data.env)
+        .map_err(|err| err.to_string().encode())?;
+
+    // Rolling the selected fork changes the state we are executing against
+    if active == Some(id) {
+        switch_fork(state, data, caller, env);
+    }
+    Ok(Bytes::new())
+}
+
+/// Converts a block number passed to a cheatcode, reverting if it does not fit in a `u64`
+fn block_number(block: U256) -> Result<u64, Bytes> {
+    if block > U256::from(u64::MAX) {
+        return Err(format!("Block number {} is too large", block).encode().into())
+    }
+    Ok(block.as_u64())
+}
+
+pub fn apply<DB: Database>(
+    state: &mut Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    caller: Address,
+    call: &HEVMCalls,
+) -> Option<Result<Bytes, Bytes>> {
+    Some(match call {
+        HEVMCalls::CreateFork0(inner) => create_fork(state, data, inner.0.clone(), None),
+        HEVMCalls::CreateFork1(inner) => create_fork(state, data, inner.0.clone(), Some(inner.1)),
+        HEVMCalls::SelectFork(inner) => select_fork(state, data, caller, inner.0),
+        HEVMCalls::RollFork0(inner) => roll_fork(state, data, caller, None, inner.0),
+        HEVMCalls::RollFork1(inner) => roll_fork(state, data, caller, Some(inner.0), inner.1),
+        _ => return None,
+    })
+}
@@ -0,0 +1,322 @@
+use crate::abi::HEVMCalls;
+use bytes::Bytes;
+use ethers::abi::{self, param_type::Reader, AbiEncode, ParamType, Token};
+use serde_json::value::RawValue;
+use std::{collections::BTreeMap, fmt};
+
+/// A JSON value.
+///
+/// Numbers are kept as their text, as [serde_json::Value] can only represent integers of up to 64
+/// bits exactly, and objects are kept in the alphabetical order of their keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Parses a JSON value from its text
+    fn parse(json: &str) -> Result<Value, serde_json::Error> {
+        let raw: &RawValue = serde_json::from_str(json)?;
+        let text = raw.get().trim();
+        Ok(match text.as_bytes().first().copied() {
+            Some(b'[') => Value::Array(
+                serde_json::from_str::<Vec<&RawValue>>(text)?
+                    .into_iter()
+                    .map(|raw| Value::parse(raw.get()))
+                    .collect::<Result<_, serde_json::Error>>()?,
+            ),
+            Some(b'{') => Value::Object(
+                serde_json::from_str::<BTreeMap<String, &RawValue>>(text)?
+                    .into_iter()
+                    .map(|(key, raw)| Ok((key, Value::parse(raw.get())?)))
+                    .collect::<Result<_, serde_json::Error>>()?,
+            ),
+            Some(b'"') => Value::String(serde_json::from_str(text)?),
+            Some(b't' | b'f') => Value::Bool(serde_json::from_str(text)?),
+            Some(b'n') => Value::Null,
+            _ => Value::Number(text.to_string()),
+        })
+    }
+
+    /// Returns the field `key` of an object
+    fn field(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array
+    fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the element at `index` of an array
+    fn element(&self, index: usize) -> Option<&Value> {
+        match self {
+            Value::Array(values) => values.get(index),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(number) => f.write_str(number),
+            Value::String(s) => write!(f, "{}", serde_json::Value::from(s.as_str())),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}:{}", serde_json::Value::from(key.as_str()), value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Returns the value at the JSONPath-like `path`, e.g. `$.contracts[0].address` or
+/// `.contracts[0]["address"]`. The root is `$`, `.` or an empty path.
+fn select<'a>(json: &'a Value, path: &str) -> Result<&'a Value, String> {
+    let path = path.trim();
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    if rest == "." {
+        rest = ""
+    }
+
+    let mut value = json;
+    while !rest.is_empty() {
+        let invalid = || format!("Invalid JSON path `{}`", path);
+        let (selected, remaining) = if let Some(field) = rest.strip_prefix('.') {
+            let end = field.find(&['.', '['][..]).unwrap_or(field.len());
+            (value.field(&field[..end]), &field[end..])
+        } else if let Some(key) = rest.strip_prefix('[') {
+            let end = key.find(']').ok_or_else(invalid)?;
+            let (key, remaining) = (key[..end].trim(), &key[end + 1..]);
+            let quoted = key
+                .strip_prefix('"')
+                .and_then(|key| key.strip_suffix('"'))
+                .or_else(|| key.strip_prefix('\'').and_then(|key| key.strip_suffix('\'')));
+            let selected = match quoted {
+                Some(field) => value.field(field),
+                None => value.element(key.parse::<usize>().map_err(|_| invalid())?),
+            };
+            (selected, remaining)
+        } else {
+            return Err(invalid())
+        };
+        value = selected.ok_or_else(|| format!("No value at `{}` in the JSON", path))?;
+        rest = remaining;
+    }
+    Ok(value)
+}
+
+/// Infers the ABI type of a JSON value.
+///
+/// Hex strings are addresses if they are 20 bytes long, `bytes32` if they are 32 bytes long and
+/// `bytes` otherwise, numbers are `uint256` or `int256` if negative, arrays are arrays of the type
+/// of their first element and objects are tuples of their values, in the alphabetical order of
+/// their keys.
+fn infer_type(value: &Value) -> Result<ParamType, String> {
+    Ok(match value {
+        Value::Null => return Err("Can't infer the type of `null`".to_string()),
+        Value::Bool(_) => ParamType::Bool,
+        Value::Number(number) if is_integer(number) && number.starts_with('-') => {
+            ParamType::Int(256)
+        }
+        Value::Number(number) if is_integer(number) => ParamType::Uint(256),
+        Value::Number(number) => {
+            return Err(format!("Can't infer the type of the non-integer number `{}`", number))
+        }
+        Value::String(s) => match s.strip_prefix("0x").filter(|digits| hex::decode(digits).is_ok())
+        {
+            Some(digits) if digits.len() == 40 => ParamType::Address,
+            Some(digits) if digits.len() == 64 => ParamType::FixedBytes(32),
+            Some(_) => ParamType::Bytes,
+            None => ParamType::String,
+        },
+        Value::Array(values) => ParamType::Array(Box::new(match values.first() {
+            Some(value) => infer_type(value)?,
+            // Empty arrays of all types are encoded the same
+            None => ParamType::Uint(256),
+        })),
+        Value::Object(fields) => {
+            ParamType::Tuple(fields.values().map(infer_type).collect::<Result<_, _>>()?)
+        }
+    })
+}
+
+/// Returns whether the text of a number is an integer, which may be negative
+fn is_integer(number: &str) -> bool {
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Converts a JSON value to a token of the ABI type
+fn to_token(value: &Value, ty: &ParamType) -> Result<Token, String> {
+    let mismatch = || format!("Can't convert `{}` to type `{}`", value, ty);
+    match ty {
+        ParamType::Array(inner) => {
+            let values = value.as_array().ok_or_else(mismatch)?;
+            Ok(Token::Array(
+                values.iter().map(|value| to_token(value, inner)).collect::<Result<_, _>>()?,
+            ))
+        }
+        ParamType::FixedArray(inner, size) => {
+            let values = value.as_array().filter(|values| values.len() == *size);
+            Ok(Token::FixedArray(
+                values
+                    .ok_or_else(mismatch)?
+                    .iter()
+                    .map(|value| to_token(value, inner))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        ParamType::Tuple(types) => {
+            let values: Vec<&Value> = match value {
+                Value::Array(values) => values.iter().collect(),
+                Value::Object(fields) => fields.values().collect(),
+                _ => return Err(mismatch()),
+            };
+            if values.len() != types.len() {
+                return Err(mismatch())
+            }
+            Ok(Token::Tuple(
+                values
+                    .into_iter()
+                    .zip(types)
+                    .map(|(value, ty)| to_token(value, ty))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        _ => {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(number) => number.clone(),
+                Value::Bool(b) => b.to_string(),
+                _ => return Err(mismatch()),
+            };
+            foundry_utils::parse_tokens(std::iter::once((ty, value.as_str())), true)
+                .map_err(|_| mismatch())
+                .map(|mut tokens| tokens.remove(0))
+        }
+    }
+}
+
+/// Parses the value at `path` in the JSON as the type `ty`, or as its inferred type, and returns
+/// it ABI-encoded
+fn parse_json(json: &str, path: &str, ty: Option<&str>) -> Result<Bytes, Bytes> {
+    let json = Value::parse(json).map_err(|err| format!("Invalid JSON: {}", err).encode())?;
+    let value = select(&json, path).map_err(|err| err.encode())?;
+    let ty = match ty {
+        Some(ty) => Reader::read(ty).map_err(|_| format!("Invalid type `{}`", ty).encode())?,
+        None => infer_type(value).map_err(|err| err.encode())?,
+    };
+    let token = to_token(value, &ty).map_err(|err| err.encode())?;
+
+    Ok(abi::encode(&[Token::Bytes(abi::encode(&[token]))]).into())
+}
+
+pub fn apply(call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
+    Some(match call {
+        HEVMCalls::ParseJson0(inner) => parse_json(&inner.0, "$", None),
+        HEVMCalls::ParseJson1(inner) => parse_json(&inner.0, &inner.1, None),
+        HEVMCalls::ParseJson2(inner) => parse_json(&inner.0, &inner.1, Some(&inner.2)),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, I256, U256};
+    use serde_json::json;
+
+    fn value(json: serde_json::Value) -> Value {
+        Value::parse(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn can_select_json_paths() {
+        let json = value(json!({ "contracts": [{ "name": "Token", "address.eth": "0x01" }] }));
+
+        assert_eq!(select(&json, "$").unwrap(), &json);
+        assert_eq!(select(&json, ".").unwrap(), &json);
+        assert_eq!(select(&json, "$.contracts[0].name").unwrap(), &value(json!("Token")));
+        assert_eq!(select(&json, ".contracts[0]['address.eth']").unwrap(), &value(json!("0x01")));
+        assert!(select(&json, "$.contracts[1]").is_err());
+        assert!(select(&json, "$.contracts[").is_err());
+    }
+
+    #[test]
+    fn can_convert_json_values() {
+        let json = value(json!({ "b": ["0x7109709ECfa91a80626fF3989D68f67F5b1DD12D"], "a": 1 }));
+        let ty = infer_type(&json).unwrap();
+        assert_eq!(
+            ty,
+            ParamType::Tuple(vec![
+                ParamType::Uint(256),
+                ParamType::Array(Box::new(ParamType::Address))
+            ])
+        );
+
+        let address: Address = "0x7109709ECfa91a80626fF3989D68f67F5b1DD12D".parse().unwrap();
+        assert_eq!(
+            to_token(&json, &ty).unwrap(),
+            Token::Tuple(vec![
+                Token::Uint(U256::one()),
+                Token::Array(vec![Token::Address(address)])
+            ])
+        );
+
+        // Numbers given as strings are only converted to numbers with an explicit type
+        let number = value(json!(U256::MAX.to_string()));
+        assert_eq!(infer_type(&number).unwrap(), ParamType::String);
+        assert_eq!(to_token(&number, &ParamType::Uint(256)).unwrap(), Token::Uint(U256::MAX));
+    }
+
+    #[test]
+    fn can_convert_large_json_integers() {
+        let number = Value::parse(&U256::MAX.to_string()).unwrap();
+        assert_eq!(infer_type(&number).unwrap(), ParamType::Uint(256));
+        assert_eq!(to_token(&number, &ParamType::Uint(256)).unwrap(), Token::Uint(U256::MAX));
+
+        let number = Value::parse(
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968",
+        )
+        .unwrap();
+        assert_eq!(infer_type(&number).unwrap(), ParamType::Int(256));
+        assert_eq!(
+            to_token(&number, &ParamType::Int(256)).unwrap(),
+            Token::Int(I256::MIN.into_raw())
+        );
+
+        let number = Value::parse("1.5e3").unwrap();
+        assert!(infer_type(&number).is_err());
+        assert_eq!(number.to_string(), "1.5e3");
+    }
+}
@@ -1,31 +1,51 @@
+/// What the cheatcodes are allowed to do outside of the EVM
+mod config;
+pub use config::CheatsConfig;
 /// Cheatcodes related to the execution environment.
 mod env;
-pub use env::{Prank, RecordAccess};
+pub use env::{Broadcast, Prank, RecordAccess, RecordedLog, RecordedLogs};
 /// Assertion helpers (such as `expectEmit`)
 mod expect;
-pub use expect::{ExpectedEmit, ExpectedRevert};
-/// Cheatcodes that interact with the external environment (FFI etc.)
+pub use expect::{
+    ActualCall, ExpectedCallData, ExpectedCalls, ExpectedEmit, ExpectedRevert, MockCallDataContext,
+    MockCallReturnData,
+};
+/// Cheatcodes that interact with the external environment (FFI, files etc.)
 mod ext;
+/// Cheatcodes that create and switch between forks
+mod fork;
 /// Cheatcodes that configure the fuzzer
 mod fuzz;
+/// Cheatcodes that parse JSON
+mod json;
+/// Cheatcodes that take and restore snapshots of the EVM state
+mod snapshot;
+pub use snapshot::Snapshot;
 /// Utility cheatcodes (`sign` etc.)
 mod util;
 
-use self::expect::{handle_expect_emit, handle_expect_revert};
+use self::expect::{find_mock, handle_expect_emit, handle_expect_revert, handle_expected_calls};
 use crate::{
     abi::HEVMCalls,
-    executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+    executor::{fork::MultiFork, CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
 };
 use bytes::Bytes;
 use ethers::{
     abi::{AbiDecode, AbiEncode, RawLog},
-    types::{Address, H256},
+    signers::LocalWallet,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, NameOrAddress, TransactionRequest, H256,
+        U256,
+    },
 };
 use revm::{
-    opcode, BlockEnv, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
-    Return,
+    opcode, return_ok, BlockEnv, CallInputs, CreateInputs, CreateScheme, Database, EVMData, Gas,
+    Inspector, Interpreter, Return,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
 };
-use std::collections::BTreeMap;
 
 /// An inspector that handles calls to various cheatcodes, each with their own behavior.
 ///
@@ -33,8 +53,8 @@ use std::collections::BTreeMap;
 /// mocking addresses, signatures and altering call reverts.
 #[derive(Clone, Debug, Default)]
 pub struct Cheatcodes {
-    /// Whether FFI is enabled or not
-    pub ffi: bool,
+    /// What the cheatcodes are allowed to do outside of the EVM, such as FFI
+    pub config: CheatsConfig,
 
     /// The block environment
     ///
@@ -54,19 +74,50 @@ pub struct Cheatcodes {
     /// Recorded storage reads and writes
     pub accesses: Option<RecordAccess>,
 
+    /// Recorded logs
+    pub recorded_logs: Option<RecordedLogs>,
+
     /// Mocked calls
-    pub mocked_calls: BTreeMap<Address, BTreeMap<Bytes, Bytes>>,
+    pub mocked_calls: BTreeMap<Address, BTreeMap<MockCallDataContext, MockCallReturnData>>,
 
     /// Expected calls
-    pub expected_calls: BTreeMap<Address, Vec<Bytes>>,
+    pub expected_calls: BTreeMap<Address, ExpectedCalls>,
 
     /// Expected emits
     pub expected_emits: Vec<ExpectedEmit>,
+
+    /// State snapshots, keyed by the ID returned from `snapshot()`
+    pub snapshots: BTreeMap<U256, Snapshot>,
+
+    /// Forks created with `createFork`
+    ///
+    /// This is shared with the executor's database, which reads from the selected fork.
+    pub forks: MultiFork,
+
+    /// Broadcast information
+    pub broadcast: Option<Broadcast>,
+
+    /// The calls and contract creations recorded while broadcasting, as transactions to send
+    pub broadcastable_transactions: VecDeque<TypedTransaction>,
+
+    /// The depth of the broadcast call or contract creation that is still running, whose
+    /// transaction is dropped if it fails
+    pub pending_broadcast: Option<u64>,
+
+    /// The wallets of the keys remembered with `rememberKey`, which sign the transactions
+    /// broadcast from their addresses
+    pub script_wallets: Vec<LocalWallet>,
+
+    /// Whether the nonce of `tx.origin` has been corrected for the current transaction
+    pub corrected_nonce: bool,
+
+    /// The number of lines read from each file with `readLine`
+    pub read_lines: BTreeMap<PathBuf, usize>,
 }
 
 impl Cheatcodes {
-    pub fn new(ffi: bool, block: BlockEnv) -> Self {
-        Self { ffi, block: Some(block), ..Default::default() }
+    pub fn new(config: CheatsConfig, block: BlockEnv) -> Self {
+        Self { config, block: Some(block), ..Default::default() }
     }
 
     fn apply_cheatcode<DB: Database>(
@@ -83,9 +134,31 @@ impl Cheatcodes {
             .or_else(|| util::apply(self, data, &decoded))
             .or_else(|| expect::apply(self, data, &decoded))
             .or_else(|| fuzz::apply(data, &decoded))
-            .or_else(|| ext::apply(self.ffi, &decoded))
+            .or_else(|| snapshot::apply(self, data, &decoded))
+            .or_else(|| fork::apply(self, data, caller, &decoded))
+            .or_else(|| ext::apply(self, &decoded))
+            .or_else(|| json::apply(&decoded))
             .ok_or_else(|| "Cheatcode was unhandled. This is a bug.".to_string().encode())?
     }
+
+    /// Drops the transaction recorded for the broadcast call or contract creation ending at the
+    /// current depth if it failed, and gives its nonce back to the broadcasting account
+    fn end_broadcast<DB: Database>(&mut self, data: &mut EVMData<'_, DB>, status: Return) {
+        if self.pending_broadcast != Some(data.subroutine.depth()) {
+            return
+        }
+        self.pending_broadcast = None;
+
+        if matches!(status, return_ok!()) {
+            return
+        }
+        if let Some(tx) = self.broadcastable_transactions.pop_back() {
+            if let (Some(from), Some(nonce)) = (tx.from(), tx.nonce()) {
+                data.subroutine.load_account(*from, data.db);
+                data.subroutine.state().get_mut(from).unwrap().info.nonce = nonce.as_u64();
+            }
+        }
+    }
 }
 
 impl<DB> Inspector<DB> for Cheatcodes
@@ -96,8 +169,13 @@ where
         &mut self,
         data: &mut EVMData<'_, DB>,
         call: &mut CallInputs,
-        _: bool,
+        is_static: bool,
     ) -> (Return, Gas, Bytes) {
+        // Every transaction needs its own nonce correction, see `broadcast`
+        if data.subroutine.depth() == 0 {
+            self.corrected_nonce = false;
+        }
+
         if call.contract == CHEATCODE_ADDRESS {
             match self.apply_cheatcode(data, call.context.caller, call) {
                 Ok(retdata) => (Return::Return, Gas::new(call.gas_limit), retdata),
@@ -105,23 +183,16 @@ where
             }
         } else if call.contract != HARDHAT_CONSOLE_ADDRESS {
             // Handle expected calls
-            if let Some(expecteds) = self.expected_calls.get_mut(&call.contract) {
-                if let Some(found_match) = expecteds.iter().position(|expected| {
-                    expected.len() <= call.input.len() && expected == &call.input[..expected.len()]
-                }) {
-                    expecteds.remove(found_match);
-                }
+            if let Some(expected_calls) = self.expected_calls.get_mut(&call.contract) {
+                expected_calls.record(ActualCall::new(call));
             }
 
             // Handle mocked calls
-            if let Some(mocks) = self.mocked_calls.get(&call.contract) {
-                if let Some(mock_retdata) = mocks.get(&call.input) {
-                    return (Return::Return, Gas::new(call.gas_limit), mock_retdata.clone())
-                } else if let Some((_, mock_retdata)) =
-                    mocks.iter().find(|(mock, _)| *mock == &call.input[..mock.len()])
-                {
-                    return (Return::Return, Gas::new(call.gas_limit), mock_retdata.clone())
-                }
+            if let Some(mock) =
+                self.mocked_calls.get(&call.contract).and_then(|mocks| find_mock(mocks, call))
+            {
+                let status = if mock.reverts { Return::Revert } else { Return::Return };
+                return (status, Gas::new(call.gas_limit), mock.data.clone())
             }
 
             // Apply our prank
@@ -142,6 +213,39 @@ where
                 }
             }
 
+            // Record the call as a transaction if we are broadcasting
+            if let Some(broadcast) = &self.broadcast {
+                if !is_static &&
+                    data.subroutine.depth() == broadcast.depth &&
+                    call.context.caller == broadcast.original_caller
+                {
+                    // The call is made by the broadcasting account, which increments its nonce
+                    data.subroutine.load_account(broadcast.origin, data.db);
+                    let account = data.subroutine.state().get_mut(&broadcast.origin).unwrap();
+                    let nonce = account.info.nonce;
+                    account.info.nonce += 1;
+
+                    call.context.caller = broadcast.origin;
+                    call.transfer.source = broadcast.origin;
+
+                    self.broadcastable_transactions.push_back(TypedTransaction::Legacy(
+                        TransactionRequest {
+                            from: Some(broadcast.origin),
+                            to: Some(NameOrAddress::Address(call.contract)),
+                            value: Some(call.transfer.value),
+                            data: Some(call.input.to_vec().into()),
+                            nonce: Some(nonce.into()),
+                            ..Default::default()
+                        },
+                    ));
+                    self.pending_broadcast = Some(data.subroutine.depth());
+
+                    if broadcast.single_call {
+                        self.broadcast = None;
+                    }
+                }
+            }
+
             (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
         } else {
             (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
@@ -197,11 +301,20 @@ where
         Return::Continue
     }
 
-    fn log(&mut self, _: &mut EVMData<'_, DB>, _: &Address, topics: &[H256], data: &Bytes) {
+    fn log(&mut self, _: &mut EVMData<'_, DB>, address: &Address, topics: &[H256], data: &Bytes) {
         // Match logs if `expectEmit` has been called
         if !self.expected_emits.is_empty() {
             handle_expect_emit(self, RawLog { topics: topics.to_vec(), data: data.to_vec() });
         }
+
+        // Record logs if `recordLogs` has been called
+        if let Some(recorded_logs) = &mut self.recorded_logs {
+            recorded_logs.entries.push(RecordedLog {
+                emitter: *address,
+                topics: topics.to_vec(),
+                data: data.to_vec(),
+            });
+        }
     }
 
     fn call_end(
@@ -217,6 +330,9 @@ where
             return (status, remaining_gas, retdata)
         }
 
+        // Drop the transaction of a broadcast call that failed, as it would fail on chain too
+        self.end_broadcast(data, status);
+
         // Clean up pranks
         if let Some(prank) = &self.prank {
             data.env.tx.caller = prank.prank_origin;
@@ -255,21 +371,10 @@ where
 
         // If the depth is 0, then this is the root call terminating
         if data.subroutine.depth() == 0 {
-            // Handle expected calls that were not fulfilled
-            if let Some((address, expecteds)) =
-                self.expected_calls.iter().find(|(_, expecteds)| !expecteds.is_empty())
-            {
-                return (
-                    Return::Revert,
-                    remaining_gas,
-                    format!(
-                        "Expected a call to 0x{} with data {}, but got none",
-                        address,
-                        ethers::types::Bytes::from(expecteds[0].clone())
-                    )
-                    .encode()
-                    .into(),
-                )
+            // Handle expected calls that were not made as expected
+            let expected_calls = std::mem::take(&mut self.expected_calls);
+            if let Err(retdata) = handle_expected_calls(&expected_calls) {
+                return (Return::Revert, remaining_gas, retdata)
             }
 
             // Check if we have any leftover expected emits
@@ -308,6 +413,46 @@ where
             }
         }
 
+        // Record the contract creation as a transaction if we are broadcasting
+        if let Some(broadcast) = &self.broadcast {
+            if data.subroutine.depth() == broadcast.depth &&
+                call.caller == broadcast.original_caller
+            {
+                // Accounts can only deploy contracts with `CREATE`, so `CREATE2` can not be
+                // broadcast
+                if call.scheme != CreateScheme::Create {
+                    return (
+                        Return::Revert,
+                        None,
+                        Gas::new(call.gas_limit),
+                        "CREATE2 is not supported while broadcasting".to_string().encode().into(),
+                    )
+                }
+
+                // The creation increments the nonce of the broadcasting account, which also
+                // determines the address of the new contract
+                data.subroutine.load_account(broadcast.origin, data.db);
+                let nonce = data.subroutine.account(broadcast.origin).info.nonce;
+                call.caller = broadcast.origin;
+
+                self.broadcastable_transactions.push_back(TypedTransaction::Legacy(
+                    TransactionRequest {
+                        from: Some(broadcast.origin),
+                        to: None,
+                        value: Some(call.value),
+                        data: Some(call.init_code.to_vec().into()),
+                        nonce: Some(nonce.into()),
+                        ..Default::default()
+                    },
+                ));
+                self.pending_broadcast = Some(data.subroutine.depth());
+
+                if broadcast.single_call {
+                    self.broadcast = None;
+                }
+            }
+        }
+
         (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
     }
 
@@ -320,6 +465,9 @@ where
         remaining_gas: Gas,
         retdata: Bytes,
     ) -> (Return, Option<Address>, Gas, Bytes) {
+        // Drop the transaction of a broadcast creation that failed, as it would fail on chain too
+        self.end_broadcast(data, status);
+
         // Clean up pranks
         if let Some(prank) = &self.prank {
             data.env.tx.caller = prank.prank_origin;
@@ -0,0 +1,113 @@
+use super::Cheatcodes;
+use crate::{
+    abi::HEVMCalls,
+    executor::{fork::ForkState, StateChangeset},
+};
+use bytes::Bytes;
+use ethers::{abi::AbiEncode, types::U256};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    BlockEnv, Database, EVMData,
+};
+
+/// A snapshot of the EVM state taken with `snapshot()`.
+///
+/// The snapshot stores the state of every account touched in the transaction up to the point the
+/// snapshot was taken. Accounts and storage slots that are not part of the snapshot are assumed to
+/// be unchanged from the executor's database, which is why the executor has to
+/// [preserve](Snapshot::preserve) the previous values of everything it commits after a snapshot
+/// was taken.
+///
+/// Changes committed to a fork are not preserved, since the snapshot holds a copy of the forks
+/// instead.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// The accounts at the time of the snapshot
+    pub accounts: StateChangeset,
+    /// The block environment at the time of the snapshot
+    pub block: BlockEnv,
+    /// The chain ID at the time of the snapshot, which depends on the selected fork
+    pub chain_id: U256,
+    /// The forks and the selected fork at the time of the snapshot
+    pub forks: ForkState,
+}
+
+impl Snapshot {
+    /// Records the values that are about to be overwritten by committing `changeset` to `db`, if
+    /// the snapshot does not already contain them.
+    pub fn preserve<DB: DatabaseRef>(&mut self, db: &CacheDB<DB>, changeset: &StateChangeset) {
+        for (address, changed) in changeset {
+            let account = self.accounts.entry(*address).or_insert_with(|| {
+                let mut account = changed.clone();
+                account.info = DatabaseRef::basic(db, *address);
+                account.storage.clear();
+                account
+            });
+
+            for slot in changed.storage.keys() {
+                account
+                    .storage
+                    .entry(*slot)
+                    .or_insert_with(|| DatabaseRef::storage(db, *address, *slot));
+            }
+        }
+    }
+}
+
+fn snapshot<DB: Database>(state: &mut Cheatcodes, data: &mut EVMData<'_, DB>) -> U256 {
+    let id = U256::from(state.snapshots.len());
+    state.snapshots.insert(
+        id,
+        Snapshot {
+            accounts: data.subroutine.state().clone(),
+            block: data.env.block.clone(),
+            chain_id: data.env.cfg.chain_id,
+            forks: state.forks.checkpoint(),
+        },
+    );
+    id
+}
+
+fn revert_to<DB: Database>(state: &Cheatcodes, data: &mut EVMData<'_, DB>, id: U256) -> bool {
+    let snapshot = match state.snapshots.get(&id) {
+        Some(snapshot) => snapshot,
+        None => return false,
+    };
+
+    // Accounts that are not part of the snapshot are read from the fork that was selected at the
+    // time of the snapshot
+    state.forks.revert_to(&snapshot.forks);
+
+    // We never remove accounts from the state, since the subroutine might still have to revert
+    // changes to them. Instead, accounts that were not part of the snapshot are reset to the
+    // values in the database.
+    let accounts = data.subroutine.state();
+    for (address, account) in accounts.iter_mut() {
+        match snapshot.accounts.get(address) {
+            Some(snapshotted) => *account = snapshotted.clone(),
+            None => {
+                account.info = data.db.basic(*address);
+                account.storage.clear();
+            }
+        }
+    }
+    for (address, snapshotted) in &snapshot.accounts {
+        accounts.entry(*address).or_insert_with(|| snapshotted.clone());
+    }
+
+    data.env.block = snapshot.block.clone();
+    data.env.cfg.chain_id = snapshot.chain_id;
+    true
+}
+
+pub fn apply<DB: Database>(
+    state: &mut Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    call: &HEVMCalls,
+) -> Option<Result<Bytes, Bytes>> {
+    Some(match call {
+        HEVMCalls::Snapshot(_) => Ok(snapshot(state, data).encode().into()),
+        HEVMCalls::RevertTo(inner) => Ok(revert_to(state, data, inner.0).encode().into()),
+        _ => return None,
+    })
+}
@@ -3,6 +3,7 @@ use bytes::Bytes;
 use ethers::{
     abi::AbiEncode,
     prelude::{k256::ecdsa::SigningKey, LocalWallet, Signer},
+    signers::{coins_bip39::English, MnemonicBuilder},
     types::{H256, U256},
     utils,
 };
@@ -10,7 +11,11 @@ use revm::{Database, EVMData};
 
 use super::Cheatcodes;
 
-fn addr(private_key: U256) -> Result<Bytes, Bytes> {
+/// The derivation path of the keys of a mnemonic, without the index, that `cast` and most wallets
+/// use by default
+const DEFAULT_DERIVATION_PATH_PREFIX: &str = "m/44'/60'/0'/0/";
+
+fn parse_private_key(private_key: U256) -> Result<SigningKey, Bytes> {
     if private_key.is_zero() {
         return Err("Private key cannot be 0.".to_string().encode().into())
     }
@@ -18,20 +23,17 @@ fn addr(private_key: U256) -> Result<Bytes, Bytes> {
     let mut bytes: [u8; 32] = [0; 32];
     private_key.to_big_endian(&mut bytes);
 
-    let key = SigningKey::from_bytes(&bytes).map_err(|err| err.to_string().encode())?;
+    SigningKey::from_bytes(&bytes).map_err(|err| err.to_string().encode().into())
+}
+
+fn addr(private_key: U256) -> Result<Bytes, Bytes> {
+    let key = parse_private_key(private_key)?;
     let addr = utils::secret_key_to_address(&key);
     Ok(addr.encode().into())
 }
 
 fn sign(private_key: U256, digest: H256, chain_id: U256) -> Result<Bytes, Bytes> {
-    if private_key.is_zero() {
-        return Err("Private key cannot be 0.".to_string().encode().into())
-    }
-
-    let mut bytes: [u8; 32] = [0; 32];
-    private_key.to_big_endian(&mut bytes);
-
-    let key = SigningKey::from_bytes(&bytes).map_err(|err| err.to_string().encode())?;
+    let key = parse_private_key(private_key)?;
     let wallet = LocalWallet::from(key).with_chain_id(chain_id.as_u64());
 
     // The `ecrecover` precompile does not use EIP-155
@@ -48,6 +50,34 @@ fn sign(private_key: U256, digest: H256, chain_id: U256) -> Result<Bytes, Bytes>
     Ok((sig.v, r_bytes, s_bytes).encode().into())
 }
 
+/// Derives the private key at `index` of the derivation path `path` from the mnemonic, the same
+/// way `cast` derives the keys of `--mnemonic-path`
+fn derive_key(mnemonic: &str, path: &str, index: u32) -> Result<Bytes, Bytes> {
+    let derivation_path = format!("{}{}", path, index);
+    let wallet = MnemonicBuilder::<English>::default()
+        .phrase(mnemonic)
+        .derivation_path(&derivation_path)
+        .map_err(|err| err.to_string().encode())?
+        .build()
+        .map_err(|err| err.to_string().encode())?;
+
+    let private_key = U256::from_big_endian(wallet.signer().to_bytes().as_slice());
+    Ok(private_key.encode().into())
+}
+
+/// Remembers the wallet of the private key, so that the transactions broadcast from its address
+/// are signed with it
+fn remember_key(state: &mut Cheatcodes, private_key: U256, chain_id: U256) -> Result<Bytes, Bytes> {
+    let key = parse_private_key(private_key)?;
+    let wallet = LocalWallet::from(key).with_chain_id(chain_id.as_u64());
+    let address = wallet.address();
+
+    if !state.script_wallets.iter().any(|wallet| wallet.address() == address) {
+        state.script_wallets.push(wallet);
+    }
+    Ok(address.encode().into())
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
@@ -56,10 +86,17 @@ pub fn apply<DB: Database>(
     Some(match call {
         HEVMCalls::Addr(inner) => addr(inner.0),
         HEVMCalls::Sign(inner) => sign(inner.0, inner.1.into(), data.env.cfg.chain_id),
+        HEVMCalls::DeriveKey0(inner) => {
+            derive_key(&inner.0, DEFAULT_DERIVATION_PATH_PREFIX, inner.1)
+        }
+        HEVMCalls::DeriveKey1(inner) => derive_key(&inner.0, &inner.1, inner.2),
+        HEVMCalls::RememberKey(inner) => remember_key(state, inner.0, data.env.cfg.chain_id),
         HEVMCalls::Label(inner) => {
             state.labels.insert(inner.0, inner.1.clone());
             Ok(Bytes::new())
         }
+        // Breakpoints only stop the debugger, which finds them in the calldata of the call
+        HEVMCalls::Breakpoint(_) => Ok(Bytes::new()),
         _ => return None,
     })
 }
@@ -0,0 +1,68 @@
+use crate::coverage::{HitMap, HitMaps};
+use bytes::Bytes;
+use ethers::{types::H256, utils::keccak256};
+use revm::{opcode, Database, EVMData, Inspector, Interpreter, Return};
+
+/// An inspector that records which instructions and branches are executed.
+#[derive(Default, Debug)]
+pub struct CoverageCollector {
+    /// The hit maps of all executed bytecode
+    pub maps: HitMaps,
+    /// The hash of the bytecode that is executing at each call depth, starting with the outermost
+    /// call
+    contexts: Vec<H256>,
+}
+
+impl<DB> Inspector<DB> for CoverageCollector
+where
+    DB: Database,
+{
+    fn initialize_interp(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _: bool,
+    ) -> Return {
+        let code: &Bytes = &interpreter.contract.code;
+        let code_hash = H256::from(keccak256(code));
+        self.maps.0.entry(code_hash).or_insert_with(|| HitMap::new(code.clone()));
+
+        let depth = frame_index(data);
+        self.contexts.truncate(depth);
+        self.contexts.push(code_hash);
+
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let depth = frame_index(data);
+        let hit_map = match self.contexts.get(depth).and_then(|hash| self.maps.0.get_mut(hash)) {
+            Some(hit_map) => hit_map,
+            None => return Return::Continue,
+        };
+
+        let pc = interpreter.program_counter();
+        hit_map.hit(pc);
+        if interpreter.contract.code[pc] == opcode::JUMPI {
+            // The condition is the second item on the stack, after the jump destination
+            if let Ok(condition) = interpreter.stack().peek(1) {
+                hit_map.branch(pc, !condition.is_zero());
+            }
+        }
+
+        Return::Continue
+    }
+}
+
+/// Returns the index of the executing frame in [CoverageCollector::contexts].
+///
+/// revm has already entered the frame when the interpreter is initialized, so the outermost call
+/// is at depth 1.
+fn frame_index<DB: Database>(data: &EVMData<'_, DB>) -> usize {
+    (data.subroutine.depth() as usize).saturating_sub(1)
+}
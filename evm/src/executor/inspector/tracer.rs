@@ -5,7 +5,7 @@ use crate::{
     },
     trace::{
         CallTrace, CallTraceArena, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
-        RawOrDecodedReturnData,
+        RawOrDecodedReturnData, StorageChange,
     },
     CallKind,
 };
@@ -14,7 +14,10 @@ use ethers::{
     abi::RawLog,
     types::{Address, H256, U256},
 };
-use revm::{return_ok, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Return};
+use revm::{
+    opcode, return_ok, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    Return,
+};
 
 /// An inspector that collects call traces.
 #[derive(Default, Debug)]
@@ -52,9 +55,14 @@ impl Tracer {
         output: Vec<u8>,
         address: Option<Address>,
     ) {
-        let trace = &mut self.traces.arena
-            [self.trace_stack.pop().expect("more traces were filled than started")]
-        .trace;
+        let idx = self.trace_stack.pop().expect("more traces were filled than started");
+
+        // The storage writes of a failed call, and of its subcalls, are reverted
+        if !success {
+            self.clear_storage_changes(idx);
+        }
+
+        let trace = &mut self.traces.arena[idx].trace;
         trace.success = success;
         trace.gas_cost = cost;
         trace.output = RawOrDecodedReturnData::Raw(output);
@@ -63,6 +71,36 @@ impl Tracer {
             trace.address = address;
         }
     }
+
+    /// Clears the storage changes of a trace and all of its subtraces
+    fn clear_storage_changes(&mut self, idx: usize) {
+        let mut stack = vec![idx];
+        while let Some(idx) = stack.pop() {
+            let node = &mut self.traces.arena[idx];
+            node.trace.storage_changes.clear();
+            stack.extend_from_slice(&node.children);
+        }
+    }
+
+    /// Records a write to a storage slot in the ongoing trace
+    fn record_storage_change(&mut self, address: Address, slot: U256, old: U256, new: U256) {
+        let trace =
+            &mut self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")].trace;
+        match trace
+            .storage_changes
+            .iter_mut()
+            .find(|change| change.address == address && change.slot == slot)
+        {
+            Some(change) => change.new_value = new,
+            None => trace.storage_changes.push(StorageChange {
+                address,
+                slot,
+                old_value: old,
+                new_value: new,
+                label: None,
+            }),
+        }
+    }
 }
 
 impl<DB> Inspector<DB> for Tracer
@@ -88,6 +126,34 @@ where
         (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
     }
 
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _: bool,
+    ) -> Return {
+        if interpreter.contract.code[interpreter.program_counter()] == opcode::SSTORE {
+            let slot = try_or_continue!(interpreter.stack().peek(0));
+            let new = try_or_continue!(interpreter.stack().peek(1));
+            let address = interpreter.contract().address;
+
+            // The old value is not loaded through the subroutine, since that would make the slot
+            // warm and change the gas cost of the SSTORE
+            let loaded = data
+                .subroutine
+                .state()
+                .get(&address)
+                .and_then(|account| account.storage.get(&slot).copied());
+            let old = match loaded {
+                Some(old) => old,
+                None => data.db.storage(address, slot),
+            };
+            self.record_storage_change(address, slot, old, new);
+        }
+
+        Return::Continue
+    }
+
     fn log(&mut self, _: &mut EVMData<'_, DB>, _: &Address, topics: &[H256], data: &Bytes) {
         let node = &mut self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")];
         node.ordering.push(LogCallOrder::Log(node.logs.len()));
@@ -24,21 +24,26 @@ pub use revm::SpecId;
 /// Executor database trait
 pub use revm::db::DatabaseRef;
 
-use self::inspector::{InspectorData, InspectorStackConfig};
-use crate::{debug::DebugArena, trace::CallTraceArena, CALLER};
+use self::{
+    fork::ForkedDb,
+    inspector::{InspectorData, InspectorStackConfig},
+};
+use crate::{coverage::HitMaps, debug::DebugArena, trace::CallTraceArena, CALLER};
 use bytes::Bytes;
 use ethers::{
     abi::{Abi, Detokenize, RawLog, Tokenize},
-    prelude::{decode_function_data, encode_function_data, Address, U256},
+    prelude::{decode_function_data, encode_function_data, Address, LocalWallet, U256},
+    types::transaction::eip2718::TypedTransaction,
 };
 use eyre::Result;
 use foundry_utils::IntoFunction;
 use hashbrown::HashMap;
 use revm::{
     db::{CacheDB, DatabaseCommit, EmptyDB},
-    return_ok, Account, BlockEnv, CreateScheme, Env, Return, TransactOut, TransactTo, TxEnv, EVM,
+    return_ok, Account, AccountInfo, BlockEnv, CreateScheme, Env, Return, TransactOut, TransactTo,
+    TxEnv, EVM,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
 /// A mapping of addresses to their changed state.
 pub type StateChangeset = HashMap<Address, Account>;
@@ -57,6 +62,7 @@ pub enum EvmError {
         debug: Option<DebugArena>,
         labels: BTreeMap<Address, String>,
         state_changeset: Option<StateChangeset>,
+        coverage: Option<HitMaps>,
     },
     /// Error which occurred during ABI encoding/decoding
     #[error(transparent)]
@@ -79,6 +85,10 @@ pub struct DeployResult {
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
+    /// The changeset of the state that was committed to the database
+    pub state_changeset: StateChangeset,
+    /// The coverage info collected during the deployment
+    pub coverage: Option<HitMaps>,
 }
 
 /// The result of a call.
@@ -102,9 +112,11 @@ pub struct CallResult<D: Detokenize> {
     pub debug: Option<DebugArena>,
     /// The changeset of the state.
     ///
-    /// This is only present if the changed state was not committed to the database (i.e. if you
-    /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
+    /// If the call was committing (i.e. if you used `call_committing` or `call_raw_committing`),
+    /// this is the changeset that was committed to the database.
     pub state_changeset: Option<StateChangeset>,
+    /// The coverage info collected during the call
+    pub coverage: Option<HitMaps>,
 }
 
 /// The result of a raw call.
@@ -130,9 +142,15 @@ pub struct RawCallResult {
     pub debug: Option<DebugArena>,
     /// The changeset of the state.
     ///
-    /// This is only present if the changed state was not committed to the database (i.e. if you
-    /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
+    /// If the call was committing (i.e. if you used `call_committing` or `call_raw_committing`),
+    /// this is the changeset that was committed to the database.
     pub state_changeset: Option<StateChangeset>,
+    /// The coverage info collected during the call
+    pub coverage: Option<HitMaps>,
+    /// The transactions recorded by the broadcast cheatcodes
+    pub transactions: Option<VecDeque<TypedTransaction>>,
+    /// The wallets of the keys remembered with the `rememberKey` cheatcode
+    pub script_wallets: Vec<LocalWallet>,
 }
 
 impl Default for RawCallResult {
@@ -148,10 +166,14 @@ impl Default for RawCallResult {
             traces: None,
             debug: None,
             state_changeset: None,
+            coverage: None,
+            transactions: None,
+            script_wallets: Vec::new(),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Executor<DB: DatabaseRef> {
     // Note: We do not store an EVM here, since we are really
     // only interested in the database. REVM's `EVM` is a thin
@@ -194,23 +216,72 @@ where
 
     /// Set the balance of an account.
     pub fn set_balance(&mut self, address: Address, amount: U256) {
-        let mut account = self.db.basic(address);
+        let mut account = self.database().basic(address);
         account.balance = amount;
 
-        self.db.insert_cache(address, account);
+        self.insert_account(address, account);
     }
 
     /// Gets the balance of an account
     pub fn get_balance(&self, address: Address) -> U256 {
-        self.db.basic(address).balance
+        self.database().basic(address).balance
     }
 
     /// Set the nonce of an account.
     pub fn set_nonce(&mut self, address: Address, nonce: u64) {
-        let mut account = self.db.basic(address);
+        let mut account = self.database().basic(address);
         account.nonce = nonce;
 
-        self.db.insert_cache(address, account);
+        self.insert_account(address, account);
+    }
+
+    /// Gets the nonce of an account
+    pub fn get_nonce(&self, address: Address) -> u64 {
+        self.database().basic(address).nonce
+    }
+
+    /// Gets the code of an account
+    pub fn get_code(&self, address: Address) -> Bytes {
+        let db = self.database();
+        let account = db.basic(address);
+        account.code.unwrap_or_else(|| db.code_by_hash(account.code_hash))
+    }
+
+    /// Gets the value of a storage slot of an account
+    pub fn get_storage(&self, address: Address, slot: U256) -> U256 {
+        self.database().storage(address, slot)
+    }
+
+    /// The database calls are executed against, which reads accounts that are not persistent from
+    /// the selected fork, if any
+    fn database(&self) -> ForkedDb<'_, DB> {
+        ForkedDb {
+            db: &self.db,
+            forks: self.inspector_config.cheatcodes.as_ref().map(|cheatcodes| &cheatcodes.forks),
+        }
+    }
+
+    /// Inserts an account into the selected fork if it is not persistent, and into the cache
+    /// otherwise.
+    fn insert_account(&mut self, address: Address, account: AccountInfo) {
+        let account = match &self.inspector_config.cheatcodes {
+            Some(cheatcodes) => cheatcodes.forks.insert_account(address, account),
+            None => Some(account),
+        };
+        if let Some(account) = account {
+            self.db.insert_cache(address, account);
+        }
+    }
+
+    /// The environment calls and transactions are executed in
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Mutable access to the environment calls and transactions are executed in, e.g. to advance
+    /// the block.
+    pub fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
     }
 
     /// Calls the `setUp()` function on a contract.
@@ -243,6 +314,8 @@ where
             traces,
             debug,
             state_changeset,
+            coverage,
+            ..
         } = self.call_raw_committing(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -257,6 +330,7 @@ where
                     traces,
                     debug,
                     state_changeset,
+                    coverage,
                 })
             }
             _ => {
@@ -272,6 +346,7 @@ where
                     debug,
                     labels,
                     state_changeset,
+                    coverage,
                 })
             }
         }
@@ -287,42 +362,20 @@ where
         calldata: Bytes,
         value: U256,
     ) -> Result<RawCallResult> {
-        let stipend = stipend(&calldata, self.env.cfg.spec_id);
-
-        // Build VM
-        let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
-        evm.database(&mut self.db);
-
-        // Run the call
-        let mut inspector = self.inspector_config.stack();
-        let (status, out, gas, _) = evm.inspect_commit(&mut inspector);
-        let result = match out {
-            TransactOut::Call(data) => data,
-            _ => Bytes::default(),
-        };
-
-        let InspectorData { logs, labels, traces, debug, cheatcodes } =
-            inspector.collect_inspector_states();
-
-        // Persist the changed block environment
-        self.inspector_config.block = evm.env.block.clone();
-
-        // Persist cheatcode state
-        self.inspector_config.cheatcodes = cheatcodes;
+        let env = self.build_env(from, TransactTo::Call(to), calldata, value);
+        self.execute_committing(env)
+    }
 
-        Ok(RawCallResult {
-            status,
-            reverted: !matches!(status, return_ok!()),
-            result,
-            gas,
-            stipend,
-            logs: logs.to_vec(),
-            labels,
-            traces,
-            debug,
-            state_changeset: None,
-        })
+    /// Executes a transaction on the current state of the VM.
+    ///
+    /// Unlike [Executor::call_raw_committing], the transaction is executed in the environment of
+    /// the executor as is, so it pays for its gas at its own gas price and is not exempt from the
+    /// base fee. This is what a node does when it includes a transaction in a block.
+    ///
+    /// The state after the transaction is persisted.
+    pub fn transact_committing(&mut self, tx: TxEnv) -> Result<RawCallResult> {
+        let env = Env { tx, ..self.env.clone() };
+        self.execute_committing(env)
     }
 
     /// Performs a call to an account on the current state of the VM.
@@ -350,6 +403,8 @@ where
             traces,
             debug,
             state_changeset,
+            coverage,
+            ..
         } = self.call_raw(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -364,6 +419,7 @@ where
                     traces,
                     debug,
                     state_changeset,
+                    coverage,
                 })
             }
             _ => {
@@ -379,6 +435,7 @@ where
                     debug,
                     labels,
                     state_changeset,
+                    coverage,
                 })
             }
         }
@@ -394,23 +451,109 @@ where
         calldata: Bytes,
         value: U256,
     ) -> Result<RawCallResult> {
-        let stipend = stipend(&calldata, self.env.cfg.spec_id);
+        let env = self.build_env(from, TransactTo::Call(to), calldata, value);
+        self.execute(env)
+    }
+
+    /// Executes a transaction on the current state of the VM.
+    ///
+    /// See [Executor::transact_committing] for how this differs from [Executor::call_raw].
+    ///
+    /// The state after the transaction is not persisted.
+    pub fn transact(&self, tx: TxEnv) -> Result<RawCallResult> {
+        let env = Env { tx, ..self.env.clone() };
+        self.execute(env)
+    }
+
+    /// Deploys a contract and commits the new state to the underlying database.
+    pub fn deploy(&mut self, from: Address, code: Bytes, value: U256) -> Result<DeployResult> {
+        let mut evm = EVM::new();
+        evm.env = self.build_env(from, TransactTo::Create(CreateScheme::Create), code, value);
+        evm.database(self.database());
+
+        let mut inspector = self.inspector_config.stack();
+        let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
+        let address = match status {
+            return_ok!() => {
+                if let TransactOut::Create(_, Some(addr)) = out {
+                    addr
+                } else {
+                    panic!("deployment succeeded, but we got no address. this is a bug.");
+                }
+            }
+            // TODO: We should have better error handling logic in the test runner
+            // regarding deployments in general
+            _ => eyre::bail!("deployment failed: {:?}", status),
+        };
+        let InspectorData { logs, traces, debug, coverage, cheatcodes, .. } =
+            inspector.collect_inspector_states();
+
+        // Persist the changed block environment
+        self.inspector_config.block = evm.env.block.clone();
+
+        // Persist cheatcode state
+        self.inspector_config.cheatcodes = cheatcodes;
+
+        // Persist the changed state
+        self.commit(state_changeset.clone());
+
+        Ok(DeployResult { address, gas, logs, traces, debug, state_changeset, coverage })
+    }
+
+    /// Commits a state changeset to the database.
+    ///
+    /// If a fork is selected, changes to accounts that are not persistent across forks are
+    /// committed to the fork instead.
+    ///
+    /// The values in the database that are overwritten by the changeset are preserved in any
+    /// snapshots taken by the cheatcode inspector, so they can still be reverted to later on. The
+    /// snapshots hold a copy of the forks, so changes committed to a fork need no preserving.
+    fn commit(&mut self, mut state_changeset: StateChangeset) {
+        if let Some(cheatcodes) = &mut self.inspector_config.cheatcodes {
+            state_changeset = cheatcodes.forks.commit(state_changeset);
+            for snapshot in cheatcodes.snapshots.values_mut() {
+                snapshot.preserve(&self.db, &state_changeset);
+            }
+        }
+        self.db.commit(state_changeset);
+    }
+
+    /// Executes the transaction in `env` without persisting the new state.
+    fn execute(&self, env: Env) -> Result<RawCallResult> {
+        let stipend = stipend(&env.tx.data, self.env.cfg.spec_id);
 
         // Build VM
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
-        evm.database(&self.db);
+        evm.env = env;
+        evm.database(self.database());
+
+        // Forks created or selected during the call are not persisted
+        let forks = self
+            .inspector_config
+            .cheatcodes
+            .as_ref()
+            .map(|cheatcodes| (cheatcodes.forks.clone(), cheatcodes.forks.checkpoint()));
 
         // Run the call
         let mut inspector = self.inspector_config.stack();
         let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
         let result = match out {
             TransactOut::Call(data) => data,
+            TransactOut::Create(data, _) => data,
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, .. } =
+        if let Some((forks, checkpoint)) = forks {
+            forks.restore(checkpoint);
+        }
+
+        let InspectorData { logs, labels, traces, debug, coverage, cheatcodes } =
             inspector.collect_inspector_states();
+        let script_wallets = cheatcodes
+            .as_ref()
+            .map(|cheatcodes| cheatcodes.script_wallets.clone())
+            .unwrap_or_default();
+        let transactions = cheatcodes.map(|cheatcodes| cheatcodes.broadcastable_transactions);
         Ok(RawCallResult {
             status,
             reverted: !matches!(status, return_ok!()),
@@ -422,39 +565,66 @@ where
             traces,
             debug,
             state_changeset: Some(state_changeset),
+            coverage,
+            transactions,
+            script_wallets,
         })
     }
 
-    /// Deploys a contract and commits the new state to the underlying database.
-    pub fn deploy(&mut self, from: Address, code: Bytes, value: U256) -> Result<DeployResult> {
+    /// Executes the transaction in `env` and persists the new state.
+    fn execute_committing(&mut self, env: Env) -> Result<RawCallResult> {
+        let stipend = stipend(&env.tx.data, self.env.cfg.spec_id);
+
+        // Build VM
+        //
+        // We do not let the EVM load accounts into our database directly, since they might belong
+        // to a fork that is no longer selected once the call is committed.
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Create(CreateScheme::Create), code, value);
-        evm.database(&mut self.db);
+        evm.env = env;
+        evm.database(self.database());
 
+        // Run the call
         let mut inspector = self.inspector_config.stack();
-        let (status, out, gas, _) = evm.inspect_commit(&mut inspector);
-        let address = match status {
-            return_ok!() => {
-                if let TransactOut::Create(_, Some(addr)) = out {
-                    addr
-                } else {
-                    panic!("deployment succeeded, but we got no address. this is a bug.");
-                }
-            }
-            // TODO: We should have better error handling logic in the test runner
-            // regarding deployments in general
-            _ => eyre::bail!("deployment failed: {:?}", status),
+        let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
+        let result = match out {
+            TransactOut::Call(data) => data,
+            TransactOut::Create(data, _) => data,
+            _ => Bytes::default(),
         };
-        let InspectorData { logs, traces, debug, cheatcodes, .. } =
+
+        let InspectorData { logs, labels, traces, debug, coverage, cheatcodes } =
             inspector.collect_inspector_states();
 
         // Persist the changed block environment
         self.inspector_config.block = evm.env.block.clone();
 
         // Persist cheatcode state
+        let transactions =
+            cheatcodes.as_ref().map(|cheatcodes| cheatcodes.broadcastable_transactions.clone());
+        let script_wallets = cheatcodes
+            .as_ref()
+            .map(|cheatcodes| cheatcodes.script_wallets.clone())
+            .unwrap_or_default();
         self.inspector_config.cheatcodes = cheatcodes;
 
-        Ok(DeployResult { address, gas, logs, traces, debug })
+        // Persist the changed state
+        self.commit(state_changeset.clone());
+
+        Ok(RawCallResult {
+            status,
+            reverted: !matches!(status, return_ok!()),
+            result,
+            gas,
+            stipend,
+            logs: logs.to_vec(),
+            labels,
+            traces,
+            debug,
+            state_changeset: Some(state_changeset),
+            coverage,
+            transactions,
+            script_wallets,
+        })
     }
 
     /// Check if a call to a test contract was successful.
@@ -0,0 +1,66 @@
+//! Persistence of the counterexamples found by the fuzzer
+use ethers::types::Bytes;
+use std::{fs, path::Path};
+
+/// Reads the calldata of all counterexamples saved at `path`.
+///
+/// Returns an empty corpus if the file does not exist or can not be read.
+pub fn read_corpus(path: &Path) -> Vec<Bytes> {
+    if !path.exists() {
+        return Vec::new()
+    }
+
+    match fs::read_to_string(path)
+        .map_err(eyre::Report::from)
+        .and_then(|content| Ok(serde_json::from_str(&content)?))
+    {
+        Ok(corpus) => corpus,
+        Err(err) => {
+            tracing::warn!(?err, ?path, "failed to read fuzz corpus");
+            Vec::new()
+        }
+    }
+}
+
+/// Saves the calldata of a counterexample at `path`, unless it is already part of the corpus.
+pub fn persist_counterexample(path: &Path, calldata: &Bytes) -> eyre::Result<()> {
+    let mut corpus = read_corpus(path);
+    if corpus.contains(calldata) {
+        return Ok(())
+    }
+    corpus.push(calldata.clone());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&corpus)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_persist_counterexamples() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src/Test.t.sol/Test/testFuzz(uint256).json");
+        assert!(read_corpus(&path).is_empty());
+
+        let first = Bytes::from(vec![1, 2, 3]);
+        let second = Bytes::from(vec![4, 5, 6]);
+        persist_counterexample(&path, &first).unwrap();
+        persist_counterexample(&path, &second).unwrap();
+        // Counterexamples that were already saved are not saved again
+        persist_counterexample(&path, &first).unwrap();
+        assert_eq!(read_corpus(&path), vec![first, second]);
+    }
+
+    #[test]
+    fn ignores_invalid_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.json");
+        fs::write(&path, "not a corpus").unwrap();
+        assert!(read_corpus(&path).is_empty());
+    }
+}
@@ -0,0 +1,361 @@
+use super::{
+    strategies::{
+        build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
+        EvmFuzzState,
+    },
+    BaseCounterExample,
+};
+use crate::{coverage::HitMaps, executor::Executor, trace::CallTraceArena};
+use ethers::{
+    abi::{Abi, Function, RawLog, StateMutability, Token},
+    types::{Address, Bytes},
+};
+use proptest::{
+    prelude::{BoxedStrategy, Strategy},
+    test_runner::{TestCaseError, TestError, TestRunner},
+};
+use revm::db::DatabaseRef;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+};
+
+/// The contracts whose functions are called by the invariant fuzzer, along with their names and
+/// ABIs.
+pub type TargetedContracts = BTreeMap<Address, (String, Abi)>;
+
+/// A single call made by the invariant fuzzer
+type InvariantCall = (Address, Bytes);
+
+/// Wrapper around an [`Executor`] which provides stateful fuzzing of invariants.
+///
+/// The fuzzer calls random sequences of functions on the targeted contracts, committing the state
+/// after every call, and checks all invariants of the test contract after each of them.
+pub struct InvariantExecutor<'a, DB: DatabaseRef + Clone> {
+    /// The VM
+    executor: &'a Executor<DB>,
+    /// The fuzzer
+    runner: TestRunner,
+    /// The account that makes the calls to the targeted contracts and the invariants
+    sender: Address,
+    /// The maximum number of calls in a sequence
+    depth: usize,
+}
+
+impl<'a, DB> InvariantExecutor<'a, DB>
+where
+    DB: DatabaseRef + Clone,
+{
+    /// Instantiates an invariant executor given a testrunner and the maximum length of a call
+    /// sequence
+    pub fn new(
+        executor: &'a Executor<DB>,
+        runner: TestRunner,
+        sender: Address,
+        depth: usize,
+    ) -> Self {
+        Self { executor, runner, sender, depth }
+    }
+
+    /// Fuzzes the invariants of the test contract at `address` by calling random sequences of
+    /// functions on the `targets`.
+    ///
+    /// Every broken invariant is reported with the shrunk call sequence that broke it. Once an
+    /// invariant is broken, the fuzzer continues with the remaining invariants.
+    pub fn invariant_fuzz(
+        &self,
+        invariants: &[&Function],
+        address: Address,
+        targets: &TargetedContracts,
+        errors: Option<&Abi>,
+    ) -> InvariantFuzzTestResult {
+        let mut result = InvariantFuzzTestResult::default();
+        let mut remaining = invariants.to_vec();
+        let invariant_coverage = RefCell::new(BTreeMap::new());
+
+        // Invariants that are broken before any calls are made do not need to be fuzzed
+        remaining.retain(|func| {
+            match self.broken_invariant(
+                self.executor,
+                &[*func],
+                address,
+                errors,
+                &invariant_coverage,
+            ) {
+                Some((_, reason)) => {
+                    result
+                        .failures
+                        .insert(func.signature(), self.replay(&[], func, address, targets, reason));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        // Stores fuzz state for use with [fuzz_calldata_from_state]
+        let state: EvmFuzzState = build_initial_state(&self.executor.db);
+
+        let strat = match invariant_strat(targets, state.clone(), self.depth) {
+            Some(strat) => strat,
+            // There is nothing to call, so the invariants can not be broken
+            None => {
+                result.invariant_coverage = invariant_coverage.into_inner();
+                return result
+            }
+        };
+
+        // The same runner is used for every pass, so each pass fuzzes new sequences instead of
+        // starting over from the same seed
+        let mut runner = self.runner.clone();
+        while !remaining.is_empty() {
+            // Stores the index of the invariant broken by the last failed sequence, the reason it
+            // was broken and the number of calls it took to break it
+            let failure: RefCell<Option<(usize, Option<String>, usize)>> = RefCell::new(None);
+            let runs = Cell::new(0);
+            let calls = Cell::new(0);
+            let reverts = Cell::new(0);
+            let coverage: RefCell<Option<HitMaps>> = RefCell::new(None);
+
+            tracing::debug!(invariants = ?remaining.len(), "fuzzing invariants");
+            let run_result = runner.run(&strat, |sequence| {
+                runs.set(runs.get() + 1);
+                let mut executor = self.executor.clone();
+
+                for (idx, (target, calldata)) in sequence.iter().enumerate() {
+                    let mut call = executor
+                        .call_raw_committing(self.sender, *target, calldata.0.clone(), 0.into())
+                        .expect("could not make raw evm call");
+                    calls.set(calls.get() + 1);
+                    coverage.replace(HitMaps::merge_opt(coverage.take(), call.coverage.take()));
+                    if call.reverted {
+                        reverts.set(reverts.get() + 1);
+                    }
+
+                    // Build fuzzer state
+                    if let Some(state_changeset) = &call.state_changeset {
+                        collect_state_from_call(&call.logs, state_changeset, state.clone());
+                    }
+
+                    if let Some((broken, reason)) = self.broken_invariant(
+                        &executor,
+                        &remaining,
+                        address,
+                        errors,
+                        &invariant_coverage,
+                    ) {
+                        // As with fuzz tests, the sequence returned by the test runner in
+                        // `TestError::Fail` is the last failing one, so we only need to remember
+                        // what broke it
+                        *failure.borrow_mut() = Some((broken, reason.clone(), idx + 1));
+                        return Err(TestCaseError::fail(reason.unwrap_or_default()))
+                    }
+                }
+
+                Ok(())
+            });
+
+            result.runs += runs.get();
+            result.calls += calls.get();
+            result.reverts += reverts.get();
+            result.coverage = HitMaps::merge_opt(result.coverage.take(), coverage.into_inner());
+
+            match run_result {
+                Err(TestError::Fail(_, sequence)) => {
+                    let (broken, reason, len) =
+                        failure.into_inner().expect("a failed sequence should break an invariant");
+                    let func = remaining.remove(broken);
+                    result.failures.insert(
+                        func.signature(),
+                        self.replay(&sequence[..len], func, address, targets, reason),
+                    );
+                }
+                Err(TestError::Abort(reason)) => {
+                    for func in remaining.drain(..) {
+                        result.failures.insert(
+                            func.signature(),
+                            InvariantFailure {
+                                reason: Some(reason.to_string()),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+                Ok(()) => break,
+            }
+        }
+
+        result.invariant_coverage = invariant_coverage.into_inner();
+        result
+    }
+
+    /// Checks the `invariants` on the current state of `executor`, returning the index of the
+    /// first broken invariant and the reason it was broken, if any.
+    ///
+    /// An invariant is broken if it reverts, fails a DSTest assertion or returns `false`. The
+    /// coverage of each check is added to the `coverage` of its invariant.
+    fn broken_invariant(
+        &self,
+        executor: &Executor<DB>,
+        invariants: &[&Function],
+        address: Address,
+        errors: Option<&Abi>,
+        coverage: &RefCell<BTreeMap<String, HitMaps>>,
+    ) -> Option<(usize, Option<String>)> {
+        invariants.iter().enumerate().find_map(|(idx, func)| {
+            let mut call = executor
+                .call_raw(
+                    self.sender,
+                    address,
+                    func.encode_input(&[]).expect("invariants do not take arguments").into(),
+                    0.into(),
+                )
+                .expect("could not make raw evm call");
+            if let Some(hits) = call.coverage.take() {
+                coverage.borrow_mut().entry(func.signature()).or_default().merge(hits);
+            }
+
+            let mut success = executor.is_success(
+                address,
+                call.reverted,
+                call.state_changeset.clone().expect("we should have a state changeset"),
+                false,
+            );
+            if success {
+                if let Ok(tokens) = func.decode_output(call.result.as_ref()) {
+                    success = !matches!(tokens.first(), Some(Token::Bool(false)));
+                }
+            }
+
+            if success {
+                None
+            } else {
+                Some((idx, foundry_utils::decode_revert(call.result.as_ref(), errors).ok()))
+            }
+        })
+    }
+
+    /// Replays a sequence that broke `invariant` to collect the logs and traces of the failure.
+    fn replay(
+        &self,
+        sequence: &[InvariantCall],
+        invariant: &Function,
+        address: Address,
+        targets: &TargetedContracts,
+        reason: Option<String>,
+    ) -> InvariantFailure {
+        let mut executor = self.executor.clone();
+        let mut failure = InvariantFailure { reason, ..Default::default() };
+
+        for (target, calldata) in sequence {
+            let call = executor
+                .call_raw_committing(self.sender, *target, calldata.0.clone(), 0.into())
+                .expect("could not make raw evm call");
+            failure.traces.extend(call.traces);
+            failure.labeled_addresses.extend(call.labels);
+            failure.counterexample.push(counterexample(*target, calldata.clone(), targets));
+        }
+
+        let call = executor
+            .call_raw(
+                self.sender,
+                address,
+                invariant.encode_input(&[]).expect("invariants do not take arguments").into(),
+                0.into(),
+            )
+            .expect("could not make raw evm call");
+        failure.logs = call.logs;
+        failure.traces.extend(call.traces);
+        failure.labeled_addresses.extend(call.labels);
+
+        failure
+    }
+}
+
+/// Builds a strategy that generates sequences of up to `depth` calls to the non-view functions of
+/// the `targets`, or `None` if there are no such functions.
+fn invariant_strat(
+    targets: &TargetedContracts,
+    state: EvmFuzzState,
+    depth: usize,
+) -> Option<BoxedStrategy<Vec<InvariantCall>>> {
+    let calls = targets
+        .iter()
+        .flat_map(|(address, (_, abi))| {
+            let address = *address;
+            let state = state.clone();
+            abi.functions().filter(|func| is_mutable(func)).map(move |func| {
+                // TODO: As with fuzz tests, the weights of the strategies should be configurable
+                proptest::strategy::Union::new_weighted(vec![
+                    (60, fuzz_calldata(func.clone())),
+                    (40, fuzz_calldata_from_state(func.clone(), state.clone())),
+                ])
+                .prop_map(move |calldata| (address, calldata))
+                .boxed()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if calls.is_empty() {
+        return None
+    }
+    Some(proptest::collection::vec(proptest::strategy::Union::new(calls), 1..=depth).boxed())
+}
+
+/// Whether calling the function can change the state of the contract
+fn is_mutable(func: &Function) -> bool {
+    !matches!(func.state_mutability, StateMutability::Pure | StateMutability::View)
+}
+
+/// Builds the counterexample for a call made by the invariant fuzzer
+fn counterexample(
+    address: Address,
+    calldata: Bytes,
+    targets: &TargetedContracts,
+) -> BaseCounterExample {
+    let (contract_name, abi) = &targets[&address];
+    let args = abi
+        .functions()
+        .find(|func| calldata.as_ref().starts_with(&func.short_signature()))
+        .and_then(|func| func.decode_input(&calldata.as_ref()[4..]).ok())
+        .unwrap_or_default();
+
+    BaseCounterExample {
+        address: Some(address),
+        contract_name: Some(contract_name.clone()),
+        calldata,
+        args,
+    }
+}
+
+/// The outcome of an invariant fuzz test
+#[derive(Debug, Default)]
+pub struct InvariantFuzzTestResult {
+    /// The invariants that were broken, keyed by the signature of the invariant function
+    pub failures: BTreeMap<String, InvariantFailure>,
+    /// The number of call sequences that were run
+    pub runs: usize,
+    /// The number of calls that were made
+    pub calls: usize,
+    /// The number of calls that reverted
+    pub reverts: usize,
+    /// The coverage info collected during all call sequences
+    pub coverage: Option<HitMaps>,
+    /// The coverage info collected while checking each invariant, keyed by the signature of the
+    /// invariant function
+    pub invariant_coverage: BTreeMap<String, HitMaps>,
+}
+
+/// A broken invariant
+#[derive(Debug, Default)]
+pub struct InvariantFailure {
+    /// The reason the invariant was broken, if any
+    pub reason: Option<String>,
+    /// The shrunk call sequence that broke the invariant
+    pub counterexample: Vec<BaseCounterExample>,
+    /// The logs emitted by the invariant after the sequence was replayed
+    pub logs: Vec<RawLog>,
+    /// The traces of the sequence and the invariant
+    pub traces: Vec<CallTraceArena>,
+    /// Labeled addresses
+    pub labeled_addresses: BTreeMap<Address, String>,
+}
@@ -1,8 +1,16 @@
 mod strategies;
 
+mod corpus;
+
+mod invariant;
+pub use invariant::{
+    InvariantExecutor, InvariantFailure, InvariantFuzzTestResult, TargetedContracts,
+};
+
 pub use proptest::test_runner::{Config as FuzzConfig, Reason};
 
 use crate::{
+    coverage::HitMaps,
     executor::{Executor, RawCallResult},
     trace::CallTraceArena,
 };
@@ -13,7 +21,7 @@ use ethers::{
 use proptest::test_runner::{TestCaseError, TestError, TestRunner};
 use revm::db::DatabaseRef;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::BTreeMap, fmt};
+use std::{cell::RefCell, collections::BTreeMap, fmt, path::PathBuf};
 use strategies::{
     build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
     EvmFuzzState,
@@ -34,6 +42,8 @@ pub struct FuzzedExecutor<'a, DB: DatabaseRef> {
     runner: TestRunner,
     /// The account that calls tests
     sender: Address,
+    /// The directory in which counterexamples are saved, if any
+    corpus_dir: Option<PathBuf>,
 }
 
 impl<'a, DB> FuzzedExecutor<'a, DB>
@@ -42,7 +52,16 @@ where
 {
     /// Instantiates a fuzzed executor given a testrunner
     pub fn new(executor: &'a Executor<DB>, runner: TestRunner, sender: Address) -> Self {
-        Self { executor, runner, sender }
+        Self { executor, runner, sender, corpus_dir: None }
+    }
+
+    /// Saves the counterexamples of failed fuzz tests in `corpus_dir`, one file per test.
+    ///
+    /// The saved counterexamples of a test are replayed before any new cases are generated.
+    #[must_use]
+    pub fn with_corpus_dir(mut self, corpus_dir: Option<PathBuf>) -> Self {
+        self.corpus_dir = corpus_dir;
+        self
     }
 
     /// Fuzzes the provided function, assuming it is available at the contract at `address`
@@ -63,6 +82,9 @@ where
         // Stores the result and calldata of the last failed call, if any.
         let counterexample: RefCell<(Bytes, RawCallResult)> = RefCell::new(Default::default());
 
+        // Stores the coverage of all fuzz calls, if coverage is collected
+        let coverage: RefCell<Option<HitMaps>> = RefCell::new(None);
+
         // Stores fuzz state for use with [fuzz_calldata_from_state]
         let state: EvmFuzzState = build_initial_state(&self.executor.db);
 
@@ -73,11 +95,12 @@ where
             (40, fuzz_calldata_from_state(func.clone(), state.clone())),
         ]);
         tracing::debug!(func = ?func.name, should_fail, "fuzzing");
-        let run_result = self.runner.clone().run(&strat, |calldata| {
-            let call = self
+        let run_case = |calldata: Bytes| {
+            let mut call = self
                 .executor
                 .call_raw(self.sender, address, calldata.0.clone(), 0.into())
                 .expect("could not make raw evm call");
+            coverage.replace(HitMaps::merge_opt(coverage.take(), call.coverage.take()));
             let state_changeset =
                 call.state_changeset.as_ref().expect("we should have a state changeset");
 
@@ -119,7 +142,30 @@ where
                     },
                 ))
             }
-        });
+        };
+
+        // Replay the counterexamples of previous runs first, so a failure is found again
+        // regardless of the seed of the test runner
+        let corpus_file =
+            self.corpus_dir.as_ref().map(|dir| dir.join(format!("{}.json", func.signature())));
+        let replayed = corpus_file
+            .as_deref()
+            .map(corpus::read_corpus)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|calldata| {
+                calldata.as_ref().starts_with(&func.short_signature()) &&
+                    func.decode_input(&calldata.as_ref()[4..]).is_ok()
+            })
+            .find_map(|calldata| match run_case(calldata.clone()) {
+                Err(TestCaseError::Fail(reason)) => Some(TestError::Fail(reason, calldata)),
+                _ => None,
+            });
+
+        let run_result = match replayed {
+            Some(failure) => Err(failure),
+            None => self.runner.clone().run(&strat, run_case),
+        };
 
         let (calldata, call) = counterexample.into_inner();
         let mut result = FuzzTestResult {
@@ -130,6 +176,7 @@ where
             logs: call.logs,
             traces: call.traces,
             labeled_addresses: call.labels,
+            coverage: coverage.into_inner(),
         };
 
         match run_result {
@@ -140,10 +187,21 @@ where
                 let reason = reason.to_string();
                 result.reason = if reason.is_empty() { None } else { Some(reason) };
 
+                if let Some(corpus_file) = &corpus_file {
+                    if let Err(err) = corpus::persist_counterexample(corpus_file, &calldata) {
+                        tracing::warn!(?err, ?corpus_file, "failed to save counterexample");
+                    }
+                }
+
                 let args = func
                     .decode_input(&calldata.as_ref()[4..])
                     .expect("could not decode fuzzer inputs");
-                result.counterexample = Some(CounterExample { calldata, args });
+                result.counterexample = Some(CounterExample::Single(BaseCounterExample {
+                    address: None,
+                    contract_name: None,
+                    calldata,
+                    args,
+                }));
             }
             _ => (),
         }
@@ -152,16 +210,55 @@ where
     }
 }
 
+/// A minimal reproduction of a failing fuzz or invariant test
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CounterExample {
+#[serde(untagged)]
+pub enum CounterExample {
+    /// The call that failed a fuzz test
+    Single(BaseCounterExample),
+    /// The sequence of calls that broke an invariant
+    Sequence(Vec<BaseCounterExample>),
+}
+
+impl fmt::Display for CounterExample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CounterExample::Single(counterexample) => write!(f, "{}", counterexample),
+            CounterExample::Sequence(sequence) => {
+                write!(f, "[Sequence]")?;
+                for counterexample in sequence {
+                    write!(f, "\n\t\t{}", counterexample)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A single call of a [CounterExample]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BaseCounterExample {
+    /// The address that was called, if it is not the test contract
+    pub address: Option<Address>,
+    /// The name of the contract that was called, if it is not the test contract
+    pub contract_name: Option<String>,
+    /// The calldata of the call
     pub calldata: Bytes,
 
     #[serde(skip)]
     pub args: Vec<Token>,
 }
 
-impl fmt::Display for CounterExample {
+impl fmt::Display for BaseCounterExample {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(address) = self.address {
+            write!(f, "addr=")?;
+            if let Some(name) = &self.contract_name {
+                write!(f, "[{}]", name)?;
+            }
+            write!(f, "{:?} ", address)?;
+        }
+
         let args = foundry_utils::format_tokens(&self.args).collect::<Vec<_>>().join(", ");
         write!(f, "calldata=0x{}, args=[{}]", hex::encode(&self.calldata), args)
     }
@@ -194,6 +291,9 @@ pub struct FuzzTestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The coverage info collected during all fuzz cases
+    pub coverage: Option<HitMaps>,
 }
 
 /// Container type for all successful test cases
@@ -4,10 +4,11 @@ use super::{
 use crate::abi::{CHEATCODE_ADDRESS, CONSOLE_ABI, HEVM_ABI};
 use ethers::{
     abi::{Abi, Address, Event, Function, Param, ParamType, Token},
-    types::H256,
+    solc::artifacts::StorageLayout,
+    types::{H256, U256},
 };
-use foundry_utils::format_token;
-use std::collections::BTreeMap;
+use foundry_utils::{format_token, SignatureDb};
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
 
 /// The call trace decoder.
 ///
@@ -32,6 +33,11 @@ pub struct CallTraceDecoder {
     pub events: BTreeMap<(H256, usize), Vec<Event>>,
     /// All known errors
     pub errors: Abi,
+    /// Known signatures, used for selectors and topics that are not in any of the identified ABIs
+    pub signatures: Arc<SignatureDb>,
+    /// The storage layouts of contracts by contract name, used to name the slots in storage
+    /// changes
+    pub storage_layouts: Arc<BTreeMap<String, StorageLayout>>,
 }
 
 impl CallTraceDecoder {
@@ -124,6 +130,8 @@ impl CallTraceDecoder {
                 .map(|event| ((event.signature(), indexed_inputs(event)), vec![event.clone()]))
                 .collect::<BTreeMap<(H256, usize), Vec<Event>>>(),
             errors: Abi::default(),
+            signatures: Default::default(),
+            storage_layouts: Default::default(),
         }
     }
 
@@ -136,6 +144,23 @@ impl CallTraceDecoder {
         info
     }
 
+    /// Sets the signature database that is used to decode calls, events and errors of contracts
+    /// that were not identified.
+    pub fn with_signatures(mut self, signatures: Arc<SignatureDb>) -> Self {
+        self.signatures = signatures;
+        self
+    }
+
+    /// Sets the storage layouts, keyed by contract name, that are used to name the slots written
+    /// by identified contracts.
+    pub fn with_storage_layouts(
+        mut self,
+        storage_layouts: Arc<BTreeMap<String, StorageLayout>>,
+    ) -> Self {
+        self.storage_layouts = storage_layouts;
+        self
+    }
+
     /// Identify unknown addresses in the specified call trace using the specified identifier.
     ///
     /// Unknown contracts are contracts that either lack a label or an ABI.
@@ -193,6 +218,16 @@ impl CallTraceDecoder {
                 node.trace.label = Some(label.clone());
             }
 
+            // Name the written storage slots. The layout of the executed code applies, which is
+            // the code of the called contract even for delegate calls.
+            if let Some(layout) =
+                node.trace.contract.as_ref().and_then(|contract| self.storage_layouts.get(contract))
+            {
+                for change in &mut node.trace.storage_changes {
+                    change.label = change.label.take().or_else(|| slot_label(layout, change.slot));
+                }
+            }
+
             // Decode call
             if let RawOrDecodedCall::Raw(bytes) = &node.trace.data {
                 if let Some(precompile_fn) = self.precompiles.get(&node.trace.address) {
@@ -220,7 +255,7 @@ impl CallTraceDecoder {
                         );
                     }
                 } else if bytes.len() >= 4 {
-                    if let Some(funcs) = self.functions.get(&bytes[0..4]) {
+                    if let Some(funcs) = self.decode_functions(bytes) {
                         // This is safe because (1) we would not have an entry for the given
                         // selector if no functions with that selector were added and (2) the same
                         // selector implies the function has the same name and inputs.
@@ -265,9 +300,7 @@ impl CallTraceDecoder {
                                                 .join(", "),
                                         );
                                     }
-                                } else if let Ok(decoded_error) =
-                                    foundry_utils::decode_revert(&bytes[..], Some(&self.errors))
-                                {
+                                } else if let Some(decoded_error) = self.decode_revert(&bytes[..]) {
                                     node.trace.output = RawOrDecodedReturnData::Decoded(format!(
                                         r#""{}""#,
                                         decoded_error
@@ -281,9 +314,7 @@ impl CallTraceDecoder {
 
                     if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
                         if !node.trace.success {
-                            if let Ok(decoded_error) =
-                                foundry_utils::decode_revert(&bytes[..], Some(&self.errors))
-                            {
+                            if let Some(decoded_error) = self.decode_revert(&bytes[..]) {
                                 node.trace.output = RawOrDecodedReturnData::Decoded(format!(
                                     r#""{}""#,
                                     decoded_error
@@ -298,9 +329,9 @@ impl CallTraceDecoder {
             node.logs.iter_mut().for_each(|log| {
                 if let RawOrDecodedLog::Raw(raw_log) = log {
                     if let Some(events) =
-                        self.events.get(&(raw_log.topics[0], raw_log.topics.len() - 1))
+                        self.decode_events(raw_log.topics[0], raw_log.topics.len() - 1)
                     {
-                        for event in events {
+                        for event in events.iter() {
                             if let Ok(decoded) = event.parse_log(raw_log.clone()) {
                                 *log = RawOrDecodedLog::Decoded(
                                     event.name.clone(),
@@ -319,6 +350,51 @@ impl CallTraceDecoder {
         }
     }
 
+    /// Returns the functions that match the selector of the calldata.
+    ///
+    /// If the selector is not in any of the identified ABIs, the known signatures that can decode
+    /// the calldata are used.
+    fn decode_functions(&self, calldata: &[u8]) -> Option<Cow<[Function]>> {
+        if let Some(funcs) = self.functions.get(&calldata[0..4]) {
+            return Some(Cow::Borrowed(funcs))
+        }
+
+        let funcs: Vec<Function> = self
+            .signatures
+            .decode_functions(&calldata[0..4])
+            .into_iter()
+            .filter(|func| func.decode_input(&calldata[4..]).is_ok())
+            .collect();
+        (!funcs.is_empty()).then(|| Cow::Owned(funcs))
+    }
+
+    /// Returns the events with the topic and number of indexed inputs, falling back to the known
+    /// signatures
+    fn decode_events(&self, topic: H256, indexed: usize) -> Option<Cow<[Event]>> {
+        if let Some(events) = self.events.get(&(topic, indexed)) {
+            return Some(Cow::Borrowed(events))
+        }
+
+        let events = self.signatures.decode_events(topic, indexed);
+        (!events.is_empty()).then(|| Cow::Owned(events))
+    }
+
+    /// Decodes revert data with the identified errors, falling back to the known signatures
+    fn decode_revert(&self, data: &[u8]) -> Option<String> {
+        if let Ok(decoded) = foundry_utils::decode_revert(data, Some(&self.errors)) {
+            return Some(decoded)
+        }
+
+        if data.len() < 4 {
+            return None
+        }
+        self.signatures.decode_functions(&data[0..4]).into_iter().find_map(|error| {
+            let tokens = error.decode_input(&data[4..]).ok()?;
+            let inputs = tokens.iter().map(format_token).collect::<Vec<_>>().join(", ");
+            Some(format!("{}({})", error.name, inputs))
+        })
+    }
+
     fn apply_label(&self, token: &Token) -> String {
         match token {
             Token::Address(addr) => {
@@ -366,6 +442,18 @@ where
     )
 }
 
+/// Returns the names of the variables stored in a slot, or `None` if the slot does not belong to a
+/// state variable, e.g. because it is an entry of a mapping
+fn slot_label(layout: &StorageLayout, slot: U256) -> Option<String> {
+    let labels = layout
+        .storage
+        .iter()
+        .filter(|storage| U256::from_dec_str(&storage.slot).ok() == Some(slot))
+        .map(|storage| storage.label.as_str())
+        .collect::<Vec<_>>();
+    (!labels.is_empty()).then(|| labels.join(", "))
+}
+
 fn indexed_inputs(event: &Event) -> usize {
     event.inputs.iter().filter(|param| param.indexed).count()
 }
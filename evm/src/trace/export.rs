@@ -0,0 +1,211 @@
+use super::{CallTrace, CallTraceArena, RawOrDecodedCall, RawOrDecodedLog};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+/// The formats call traces can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// A JSON tree of the calls, see [CallTraceArena::to_json]
+    Json,
+    /// Folded stacks of the gas used by each call path, see [CallTraceArena::to_folded_stacks]
+    Folded,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(TraceFormat::Json),
+            "folded" => Ok(TraceFormat::Folded),
+            _ => Err(format!("Unrecognized trace format `{}`", s)),
+        }
+    }
+}
+
+impl TraceFormat {
+    /// Renders the trace in this format
+    pub fn render(&self, trace: &CallTraceArena) -> String {
+        match self {
+            TraceFormat::Json => trace.to_json().to_string(),
+            TraceFormat::Folded => trace.to_folded_stacks(),
+        }
+    }
+
+    /// The file extension of traces in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TraceFormat::Json => "json",
+            TraceFormat::Folded => "folded",
+        }
+    }
+}
+
+impl CallTraceArena {
+    /// Returns the trace as a JSON tree.
+    ///
+    /// Each call has its (decoded, if possible) calldata, return data, logs and subcalls, as well
+    /// as the gas it used.
+    pub fn to_json(&self) -> Value {
+        fn inner(arena: &CallTraceArena, idx: usize) -> Value {
+            let node = &arena.arena[idx];
+            let trace = &node.trace;
+
+            let mut json = json!({
+                "address": trace.address,
+                "label": trace.label,
+                "contract": trace.contract,
+                "kind": trace.kind,
+                "depth": trace.depth,
+                "success": trace.success,
+                "value": trace.value,
+                "gas": trace.gas_cost,
+                "output": trace.output.to_string(),
+                "storage_changes": trace.storage_changes,
+                "logs": node.logs.iter().map(log_json).collect::<Vec<_>>(),
+                "calls": node.children.iter().map(|child| inner(arena, *child)).collect::<Vec<_>>(),
+            });
+            match &trace.data {
+                RawOrDecodedCall::Raw(bytes) => {
+                    json["calldata"] = format!("0x{}", hex::encode(bytes)).into();
+                }
+                RawOrDecodedCall::Decoded(func, inputs) => {
+                    json["function"] = func.clone().into();
+                    json["inputs"] = inputs.clone().into();
+                }
+            }
+            json
+        }
+
+        inner(self, 0)
+    }
+
+    /// Returns the gas used by each call path in the folded stack format, which can be turned
+    /// into a flamegraph with e.g. [inferno](https://github.com/jonhoo/inferno) or
+    /// [FlameGraph](https://github.com/brendangregg/FlameGraph).
+    ///
+    /// Each line is a call path, with the calls separated by `;`, followed by the gas used by the
+    /// last call of the path, excluding the gas used by its subcalls.
+    pub fn to_folded_stacks(&self) -> String {
+        fn inner(
+            arena: &CallTraceArena,
+            idx: usize,
+            stack: &mut Vec<String>,
+            stacks: &mut BTreeMap<String, u64>,
+        ) {
+            let node = &arena.arena[idx];
+            stack.push(frame(&node.trace));
+
+            let children_gas: u64 =
+                node.children.iter().map(|child| arena.arena[*child].trace.gas_cost).sum();
+            let gas = node.trace.gas_cost.saturating_sub(children_gas);
+            if gas > 0 {
+                *stacks.entry(stack.join(";")).or_default() += gas;
+            }
+
+            for child in &node.children {
+                inner(arena, *child, stack, stacks);
+            }
+            stack.pop();
+        }
+
+        let mut stacks = BTreeMap::new();
+        inner(self, 0, &mut Vec::new(), &mut stacks);
+
+        let mut folded = String::new();
+        for (stack, gas) in stacks {
+            writeln!(folded, "{} {}", stack, gas).expect("writing to a string never fails");
+        }
+        folded
+    }
+}
+
+fn log_json(log: &RawOrDecodedLog) -> Value {
+    match log {
+        RawOrDecodedLog::Raw(log) => json!({
+            "topics": log.topics,
+            "data": format!("0x{}", hex::encode(&log.data)),
+        }),
+        RawOrDecodedLog::Decoded(name, params) => json!({
+            "name": name,
+            "params": params.iter().cloned().collect::<BTreeMap<_, _>>(),
+        }),
+    }
+}
+
+/// The name of a call in a folded stack, e.g. `Token::transfer`
+fn frame(trace: &CallTrace) -> String {
+    let name = trace.label.clone().unwrap_or_else(|| format!("{:?}", trace.address));
+    let frame = if trace.created() {
+        format!("new {}", name)
+    } else {
+        let func = match &trace.data {
+            RawOrDecodedCall::Decoded(func, _) => func.clone(),
+            RawOrDecodedCall::Raw(bytes) => {
+                bytes.get(0..4).map(hex::encode).unwrap_or_else(|| "fallback".to_string())
+            }
+        };
+        format!("{}::{}", name, func)
+    };
+    // `;` separates the calls of a stack
+    frame.replace(';', ",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{trace::CallTraceNode, CallKind};
+
+    fn node(
+        idx: usize,
+        parent: Option<usize>,
+        children: Vec<usize>,
+        trace: CallTrace,
+    ) -> CallTraceNode {
+        CallTraceNode { parent, children, idx, trace, ..Default::default() }
+    }
+
+    fn call(depth: usize, label: &str, func: &str, gas_cost: u64) -> CallTrace {
+        CallTrace {
+            depth,
+            success: true,
+            label: Some(label.to_string()),
+            data: RawOrDecodedCall::Decoded(func.to_string(), vec!["1".to_string()]),
+            gas_cost,
+            ..Default::default()
+        }
+    }
+
+    fn arena() -> CallTraceArena {
+        CallTraceArena {
+            arena: vec![
+                node(0, None, vec![1, 2], call(0, "Test", "testFoo", 100)),
+                node(1, Some(0), vec![], call(1, "Token", "transfer", 30)),
+                node(
+                    2,
+                    Some(0),
+                    vec![],
+                    CallTrace { kind: CallKind::Create, ..call(1, "Token", "", 50) },
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn can_export_json() {
+        let json = arena().to_json();
+        assert_eq!(json["function"], "testFoo");
+        assert_eq!(json["gas"], 100);
+        assert_eq!(json["calls"][0]["label"], "Token");
+        assert_eq!(json["calls"][0]["inputs"], json!(["1"]));
+        assert_eq!(json["calls"][1]["kind"], "Create");
+    }
+
+    #[test]
+    fn can_export_folded_stacks() {
+        assert_eq!(
+            arena().to_folded_stacks(),
+            "Test::testFoo 20\nTest::testFoo;Token::transfer 30\nTest::testFoo;new Token 50\n"
+        );
+    }
+}
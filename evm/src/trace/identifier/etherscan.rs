@@ -0,0 +1,165 @@
+use super::TraceIdentifier;
+use crate::abi::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS};
+use ethers::{
+    abi::{Abi, Address},
+    etherscan::{errors::EtherscanError, Client},
+    types::Chain,
+};
+use foundry_utils::RuntimeOrHandle;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap, path::PathBuf};
+
+/// A contract that is verified on Etherscan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EtherscanContract {
+    name: String,
+    abi: Abi,
+}
+
+/// The Etherscan trace identifier looks up the names and ABIs of verified contracts on Etherscan.
+///
+/// Every address is only looked up once. If a cache directory is set, the results are also
+/// stored in `<cache dir>/<address>.json`, including the addresses that are not verified.
+pub struct EtherscanIdentifier {
+    client: Client,
+    cache_dir: Option<PathBuf>,
+    runtime: RuntimeOrHandle,
+    /// The contracts that were looked up, or `None` for addresses that are not verified
+    contracts: RefCell<BTreeMap<Address, Option<EtherscanContract>>>,
+}
+
+impl EtherscanIdentifier {
+    pub fn new(chain: Chain, api_key: &str, cache_dir: Option<PathBuf>) -> eyre::Result<Self> {
+        Ok(Self {
+            client: Client::new(chain, api_key)?,
+            cache_dir,
+            runtime: RuntimeOrHandle::new(),
+            contracts: Default::default(),
+        })
+    }
+
+    /// Returns the contract at `address`, reading it from the cache or looking it up on Etherscan
+    fn contract(&self, address: &Address) -> Option<EtherscanContract> {
+        if let Some(contract) = self.contracts.borrow().get(address) {
+            return contract.clone()
+        }
+
+        let cache_file = self.cache_dir.as_ref().map(|dir| dir.join(format!("{:?}.json", address)));
+        let cached = cache_file
+            .as_ref()
+            .and_then(|file| std::fs::read(file).ok())
+            .and_then(|content| serde_json::from_slice::<Option<EtherscanContract>>(&content).ok());
+
+        let contract = match cached {
+            Some(contract) => contract,
+            None => {
+                // Failed lookups are not cached, so they are tried again for the next trace
+                let contract = match self.runtime.block_on(self.fetch(*address)) {
+                    Ok(contract) => contract,
+                    Err(err) => {
+                        tracing::warn!(target: "etherscan", ?address, %err, "lookup failed");
+                        return None
+                    }
+                };
+                if let Some(file) = cache_file {
+                    if let Err(err) = write_cache(&file, &contract) {
+                        tracing::warn!(target: "etherscan", ?file, %err, "caching failed");
+                    }
+                }
+                contract
+            }
+        };
+
+        self.contracts.borrow_mut().insert(*address, contract.clone());
+        contract
+    }
+
+    /// Looks up the contract at `address` on Etherscan.
+    ///
+    /// For proxies, the ABI of the implementation is used.
+    async fn fetch(&self, address: Address) -> eyre::Result<Option<EtherscanContract>> {
+        let metadata = match self.client.contract_source_code(address).await {
+            Ok(source) => match source.items.into_iter().next() {
+                Some(metadata) => metadata,
+                None => return Ok(None),
+            },
+            Err(EtherscanError::ContractCodeNotVerified(_)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let abi = if metadata.implementation.is_empty() {
+            serde_json::from_str(&metadata.abi)?
+        } else {
+            self.client.contract_abi(metadata.implementation.parse()?).await?
+        };
+
+        Ok(Some(EtherscanContract { name: metadata.contract_name, abi }))
+    }
+}
+
+impl TraceIdentifier for EtherscanIdentifier {
+    fn identify_address(
+        &self,
+        address: &Address,
+        code: Option<&Vec<u8>>,
+    ) -> (Option<String>, Option<String>, Option<Cow<Abi>>) {
+        // Contracts created in the trace, precompiles and the cheatcode and console addresses are
+        // not on chain
+        if code.is_some() ||
+            *address == CHEATCODE_ADDRESS ||
+            *address == HARDHAT_CONSOLE_ADDRESS ||
+            *address <= Address::from_low_u64_be(9)
+        {
+            return (None, None, None)
+        }
+
+        match self.contract(address) {
+            Some(EtherscanContract { name, abi }) => {
+                (Some(name.clone()), Some(name), Some(Cow::Owned(abi)))
+            }
+            None => (None, None, None),
+        }
+    }
+}
+
+fn write_cache(file: &std::path::Path, contract: &Option<EtherscanContract>) -> eyre::Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file, serde_json::to_vec(contract)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_read_cached_contracts() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().to_path_buf();
+        let verified = Address::random();
+        let unverified = Address::random();
+        let abi = ethers::abi::parse_abi(&["function foo()"]).unwrap();
+
+        write_cache(
+            &cache_dir.join(format!("{:?}.json", verified)),
+            &Some(EtherscanContract { name: "Foo".to_string(), abi }),
+        )
+        .unwrap();
+        write_cache(&cache_dir.join(format!("{:?}.json", unverified)), &None).unwrap();
+
+        // The client is never used, since all addresses are cached
+        let identifier =
+            EtherscanIdentifier::new(Chain::Mainnet, "", Some(cache_dir.clone())).unwrap();
+
+        let (contract, label, abi) = identifier.identify_address(&verified, None);
+        assert_eq!(contract, Some("Foo".to_string()));
+        assert_eq!(label, Some("Foo".to_string()));
+        assert!(abi.unwrap().function("foo").is_ok());
+        assert!(identifier.identify_address(&unverified, None).2.is_none());
+
+        // Contracts created during execution are never looked up
+        assert!(identifier.identify_address(&verified, Some(&vec![1])).2.is_none());
+    }
+}
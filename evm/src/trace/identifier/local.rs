@@ -1,21 +1,9 @@
+use super::TraceIdentifier;
 use ethers::{
     abi::{Abi, Address},
     prelude::ArtifactId,
 };
-use std::collections::BTreeMap;
-
-/// Trace identifiers figure out what ABIs and labels belong to all the addresses of the trace.
-pub trait TraceIdentifier {
-    /// Attempts to identify an address in one or more call traces.
-    ///
-    /// The tuple is of the format `(contract, label, abi)`, where `contract` is intended to be of
-    /// the format `"<artifact>:<contract>"`, e.g. `"Foo.json:Foo"`.
-    fn identify_address(
-        &self,
-        address: &Address,
-        code: Option<&Vec<u8>>,
-    ) -> (Option<String>, Option<String>, Option<&Abi>);
-}
+use std::{borrow::Cow, collections::BTreeMap};
 
 /// The local trace identifier keeps track of addresses that are instances of local contracts.
 pub struct LocalTraceIdentifier {
@@ -40,14 +28,14 @@ impl TraceIdentifier for LocalTraceIdentifier {
         &self,
         _: &Address,
         code: Option<&Vec<u8>>,
-    ) -> (Option<String>, Option<String>, Option<&Abi>) {
+    ) -> (Option<String>, Option<String>, Option<Cow<Abi>>) {
         if let Some(code) = code {
             if let Some((_, (name, abi))) = self
                 .local_contracts
                 .iter()
                 .find(|(known_code, _)| diff_score(known_code, code) < 0.1)
             {
-                (Some(name.clone()), Some(name.clone()), Some(abi))
+                (Some(name.clone()), Some(name.clone()), Some(Cow::Borrowed(abi)))
             } else {
                 (None, None, None)
             }
@@ -60,7 +48,7 @@ impl TraceIdentifier for LocalTraceIdentifier {
 /// Very simple fuzzy matching of contract bytecode.
 ///
 /// Will fail for small contracts that are essentially all immutable variables.
-fn diff_score(a: &[u8], b: &[u8]) -> f64 {
+pub fn diff_score(a: &[u8], b: &[u8]) -> f64 {
     let cutoff_len = usize::min(a.len(), b.len());
     if cutoff_len == 0 {
         return 1.0
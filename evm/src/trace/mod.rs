@@ -7,6 +7,10 @@ pub use identifier::TraceIdentifier;
 mod decoder;
 pub use decoder::CallTraceDecoder;
 
+/// Exporting call traces as JSON and folded stacks
+mod export;
+pub use export::TraceFormat;
+
 use crate::{abi::CHEATCODE_ADDRESS, CallKind};
 use ansi_term::Colour;
 use ethers::{
@@ -122,6 +126,11 @@ impl fmt::Display for CallTraceArena {
                 }
             }
 
+            // Display storage changes
+            for change in &node.trace.storage_changes {
+                writeln!(writer, "{}{}", left_prefix, change)?;
+            }
+
             // Display trace return data
             let color = trace_color(&node.trace);
             write!(writer, "{}{}", child, EDGE)?;
@@ -294,6 +303,8 @@ pub struct CallTrace {
     pub output: RawOrDecodedReturnData,
     /// The gas cost of the call
     pub gas_cost: u64,
+    /// The storage slots written by the call, excluding its subcalls
+    pub storage_changes: Vec<StorageChange>,
 }
 
 impl CallTrace {
@@ -368,6 +379,42 @@ impl fmt::Display for CallTrace {
     }
 }
 
+/// A write to a storage slot.
+///
+/// If a call writes to the same slot several times, only the value before the first write and the
+/// value after the last write are kept.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StorageChange {
+    /// The address of the account whose storage was written, which differs from the address of
+    /// the call for delegate calls
+    pub address: Address,
+    /// The slot that was written
+    pub slot: U256,
+    /// The value of the slot before the write
+    pub old_value: U256,
+    /// The value of the slot after the write
+    pub new_value: U256,
+    /// The name of the variable stored in the slot, if the storage layout of the contract is known
+    pub label: Option<String>,
+}
+
+impl fmt::Display for StorageChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let slot = match &self.label {
+            Some(label) => format!("{} (slot {:#x})", label, self.slot),
+            None => format!("slot {:#x}", self.slot),
+        };
+        write!(
+            f,
+            "{} {}: {:#x} → {:#x}",
+            Colour::Purple.paint("storage"),
+            slot,
+            self.old_value,
+            self.new_value
+        )
+    }
+}
+
 /// Specifies the kind of trace.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TraceKind {
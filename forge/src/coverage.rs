@@ -0,0 +1,282 @@
+pub use foundry_evm::coverage::{HitMap, HitMaps};
+
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
+use ethers::solc::{artifacts::ContractBytecode, sourcemap::SourceMap, ArtifactId};
+use foundry_evm::{coverage::instructions, trace::identifier::diff_score};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// A coverage report for a set of source files, built from the hit maps collected by the
+/// coverage inspector.
+#[derive(Debug, Default)]
+pub struct CoverageReport {
+    /// The source files to report coverage for, keyed by their ID in the source maps
+    sources: BTreeMap<u32, SourceFile>,
+    /// The coverage of each contract, keyed by its artifact, since contracts in different files
+    /// can have the same name
+    contracts: BTreeMap<ArtifactId, ContractCoverage>,
+}
+
+/// A source file and the byte offsets at which its lines start
+#[derive(Debug)]
+struct SourceFile {
+    path: PathBuf,
+    line_offsets: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(path: PathBuf, content: &str) -> Self {
+        let line_offsets = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+        Self { path, line_offsets }
+    }
+
+    /// Returns the (1-based) line number of the given byte offset
+    fn line(&self, offset: usize) -> usize {
+        match self.line_offsets.binary_search(&offset) {
+            Ok(line) => line + 1,
+            Err(line) => line,
+        }
+    }
+}
+
+/// The coverage of a single contract
+#[derive(Debug, Default)]
+pub struct ContractCoverage {
+    /// The number of times each line was executed, keyed by source ID and line number
+    pub lines: BTreeMap<(u32, usize), u64>,
+    /// The branches of the contract
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl ContractCoverage {
+    /// Returns the number of lines that were executed and the total number of lines
+    pub fn line_hits(&self) -> (usize, usize) {
+        (self.lines.values().filter(|hits| **hits > 0).count(), self.lines.len())
+    }
+
+    /// Returns the number of branch paths that were taken and the total number of branch paths
+    pub fn branch_hits(&self) -> (usize, usize) {
+        let taken = self
+            .branches
+            .iter()
+            .map(|branch| (branch.jumped > 0) as usize + (branch.not_jumped > 0) as usize)
+            .sum();
+        (taken, self.branches.len() * 2)
+    }
+}
+
+/// A conditional jump (`JUMPI`) and the number of times each of its paths was taken
+#[derive(Debug, Clone)]
+pub struct BranchCoverage {
+    /// The ID of the source file the branch is in
+    pub source: u32,
+    /// The line the branch is on
+    pub line: usize,
+    /// The number of times the jump was taken
+    pub jumped: u64,
+    /// The number of times the jump was not taken
+    pub not_jumped: u64,
+}
+
+impl CoverageReport {
+    /// Creates an empty report for the given source files, keyed by their ID in the source maps.
+    ///
+    /// Code that maps to any other source file is ignored.
+    pub fn new(sources: BTreeMap<u32, (PathBuf, String)>) -> Self {
+        Self {
+            sources: sources
+                .into_iter()
+                .map(|(id, (path, content))| (id, SourceFile::new(path, &content)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the coverage of a compiled contract, i.e. of both its creation code and its runtime
+    /// code, to the report.
+    ///
+    /// Bytecode that is not fully linked or that has no source map is skipped.
+    pub fn add_contract(
+        &mut self,
+        id: &ArtifactId,
+        contract: ContractBytecode,
+        hit_maps: &HitMaps,
+    ) {
+        let ContractBytecode { bytecode, deployed_bytecode, .. } = contract;
+        let runtime_bytecode = deployed_bytecode.and_then(|bytecode| bytecode.bytecode);
+        for bytecode in bytecode.into_iter().chain(runtime_bytecode) {
+            if let (Some(code), Some(Ok(source_map))) =
+                (bytecode.object.as_bytes(), bytecode.source_map())
+            {
+                self.add_bytecode(id, code, &source_map, hit_maps);
+            }
+        }
+    }
+
+    /// Adds the coverage of a piece of a contract's bytecode (either the creation code or the
+    /// runtime code) to the report.
+    ///
+    /// The hit maps are matched to the bytecode using the same fuzzy matching that is used to
+    /// identify contracts in traces, since the executed code may contain immutables or
+    /// constructor arguments.
+    pub fn add_bytecode(
+        &mut self,
+        id: &ArtifactId,
+        bytecode: &[u8],
+        source_map: &SourceMap,
+        hit_maps: &HitMaps,
+    ) {
+        // Interfaces and abstract contracts have no code to cover
+        if bytecode.is_empty() {
+            return
+        }
+
+        let hit_maps: Vec<&HitMap> =
+            hit_maps.0.values().filter(|map| diff_score(&map.bytecode, bytecode) < 0.1).collect();
+        let contract = self.contracts.entry(id.clone()).or_default();
+
+        // A line is executed as often as its most executed instruction
+        let mut lines: BTreeMap<(u32, usize), u64> = BTreeMap::new();
+        for instruction in instructions(bytecode) {
+            // The metadata appended to the bytecode is not part of the source map
+            let element = match source_map.get(instruction.ic) {
+                Some(element) => element,
+                None => break,
+            };
+            let (source, file) = match element
+                .index
+                .and_then(|index| self.sources.get(&index).map(|file| (index, file)))
+            {
+                Some(source) => source,
+                None => continue,
+            };
+            let line = file.line(element.offset);
+
+            let hits = hit_maps.iter().map(|map| map.hits(instruction.pc)).sum();
+            let line_hits = lines.entry((source, line)).or_default();
+            *line_hits = (*line_hits).max(hits);
+
+            if instruction.is_branch() {
+                let (jumped, not_jumped) = hit_maps
+                    .iter()
+                    .map(|map| map.branch_hits(instruction.pc))
+                    .fold((0, 0), |acc, hits| (acc.0 + hits.0, acc.1 + hits.1));
+                contract.branches.push(BranchCoverage { source, line, jumped, not_jumped });
+            }
+        }
+
+        for (line, hits) in lines {
+            *contract.lines.entry(line).or_default() += hits;
+        }
+    }
+
+    /// Returns the coverage of each contract, keyed by its artifact
+    pub fn contracts(&self) -> &BTreeMap<ArtifactId, ContractCoverage> {
+        &self.contracts
+    }
+
+    /// Writes the report in the LCOV tracefile format.
+    ///
+    /// See <https://manpages.debian.org/unstable/lcov/geninfo.1.en.html#TRACEFILE_FORMAT>
+    pub fn write_lcov(&self, mut out: impl Write) -> io::Result<()> {
+        // Contracts can share source files, e.g. through inheritance, so coverage is merged per
+        // source file
+        let mut files: BTreeMap<u32, (BTreeMap<usize, u64>, Vec<&BranchCoverage>)> =
+            BTreeMap::new();
+        for contract in self.contracts.values() {
+            for ((source, line), hits) in &contract.lines {
+                *files.entry(*source).or_default().0.entry(*line).or_default() += hits;
+            }
+            for branch in &contract.branches {
+                files.entry(branch.source).or_default().1.push(branch);
+            }
+        }
+
+        for (source, (lines, branches)) in files {
+            writeln!(out, "TN:")?;
+            writeln!(out, "SF:{}", self.sources[&source].path.display())?;
+
+            for (line, hits) in &lines {
+                writeln!(out, "DA:{},{}", line, hits)?;
+            }
+            writeln!(out, "LF:{}", lines.len())?;
+            writeln!(out, "LH:{}", lines.values().filter(|hits| **hits > 0).count())?;
+
+            let mut branches_hit = 0;
+            for (block, branch) in branches.iter().enumerate() {
+                let executed = branch.jumped + branch.not_jumped > 0;
+                for (path, taken) in [branch.jumped, branch.not_jumped].into_iter().enumerate() {
+                    if executed {
+                        writeln!(out, "BRDA:{},{},{},{}", branch.line, block, path, taken)?;
+                    } else {
+                        writeln!(out, "BRDA:{},{},{},-", branch.line, block, path)?;
+                    }
+                    if taken > 0 {
+                        branches_hit += 1;
+                    }
+                }
+            }
+            writeln!(out, "BRF:{}", branches.len() * 2)?;
+            writeln!(out, "BRH:{}", branches_hit)?;
+
+            writeln!(out, "end_of_record")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        table.set_header(vec![
+            Cell::new("Contract").add_attribute(Attribute::Bold),
+            Cell::new("% Lines").add_attribute(Attribute::Bold),
+            Cell::new("% Branches").add_attribute(Attribute::Bold),
+        ]);
+
+        let mut total_lines = (0, 0);
+        let mut total_branches = (0, 0);
+        for (id, contract) in &self.contracts {
+            let lines = contract.line_hits();
+            let branches = contract.branch_hits();
+            table.add_row(vec![
+                Cell::new(id.identifier()),
+                coverage_cell(lines.0, lines.1),
+                coverage_cell(branches.0, branches.1),
+            ]);
+
+            total_lines = (total_lines.0 + lines.0, total_lines.1 + lines.1);
+            total_branches = (total_branches.0 + branches.0, total_branches.1 + branches.1);
+        }
+        table.add_row(vec![
+            Cell::new("Total").add_attribute(Attribute::Bold),
+            coverage_cell(total_lines.0, total_lines.1),
+            coverage_cell(total_branches.0, total_branches.1),
+        ]);
+
+        writeln!(f, "{}", table)
+    }
+}
+
+/// Formats a coverage ratio as a percentage, colored by how well the code is covered
+fn coverage_cell(hit: usize, total: usize) -> Cell {
+    if total == 0 {
+        return Cell::new("-")
+    }
+
+    let percentage = hit as f64 / total as f64 * 100.0;
+    let color = match percentage {
+        p if p >= 90.0 => Color::Green,
+        p if p >= 50.0 => Color::Yellow,
+        _ => Color::Red,
+    };
+    Cell::new(format!("{:.2}% ({}/{})", percentage, hit, total)).fg(color)
+}
@@ -66,7 +66,9 @@ impl GasReport {
                     }
                     // TODO: More robust test contract filtering
                     RawOrDecodedCall::Decoded(func, _)
-                        if !func.starts_with("test") && func != "setUp" =>
+                        if !func.starts_with("test") &&
+                            !func.starts_with("invariant") &&
+                            func != "setUp" =>
                     {
                         let function_report = contract_report
                             .functions
@@ -1,6 +1,9 @@
 /// Gas reports
 pub mod gas_report;
 
+/// Coverage reports
+pub mod coverage;
+
 /// The Forge test runner
 mod runner;
 pub use runner::{ContractRunner, SuiteResult, TestKind, TestKindGas, TestResult};
@@ -29,6 +32,7 @@ pub mod test_helpers {
     use foundry_evm::{
         executor::{
             builder::Backend,
+            inspector::CheatsConfig,
             opts::{Env, EvmOpts},
             DatabaseRef, Executor, ExecutorBuilder,
         },
@@ -63,7 +67,7 @@ pub mod test_helpers {
 
     pub fn test_executor() -> Executor<Backend> {
         ExecutorBuilder::new()
-            .with_cheatcodes(false)
+            .with_cheatcodes(CheatsConfig::default())
             .with_config((*EVM_OPTS).evm_env())
             .build(Backend::simple())
     }
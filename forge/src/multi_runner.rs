@@ -7,12 +7,18 @@ use ethers::{
 };
 use eyre::Result;
 use foundry_evm::executor::{
-    builder::Backend, opts::EvmOpts, DatabaseRef, Executor, ExecutorBuilder, Fork, SpecId,
+    builder::Backend, fork::ForkCache, inspector::CheatsConfig, opts::EvmOpts, DatabaseRef,
+    Executor, ExecutorBuilder, Fork, SpecId,
 };
 use foundry_utils::PostLinkInput;
 use proptest::test_runner::TestRunner;
 use rayon::prelude::*;
-use std::{collections::BTreeMap, marker::Sync, path::Path, sync::mpsc::Sender};
+use std::{
+    collections::BTreeMap,
+    marker::Sync,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::Sender,
+};
 
 /// Builder used for instantiating the multi-contract runner
 #[derive(Debug, Default)]
@@ -28,6 +34,16 @@ pub struct MultiContractRunnerBuilder {
     pub evm_spec: Option<SpecId>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// Where the storage of forks created at runtime is cached
+    pub fork_cache: Option<ForkCache>,
+    /// The directory in which the counterexamples of failed fuzz tests are saved
+    pub fuzz_corpus_dir: Option<PathBuf>,
+    /// The maximum number of calls in each sequence of calls of an invariant test
+    pub invariant_depth: Option<usize>,
+    /// Whether or not to collect coverage info
+    pub coverage: bool,
+    /// What the cheatcodes are allowed to do outside of the EVM
+    pub cheats_config: CheatsConfig,
 }
 
 pub type DeployableContracts = BTreeMap<ArtifactId, (Abi, Bytes, Vec<Bytes>)>;
@@ -87,7 +103,9 @@ impl MultiContractRunnerBuilder {
                 let abi = contract.abi.expect("We should have an abi by now");
                 // if its a test, add it to deployable contracts
                 if abi.constructor.as_ref().map(|c| c.inputs.is_empty()).unwrap_or(true) &&
-                    abi.functions().any(|func| func.name.starts_with("test"))
+                    abi.functions().any(|func| {
+                        func.name.starts_with("test") || func.name.starts_with("invariant")
+                    })
                 {
                     deployable_contracts
                         .insert(id.clone(), (abi.clone(), bytecode, dependencies.to_vec()));
@@ -113,6 +131,12 @@ impl MultiContractRunnerBuilder {
             errors: Some(execution_info.2),
             source_paths,
             fork: self.fork,
+            fork_cache: self.fork_cache,
+            fuzz_corpus_dir: self.fuzz_corpus_dir,
+            // Sequences have at least one call
+            invariant_depth: self.invariant_depth.unwrap_or(15).max(1),
+            coverage: self.coverage,
+            cheats_config: self.cheats_config,
         })
     }
 
@@ -145,6 +169,36 @@ impl MultiContractRunnerBuilder {
         self.fork = fork;
         self
     }
+
+    #[must_use]
+    pub fn with_fork_cache(mut self, fork_cache: Option<ForkCache>) -> Self {
+        self.fork_cache = fork_cache;
+        self
+    }
+
+    #[must_use]
+    pub fn with_fuzz_corpus_dir(mut self, fuzz_corpus_dir: Option<PathBuf>) -> Self {
+        self.fuzz_corpus_dir = fuzz_corpus_dir;
+        self
+    }
+
+    #[must_use]
+    pub fn invariant_depth(mut self, depth: usize) -> Self {
+        self.invariant_depth = Some(depth);
+        self
+    }
+
+    #[must_use]
+    pub fn set_coverage(mut self, enable: bool) -> Self {
+        self.coverage = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cheats_config(mut self, cheats_config: CheatsConfig) -> Self {
+        self.cheats_config = cheats_config;
+        self
+    }
 }
 
 /// A multi contract runner receives a set of contracts deployed in an EVM instance and proceeds
@@ -169,6 +223,16 @@ pub struct MultiContractRunner {
     pub source_paths: BTreeMap<String, String>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// Where the storage of forks created at runtime is cached
+    pub fork_cache: Option<ForkCache>,
+    /// The directory in which the counterexamples of failed fuzz tests are saved
+    pub fuzz_corpus_dir: Option<PathBuf>,
+    /// The maximum number of calls in each sequence of calls of an invariant test
+    pub invariant_depth: usize,
+    /// Whether or not to collect coverage info
+    pub coverage: bool,
+    /// What the cheatcodes are allowed to do outside of the EVM
+    pub cheats_config: CheatsConfig,
 }
 
 impl MultiContractRunner {
@@ -206,18 +270,22 @@ impl MultiContractRunner {
             .filter(|(_, (abi, _, _))| abi.functions().any(|func| filter.matches_test(&func.name)))
             .map(|(id, (abi, deploy_code, libs))| {
                 let mut builder = ExecutorBuilder::new()
-                    .with_cheatcodes(self.evm_opts.ffi)
+                    .with_cheatcodes(self.cheats_config.clone())
                     .with_config(env.clone())
                     .with_spec(self.evm_spec)
-                    .with_gas_limit(self.evm_opts.gas_limit());
+                    .with_gas_limit(self.evm_opts.gas_limit())
+                    .with_fork_cache(self.fork_cache.clone());
 
                 if self.evm_opts.verbosity >= 3 {
                     builder = builder.with_tracing();
                 }
+                if self.coverage {
+                    builder = builder.with_coverage();
+                }
 
                 let executor = builder.build(db.clone());
                 let result = self.run_tests(
-                    &id.identifier(),
+                    id,
                     abi,
                     executor,
                     deploy_code.clone(),
@@ -238,16 +306,15 @@ impl MultiContractRunner {
         Ok(results)
     }
 
-    // The _name field is unused because we only want it for tracing
     #[tracing::instrument(
         name = "contract",
         skip_all,
         err,
-        fields(name = %_name)
+        fields(name = %id.identifier())
     )]
-    fn run_tests<DB: DatabaseRef + Send + Sync>(
+    fn run_tests<DB: DatabaseRef + Clone + Send + Sync>(
         &self,
-        _name: &str,
+        id: &ArtifactId,
         contract: &Abi,
         executor: Executor<DB>,
         deploy_code: Bytes,
@@ -262,23 +329,57 @@ impl MultiContractRunner {
             self.sender,
             self.errors.as_ref(),
             libs,
+            &self.known_contracts,
+            // Counterexamples are saved per test contract
+            self.fuzz_corpus_dir.as_ref().map(|dir| corpus_dir(dir, &id.source, &id.name)),
+            self.invariant_depth,
         );
         runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
     }
 }
 
+/// Returns the directory in which the counterexamples of a test contract are saved, e.g.
+/// `<dir>/test/Counter.t.sol/CounterTest`.
+///
+/// Sources outside of the project keep their absolute path, so only its normal components are
+/// used to keep the directory inside `dir`.
+fn corpus_dir(dir: &Path, source: &Path, name: &str) -> PathBuf {
+    let source: PathBuf =
+        source.components().filter(|component| matches!(component, Component::Normal(_))).collect();
+    dir.join(source).join(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        coverage::{CoverageReport, HitMaps},
         decode::decode_console_logs,
         test_helpers::{filter::Filter, COMPILED, EVM_OPTS, PROJECT},
     };
-    use foundry_evm::trace::TraceKind;
+    use ethers::abi::{AbiParser, Token};
+    use foundry_config::fs_permissions::{FsPermissions, PathPermission};
+    use foundry_evm::{
+        fuzz::{CounterExample, FuzzConfig},
+        trace::TraceKind,
+    };
 
     /// Builds a base runner
     fn base_runner() -> MultiContractRunnerBuilder {
-        MultiContractRunnerBuilder::default().sender(EVM_OPTS.sender)
+        MultiContractRunnerBuilder::default()
+            .sender(EVM_OPTS.sender)
+            .with_cheats_config(cheats_config())
+    }
+
+    /// The cheatcode config of the test data, which may read and write the file fixtures
+    fn cheats_config() -> CheatsConfig {
+        let root = &(*PROJECT).paths.root;
+        let mut fs_permissions = FsPermissions::new([
+            PathPermission::read("fixtures/File"),
+            PathPermission::read_write("fixtures/File/write"),
+        ]);
+        fs_permissions.join_all(root);
+        CheatsConfig { ffi: EVM_OPTS.ffi, root: root.clone(), fs_permissions }
     }
 
     /// Builds a non-tracing runner
@@ -481,6 +582,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fork() {
+        // Skip fork tests if the RPC url is not set.
+        if std::env::var("ETH_RPC_URL").is_err() {
+            eprintln!("Skipping test test_fork. ETH_RPC_URL is not set.");
+            return
+        };
+
+        let mut runner = runner();
+        let suite_result = runner.test(&Filter::new(".*", ".*", ".*fork"), None, true).unwrap();
+        assert!(!suite_result.is_empty(), "No fork tests were run");
+
+        for (_, SuiteResult { test_results, .. }) in suite_result {
+            for (test_name, result) in test_results {
+                let logs = decode_console_logs(&result.logs);
+                assert!(
+                    result.success,
+                    "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
+                    test_name,
+                    result.reason,
+                    logs.join("\n")
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_fuzz() {
         let mut runner = runner();
@@ -510,6 +637,208 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_corpus_dir() {
+        let dir = Path::new("/project/cache/fuzz");
+        assert_eq!(
+            corpus_dir(dir, Path::new("test/Counter.t.sol"), "CounterTest"),
+            dir.join("test/Counter.t.sol/CounterTest")
+        );
+        assert_eq!(
+            corpus_dir(dir, Path::new("/other/lib/Counter.t.sol"), "CounterTest"),
+            dir.join("other/lib/Counter.t.sol/CounterTest")
+        );
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        let corpus = tempfile::tempdir().unwrap();
+        let filter = Filter::new("testFailFuzz", "FuzzTest", ".*fuzz/Fuzz.t.sol");
+        // No cases are run, so only the saved counterexamples can fail the test
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::new(FuzzConfig {
+                cases: 0,
+                failure_persistence: None,
+                ..Default::default()
+            }))
+            .with_fuzz_corpus_dir(Some(corpus.path().to_path_buf()))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let mut run = || {
+            let result = runner
+                .test(&filter, None, true)
+                .unwrap()
+                .into_values()
+                .flat_map(|suite| suite.test_results.into_values())
+                .next()
+                .expect("testFailFuzz was not run");
+            match result.counterexample {
+                Some(CounterExample::Single(counterexample)) => Some(counterexample.calldata),
+                _ => None,
+            }
+        };
+        assert_eq!(run(), None);
+
+        let func = AbiParser::default().parse_function("function testFailFuzz(uint8 x)").unwrap();
+        let calldata = |x: u8| Bytes::from(func.encode_input(&[Token::Uint(x.into())]).unwrap());
+        let (id, _) = (*COMPILED)
+            .clone()
+            .with_stripped_file_prefixes(&(*PROJECT).paths.root)
+            .into_artifacts()
+            .find(|(id, _)| id.name == "FuzzTest")
+            .unwrap();
+        let corpus_file =
+            corpus_dir(corpus.path(), &id.source, &id.name).join("testFailFuzz(uint8).json");
+        std::fs::create_dir_all(corpus_file.parent().unwrap()).unwrap();
+        std::fs::write(&corpus_file, serde_json::to_string(&[calldata(1), calldata(5)]).unwrap())
+            .unwrap();
+
+        // The saved counterexamples are replayed before any other case, skipping those that
+        // no longer fail
+        assert_eq!(run(), Some(calldata(5)));
+    }
+
+    #[test]
+    fn test_invariant() {
+        let cfg = FuzzConfig { failure_persistence: None, ..Default::default() };
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::new(cfg))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let suite_result =
+            runner.test(&Filter::new(".*", ".*", ".*fuzz/invariant"), None, true).unwrap();
+        assert!(!suite_result.is_empty(), "No invariant tests were run");
+
+        for (_, SuiteResult { test_results, .. }) in suite_result {
+            for (test_name, result) in test_results {
+                assert!(result.is_invariant());
+                match test_name.as_ref() {
+                    "invariantAlwaysTrue()" => {
+                        assert!(result.success, "{} was broken: {:?}", test_name, result.reason)
+                    }
+                    "invariantFlag0()" | "invariantFlag1()" => {
+                        assert!(!result.success, "{} was not broken", test_name);
+                        match result.counterexample {
+                            Some(CounterExample::Sequence(sequence)) => assert!(
+                                !sequence.is_empty(),
+                                "{} was broken without any calls",
+                                test_name
+                            ),
+                            _ => panic!("{} has no call sequence", test_name),
+                        }
+                    }
+                    _ => panic!("Unexpected test {}", test_name),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut runner = base_runner()
+            .set_coverage(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let suite_result =
+            runner.test(&Filter::new(".*", ".*", ".*core/Reverting"), None, true).unwrap();
+
+        let mut hit_maps = None;
+        for (_, SuiteResult { test_results, coverage, .. }) in suite_result {
+            hit_maps = HitMaps::merge_opt(hit_maps, coverage);
+            for (test_name, result) in test_results {
+                assert!(result.coverage.is_some(), "No coverage was collected for {}", test_name);
+                hit_maps = HitMaps::merge_opt(hit_maps, result.coverage);
+            }
+        }
+        let hit_maps = hit_maps.expect("No tests were run");
+
+        let (artifacts, sources) = (*COMPILED).clone().into_artifacts_with_sources();
+        let sources: BTreeMap<u32, (PathBuf, String)> = sources
+            .into_ids()
+            .filter(|(_, path)| path.ends_with("core/Reverting.t.sol"))
+            .map(|(id, path)| (id, (PathBuf::from(&path), std::fs::read_to_string(&path).unwrap())))
+            .collect();
+        let mut report = CoverageReport::new(sources);
+        for (id, artifact) in artifacts {
+            if id.name == "RevertingTest" {
+                let contract: CompactContractBytecode = artifact.into();
+                report.add_contract(&id, contract.into(), &hit_maps);
+            }
+        }
+
+        // The body of `testFailRevert` was executed
+        let (_, contract) = report
+            .contracts()
+            .iter()
+            .find(|(id, _)| id.name == "RevertingTest")
+            .expect("RevertingTest was not reported");
+        let (hit, total) = contract.line_hits();
+        assert!(total > 0, "No lines were found in RevertingTest");
+        assert!(hit > 0, "No lines were covered in RevertingTest");
+    }
+
+    #[test]
+    fn test_coverage_nested_calls() {
+        let mut runner = base_runner()
+            .set_coverage(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let suite_result =
+            runner.test(&Filter::new(".*", ".*", ".*coverage/Nested"), None, true).unwrap();
+
+        let mut hit_maps = None;
+        for (_, SuiteResult { test_results, coverage, .. }) in suite_result {
+            hit_maps = HitMaps::merge_opt(hit_maps, coverage);
+            for (_, result) in test_results {
+                hit_maps = HitMaps::merge_opt(hit_maps, result.coverage);
+            }
+        }
+        let hit_maps = hit_maps.expect("No tests were run");
+
+        let (artifacts, sources) = (*COMPILED).clone().into_artifacts_with_sources();
+        let (source, path) = sources
+            .into_ids()
+            .find(|(_, path)| path.ends_with("coverage/Nested.t.sol"))
+            .expect("Nested.t.sol was not compiled");
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut report = CoverageReport::new(BTreeMap::from([(
+            source,
+            (PathBuf::from(&path), content.clone()),
+        )]));
+        for (id, artifact) in artifacts {
+            if id.name == "Counter" || id.name == "NestedCoverageTest" {
+                let contract: CompactContractBytecode = artifact.into();
+                report.add_contract(&id, contract.into(), &hit_maps);
+            }
+        }
+
+        let line = |code: &str| {
+            let line = content.lines().position(|line| line.contains(code)).unwrap() + 1;
+            (source, line)
+        };
+        // Other test files have a `Counter` too, which is reported separately
+        assert!(report.contracts().keys().filter(|id| id.name == "Counter").count() > 1);
+        let contract = |name: &str| {
+            report
+                .contracts()
+                .iter()
+                .find(|(id, _)| id.name == name && id.source.ends_with("coverage/Nested.t.sol"))
+                .map(|(_, contract)| contract)
+                .unwrap()
+        };
+        let counter = contract("Counter");
+        let test = contract("NestedCoverageTest");
+
+        // The lines of the called contract are attributed to it, not to the caller
+        assert_eq!(counter.lines.get(&line("count += by")), Some(&1));
+        assert_eq!(counter.lines.get(&line("return 42")), Some(&0));
+        assert!(!test.lines.contains_key(&line("count += by")));
+        // The caller is still covered after the nested call returns
+        assert!(test.lines[&line("uint256 afterCall")] > 0);
+        // The setup is shared by both tests, but only counted once
+        assert_eq!(test.lines.get(&line("counter = new Counter()")), Some(&1));
+    }
+
     #[test]
     fn test_trace() {
         let mut runner = tracing_runner();
@@ -547,6 +876,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trace_storage_changes() {
+        let mut runner = tracing_runner();
+        let suite_result =
+            runner.test(&Filter::new(".*", "StorageChangesTest", ".*trace"), None, true).unwrap();
+        let result = &suite_result["trace/StorageChanges.t.sol:StorageChangesTest"].test_results
+            ["testStorageChanges()"];
+        let (_, trace) = result
+            .traces
+            .iter()
+            .find(|(kind, _)| *kind == TraceKind::Execution)
+            .expect("no execution trace");
+
+        // The test itself writes no storage, the counter writes its slot twice
+        assert!(trace.arena[0].trace.storage_changes.is_empty());
+        let changes = &trace.arena[1].trace.storage_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].slot, U256::zero());
+        assert_eq!(changes[0].old_value, U256::zero());
+        assert_eq!(changes[0].new_value, U256::from(2));
+
+        let result = &suite_result["trace/StorageChanges.t.sol:StorageChangesTest"].test_results
+            ["testRevertedStorageChanges()"];
+        let (_, trace) = result
+            .traces
+            .iter()
+            .find(|(kind, _)| *kind == TraceKind::Execution)
+            .expect("no execution trace");
+
+        // The writes of the reverted call are not shown
+        assert!(!trace.arena[1].trace.success);
+        assert!(trace.arena[1].trace.storage_changes.is_empty());
+        let changes = &trace.arena[2].trace.storage_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_value, U256::zero());
+        assert_eq!(changes[0].new_value, U256::from(2));
+    }
+
     #[test]
     fn test_doesnt_run_abstract_contract() {
         let mut runner = runner();
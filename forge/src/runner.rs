@@ -1,13 +1,18 @@
 use crate::TestFilter;
 use ethers::{
     abi::{Abi, Function, RawLog},
+    prelude::ArtifactId,
     types::{Address, Bytes, U256},
 };
 use eyre::Result;
 use foundry_evm::{
-    executor::{CallResult, DatabaseRef, DeployResult, EvmError, Executor},
-    fuzz::{CounterExample, FuzzedCases, FuzzedExecutor},
-    trace::{CallTraceArena, TraceKind},
+    coverage::HitMaps,
+    executor::{
+        CallResult, DatabaseRef, DeployResult, EvmError, Executor, StateChangeset,
+        CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS,
+    },
+    fuzz::{CounterExample, FuzzedCases, FuzzedExecutor, InvariantExecutor, TargetedContracts},
+    trace::{identifier::LocalTraceIdentifier, CallTraceArena, TraceIdentifier, TraceKind},
     CALLER,
 };
 use proptest::test_runner::TestRunner;
@@ -16,6 +21,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     fmt,
+    path::PathBuf,
     time::{Duration, Instant},
 };
 
@@ -26,11 +32,19 @@ pub struct SuiteResult {
     pub duration: Duration,
     /// Individual test results. `test method name -> TestResult`
     pub test_results: BTreeMap<String, TestResult>,
+    /// The coverage info collected during the setup and the calls of the invariant campaign,
+    /// which are shared by all tests, if coverage is enabled
+    #[serde(skip)]
+    pub coverage: Option<HitMaps>,
 }
 
 impl SuiteResult {
-    pub fn new(duration: Duration, test_results: BTreeMap<String, TestResult>) -> Self {
-        Self { duration, test_results }
+    pub fn new(
+        duration: Duration,
+        test_results: BTreeMap<String, TestResult>,
+        coverage: Option<HitMaps>,
+    ) -> Self {
+        Self { duration, test_results, coverage }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -70,6 +84,16 @@ pub struct TestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The coverage info collected during the test, if coverage is enabled.
+    ///
+    /// The coverage of the setup is part of the [SuiteResult] instead, so that it is only counted
+    /// once.
+    #[serde(skip)]
+    pub coverage: Option<HitMaps>,
+
+    /// The time it took to run the test
+    pub duration: Duration,
 }
 
 impl TestResult {
@@ -77,6 +101,11 @@ impl TestResult {
     pub fn is_fuzz(&self) -> bool {
         matches!(self.kind, TestKind::Fuzz(_))
     }
+
+    /// Returns `true` if this is the result of an invariant test
+    pub fn is_invariant(&self) -> bool {
+        matches!(self.kind, TestKind::Invariant { .. })
+    }
 }
 
 /// Used gas by a test
@@ -84,6 +113,7 @@ impl TestResult {
 pub enum TestKindGas {
     Standard(u64),
     Fuzz { runs: usize, mean: u64, median: u64 },
+    Invariant { runs: usize, calls: usize, reverts: usize },
 }
 
 impl fmt::Display for TestKindGas {
@@ -95,6 +125,9 @@ impl fmt::Display for TestKindGas {
             TestKindGas::Fuzz { runs, mean, median } => {
                 write!(f, "(runs: {}, μ: {}, ~: {})", runs, mean, median)
             }
+            TestKindGas::Invariant { runs, calls, reverts } => {
+                write!(f, "(runs: {}, calls: {}, reverts: {})", runs, calls, reverts)
+            }
         }
     }
 }
@@ -106,6 +139,8 @@ impl TestKindGas {
             TestKindGas::Standard(gas) => *gas,
             // We use the median for comparisons
             TestKindGas::Fuzz { median, .. } => *median,
+            // The gas of the calls made by an invariant test is not tracked
+            TestKindGas::Invariant { .. } => 0,
         }
     }
 }
@@ -119,6 +154,9 @@ pub enum TestKind {
     Standard(u64),
     /// A solidity fuzz test, that stores all test cases
     Fuzz(FuzzedCases),
+    /// A solidity invariant test, that stores the number of call sequences that were run, the
+    /// number of calls made and how many of them reverted
+    Invariant { runs: usize, calls: usize, reverts: usize },
 }
 
 impl TestKind {
@@ -131,6 +169,9 @@ impl TestKind {
                 median: fuzzed.median_gas(false),
                 mean: fuzzed.mean_gas(false),
             },
+            TestKind::Invariant { runs, calls, reverts } => {
+                TestKindGas::Invariant { runs: *runs, calls: *calls, reverts: *reverts }
+            }
         }
     }
 }
@@ -145,6 +186,10 @@ pub struct TestSetup {
     pub traces: Vec<(TraceKind, CallTraceArena)>,
     /// Addresses labeled during setup
     pub labeled_addresses: BTreeMap<Address, String>,
+    /// The runtime code of the contracts created by the test contract's constructor and `setUp`
+    pub created_contracts: BTreeMap<Address, Vec<u8>>,
+    /// The coverage info collected during setup
+    pub coverage: Option<HitMaps>,
     /// Whether the setup failed
     pub setup_failed: bool,
     /// The reason the setup failed
@@ -163,6 +208,13 @@ pub struct ContractRunner<'a, DB: DatabaseRef> {
     pub contract: &'a Abi,
     /// All known errors, used to decode reverts
     pub errors: Option<&'a Abi>,
+    /// Compiled contracts that have an ABI and runtime bytecode, used to identify the contracts
+    /// targeted by invariant tests
+    pub known_contracts: &'a BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+    /// The directory in which the counterexamples of the contract's fuzz tests are saved
+    pub fuzz_corpus_dir: Option<PathBuf>,
+    /// The maximum number of calls in each sequence of calls of the contract's invariant tests
+    pub invariant_depth: usize,
 
     /// The initial balance of the test contract
     pub initial_balance: U256,
@@ -180,6 +232,9 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
         sender: Option<Address>,
         errors: Option<&'a Abi>,
         predeploy_libs: &'a [Bytes],
+        known_contracts: &'a BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+        fuzz_corpus_dir: Option<PathBuf>,
+        invariant_depth: usize,
     ) -> Self {
         Self {
             executor,
@@ -189,11 +244,14 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
             sender: sender.unwrap_or_default(),
             errors,
             predeploy_libs,
+            known_contracts,
+            fuzz_corpus_dir,
+            invariant_depth,
         }
     }
 }
 
-impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
+impl<'a, DB: DatabaseRef + Clone + Send + Sync> ContractRunner<'a, DB> {
     /// Deploys the test contract inside the runner from the sending account, and optionally runs
     /// the `setUp` function on the test contract.
     pub fn setup(&mut self, setup: bool) -> Result<TestSetup> {
@@ -205,14 +263,16 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         self.executor.set_nonce(self.sender, 1);
 
         // Deploy libraries
+        let mut coverage = None;
         let mut traces: Vec<(TraceKind, CallTraceArena)> = self
             .predeploy_libs
             .iter()
             .filter_map(|code| {
-                let DeployResult { traces, .. } = self
+                let DeployResult { traces, coverage: library_coverage, .. } = self
                     .executor
                     .deploy(self.sender, code.0.clone(), 0u32.into())
                     .expect("couldn't deploy library");
+                coverage = HitMaps::merge_opt(coverage.take(), library_coverage);
 
                 traces
             })
@@ -220,11 +280,20 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             .collect();
 
         // Deploy an instance of the contract
-        let DeployResult { address, mut logs, traces: constructor_traces, .. } = self
+        let DeployResult {
+            address,
+            mut logs,
+            traces: constructor_traces,
+            state_changeset: constructor_changeset,
+            coverage: constructor_coverage,
+            ..
+        } = self
             .executor
             .deploy(self.sender, self.code.0.clone(), 0u32.into())
             .expect("couldn't deploy");
         traces.extend(constructor_traces.map(|traces| (TraceKind::Deployment, traces)).into_iter());
+        let mut created_contracts = created_contracts(&constructor_changeset);
+        coverage = HitMaps::merge_opt(coverage, constructor_coverage);
 
         // Now we set the contracts initial balance, and we also reset `self.sender`s balance to
         // the initial balance we want
@@ -234,28 +303,56 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         // Optionally call the `setUp` function
         Ok(if setup {
             tracing::trace!("setting up");
-            let (setup_failed, setup_logs, setup_traces, labeled_addresses, reason) = match self
-                .executor
-                .setup(address)
-            {
-                Ok(CallResult { traces, labels, logs, .. }) => (false, logs, traces, labels, None),
-                Err(EvmError::Execution { traces, labels, logs, reason, .. }) => {
-                    (true, logs, traces, labels, Some(format!("Setup failed: {}", reason)))
-                }
-                Err(e) => (
-                    true,
-                    Vec::new(),
-                    None,
-                    BTreeMap::new(),
-                    Some(format!("Setup failed: {}", &e.to_string())),
-                ),
-            };
+            let (setup_failed, setup_logs, setup_traces, labeled_addresses, reason) =
+                match self.executor.setup(address) {
+                    Ok(CallResult {
+                        traces,
+                        labels,
+                        logs,
+                        state_changeset,
+                        coverage: setup_coverage,
+                        ..
+                    }) => {
+                        if let Some(state_changeset) = state_changeset {
+                            created_contracts.extend(self::created_contracts(&state_changeset));
+                        }
+                        coverage = HitMaps::merge_opt(coverage.take(), setup_coverage);
+                        (false, logs, traces, labels, None)
+                    }
+                    Err(EvmError::Execution {
+                        traces,
+                        labels,
+                        logs,
+                        reason,
+                        coverage: setup_coverage,
+                        ..
+                    }) => {
+                        coverage = HitMaps::merge_opt(coverage.take(), setup_coverage);
+                        (true, logs, traces, labels, Some(format!("Setup failed: {}", reason)))
+                    }
+                    Err(e) => (
+                        true,
+                        Vec::new(),
+                        None,
+                        BTreeMap::new(),
+                        Some(format!("Setup failed: {}", &e.to_string())),
+                    ),
+                };
             traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
             logs.extend_from_slice(&setup_logs);
 
-            TestSetup { address, logs, traces, labeled_addresses, setup_failed, reason }
+            TestSetup {
+                address,
+                logs,
+                traces,
+                labeled_addresses,
+                created_contracts,
+                coverage,
+                setup_failed,
+                reason,
+            }
         } else {
-            TestSetup { address, logs, traces, ..Default::default() }
+            TestSetup { address, logs, traces, created_contracts, coverage, ..Default::default() }
         })
     }
 
@@ -285,9 +382,12 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                         kind: TestKind::Standard(0),
                         traces: setup.traces,
                         labeled_addresses: setup.labeled_addresses,
+                        coverage: None,
+                        duration: start.elapsed(),
                     },
                 )]
                 .into(),
+                setup.coverage,
             ))
         }
 
@@ -304,7 +404,20 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             .map(|func| (func, func.name.starts_with("testFail")))
             .collect();
 
-        let test_results = tests
+        // Collect invariants, which are only checked if fuzz tests are included
+        let invariants: Vec<_> = self
+            .contract
+            .functions()
+            .into_iter()
+            .filter(|func| {
+                func.name.starts_with("invariant") &&
+                    func.inputs.is_empty() &&
+                    filter.matches_test(func.signature()) &&
+                    include_fuzz_tests
+            })
+            .collect();
+
+        let mut test_results = tests
             .par_iter()
             .filter_map(|(func, should_fail)| {
                 let result = if func.inputs.is_empty() {
@@ -319,6 +432,14 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             })
             .collect::<Result<BTreeMap<_, _>>>()?;
 
+        let mut coverage = setup.coverage.clone();
+        if let Some(fuzzer) = fuzzer.filter(|_| !invariants.is_empty()) {
+            let (invariant_results, campaign_coverage) =
+                self.run_invariant_test(&invariants, fuzzer, setup);
+            test_results.extend(invariant_results);
+            coverage = HitMaps::merge_opt(coverage, campaign_coverage);
+        }
+
         let duration = start.elapsed();
         if !test_results.is_empty() {
             let successful = test_results.iter().filter(|(_, tst)| tst.success).count();
@@ -329,7 +450,7 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                 test_results.len()
             );
         }
-        Ok(SuiteResult::new(duration, test_results))
+        Ok(SuiteResult::new(duration, test_results, coverage))
     }
 
     #[tracing::instrument(name = "test", skip_all, fields(name = %func.signature(), %should_fail))]
@@ -343,44 +464,67 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
 
         // Run unit test
         let start = Instant::now();
-        let (reverted, reason, gas, stipend, execution_traces, state_changeset) = match self
-            .executor
-            .call::<(), _, _>(self.sender, address, func.clone(), (), 0.into(), self.errors)
-        {
-            Ok(CallResult {
-                reverted,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, None, gas, stipend, execution_trace, state_changeset)
-            }
-            Err(EvmError::Execution {
-                reverted,
-                reason,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, Some(reason), gas, stipend, execution_trace, state_changeset)
-            }
-            Err(err) => {
-                tracing::error!(?err);
-                return Err(err.into())
-            }
-        };
+        let (reverted, reason, gas, stipend, execution_traces, state_changeset, coverage) =
+            match self.executor.call::<(), _, _>(
+                self.sender,
+                address,
+                func.clone(),
+                (),
+                0.into(),
+                self.errors,
+            ) {
+                Ok(CallResult {
+                    reverted,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    labels: new_labels,
+                    state_changeset,
+                    coverage: execution_coverage,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (
+                        reverted,
+                        None,
+                        gas,
+                        stipend,
+                        execution_trace,
+                        state_changeset,
+                        execution_coverage,
+                    )
+                }
+                Err(EvmError::Execution {
+                    reverted,
+                    reason,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    labels: new_labels,
+                    state_changeset,
+                    coverage: execution_coverage,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (
+                        reverted,
+                        Some(reason),
+                        gas,
+                        stipend,
+                        execution_trace,
+                        state_changeset,
+                        execution_coverage,
+                    )
+                }
+                Err(err) => {
+                    tracing::error!(?err);
+                    return Err(err.into())
+                }
+            };
         traces.extend(execution_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
 
         let success = self.executor.is_success(
@@ -391,8 +535,9 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         );
 
         // Record test execution time
+        let duration = start.elapsed();
         tracing::debug!(
-            duration = ?start.elapsed(),
+            ?duration,
             %success,
             %gas
         );
@@ -405,6 +550,8 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             kind: TestKind::Standard(gas.overflowing_sub(stipend).0),
             traces,
             labeled_addresses,
+            coverage,
+            duration,
         })
     }
 
@@ -420,12 +567,9 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
 
         // Run fuzz test
         let start = Instant::now();
-        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender).fuzz(
-            func,
-            address,
-            should_fail,
-            self.errors,
-        );
+        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender)
+            .with_corpus_dir(self.fuzz_corpus_dir.clone())
+            .fuzz(func, address, should_fail, self.errors);
 
         // Record logs, labels and traces
         logs.append(&mut result.logs);
@@ -433,8 +577,9 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         traces.extend(result.traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
 
         // Record test execution time
+        let duration = start.elapsed();
         tracing::debug!(
-            duration = ?start.elapsed(),
+            ?duration,
             success = %result.success
         );
 
@@ -446,6 +591,116 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             kind: TestKind::Fuzz(result.cases),
             traces,
             labeled_addresses,
+            coverage: result.coverage,
+            duration,
         })
     }
+
+    /// Runs the invariant campaign of the test contract.
+    ///
+    /// Returns the results of all `invariants` along with the coverage of the calls of the
+    /// campaign, which are shared by all invariants.
+    #[tracing::instrument(name = "invariant-test", skip_all)]
+    pub fn run_invariant_test(
+        &self,
+        invariants: &[&Function],
+        runner: TestRunner,
+        setup: TestSetup,
+    ) -> (BTreeMap<String, TestResult>, Option<HitMaps>) {
+        let TestSetup { address, logs, traces, labeled_addresses, created_contracts, .. } = setup;
+
+        // Only contracts created during setup whose ABI we know are targeted
+        let identifier = LocalTraceIdentifier::new(self.known_contracts);
+        let targets: TargetedContracts = created_contracts
+            .iter()
+            .filter(|(target, _)| {
+                **target != address &&
+                    **target != CHEATCODE_ADDRESS &&
+                    **target != HARDHAT_CONSOLE_ADDRESS
+            })
+            .filter_map(|(target, code)| {
+                let (name, _, abi) = identifier.identify_address(target, Some(code));
+                name.zip(abi).map(|(name, abi)| (*target, (name, abi.into_owned())))
+            })
+            .collect();
+
+        // Run invariant test
+        let start = Instant::now();
+        let mut result =
+            InvariantExecutor::new(&self.executor, runner, self.sender, self.invariant_depth)
+                .invariant_fuzz(invariants, address, &targets, self.errors);
+
+        // Record test execution time
+        let duration = start.elapsed();
+        tracing::debug!(
+            ?duration,
+            broken = %result.failures.len()
+        );
+
+        let results = invariants
+            .iter()
+            .map(|func| {
+                let mut logs = logs.clone();
+                let mut traces = traces.clone();
+                let mut labeled_addresses = labeled_addresses.clone();
+
+                let (success, reason, counterexample) = match result
+                    .failures
+                    .remove(&func.signature())
+                {
+                    Some(mut failure) => {
+                        // Record logs, labels and traces of the broken invariant
+                        logs.append(&mut failure.logs);
+                        labeled_addresses.append(&mut failure.labeled_addresses);
+                        traces.extend(
+                            failure.traces.into_iter().map(|traces| (TraceKind::Execution, traces)),
+                        );
+                        (
+                            false,
+                            failure.reason,
+                            Some(CounterExample::Sequence(failure.counterexample)),
+                        )
+                    }
+                    None => (true, None, None),
+                };
+
+                (
+                    func.signature(),
+                    TestResult {
+                        success,
+                        reason,
+                        counterexample,
+                        logs,
+                        kind: TestKind::Invariant {
+                            runs: result.runs,
+                            calls: result.calls,
+                            reverts: result.reverts,
+                        },
+                        traces,
+                        labeled_addresses,
+                        coverage: result.invariant_coverage.remove(&func.signature()),
+                        // All invariants are checked during the same campaign
+                        duration,
+                    },
+                )
+            })
+            .collect();
+
+        (results, result.coverage)
+    }
+}
+
+/// Returns the runtime code of all contracts in a changeset
+fn created_contracts(state_changeset: &StateChangeset) -> BTreeMap<Address, Vec<u8>> {
+    state_changeset
+        .iter()
+        .filter_map(|(address, account)| {
+            account
+                .info
+                .code
+                .as_ref()
+                .filter(|code| !code.is_empty())
+                .map(|code| (*address, code.to_vec()))
+        })
+        .collect()
 }
@@ -0,0 +1,242 @@
+use crate::types::{BlockTag, LogFilter};
+use ethers::{
+    types::{Block, Log, Transaction, TransactionReceipt, H256, U256, U64},
+    utils::{keccak256, rlp::RlpStream},
+};
+use std::collections::HashMap;
+
+/// The blocks, transactions and receipts mined by the node.
+///
+/// When forking, the chain starts at the forked block. Blocks before it are not available.
+#[derive(Debug, Clone)]
+pub struct Blockchain {
+    /// The mined blocks, starting with the genesis block
+    blocks: Vec<Block<Transaction>>,
+    /// The mined transactions, keyed by their hash
+    transactions: HashMap<H256, Transaction>,
+    /// The receipts of the mined transactions, keyed by the transaction hash
+    receipts: HashMap<H256, TransactionReceipt>,
+}
+
+impl Blockchain {
+    /// Creates a chain that only has a genesis block with the given number and timestamp
+    pub fn new(number: u64, timestamp: u64, gas_limit: U256) -> Self {
+        let mut genesis = Block {
+            number: Some(number.into()),
+            timestamp: timestamp.into(),
+            gas_limit,
+            base_fee_per_gas: Some(U256::zero()),
+            ..Default::default()
+        };
+        genesis.hash = Some(block_hash(&genesis));
+
+        Self { blocks: vec![genesis], transactions: HashMap::new(), receipts: HashMap::new() }
+    }
+
+    /// The latest mined block
+    pub fn latest(&self) -> &Block<Transaction> {
+        self.blocks.last().expect("the genesis block always exists")
+    }
+
+    /// The number of the latest mined block
+    pub fn latest_number(&self) -> u64 {
+        self.latest().number.expect("mined blocks have a number").as_u64()
+    }
+
+    /// The number of the genesis block
+    pub fn genesis_number(&self) -> u64 {
+        self.blocks[0].number.expect("mined blocks have a number").as_u64()
+    }
+
+    /// Resolves a block tag to a block number
+    pub fn resolve(&self, tag: BlockTag) -> u64 {
+        match tag {
+            BlockTag::Earliest => self.genesis_number(),
+            BlockTag::Latest | BlockTag::Pending => self.latest_number(),
+            BlockTag::Number(number) => number,
+        }
+    }
+
+    /// Returns the block with the given number, if it was mined by the node
+    pub fn block_by_number(&self, number: u64) -> Option<&Block<Transaction>> {
+        let idx = number.checked_sub(self.genesis_number())?;
+        self.blocks.get(idx as usize)
+    }
+
+    /// Returns the block with the given hash, if it was mined by the node
+    pub fn block_by_hash(&self, hash: H256) -> Option<&Block<Transaction>> {
+        self.blocks.iter().rev().find(|block| block.hash == Some(hash))
+    }
+
+    /// Returns a mined transaction by its hash
+    pub fn transaction(&self, hash: H256) -> Option<&Transaction> {
+        self.transactions.get(&hash)
+    }
+
+    /// Returns the receipt of a mined transaction by the transaction hash
+    pub fn receipt(&self, hash: H256) -> Option<&TransactionReceipt> {
+        self.receipts.get(&hash)
+    }
+
+    /// Mines a block on top of the latest block with the given transactions and their receipts.
+    ///
+    /// The block related fields of the transactions, receipts and logs are filled in.
+    pub fn mine(
+        &mut self,
+        timestamp: u64,
+        gas_limit: U256,
+        executed: Vec<(Transaction, TransactionReceipt)>,
+    ) -> H256 {
+        let parent = self.latest();
+        let number = U64::from(self.latest_number() + 1);
+        let mut block = Block {
+            parent_hash: parent.hash.expect("mined blocks have a hash"),
+            number: Some(number),
+            timestamp: timestamp.into(),
+            gas_limit,
+            gas_used: executed
+                .iter()
+                .fold(U256::zero(), |sum, (_, receipt)| sum + receipt.gas_used.unwrap_or_default()),
+            base_fee_per_gas: Some(U256::zero()),
+            transactions: executed.iter().map(|(tx, _)| tx.clone()).collect(),
+            ..Default::default()
+        };
+        let hash = block_hash(&block);
+        block.hash = Some(hash);
+
+        let mut log_index: u64 = 0;
+        for (idx, tx) in block.transactions.iter_mut().enumerate() {
+            tx.block_hash = Some(hash);
+            tx.block_number = Some(number);
+            tx.transaction_index = Some(idx.into());
+        }
+        for (idx, (tx, mut receipt)) in executed.into_iter().enumerate() {
+            receipt.block_hash = Some(hash);
+            receipt.block_number = Some(number);
+            receipt.transaction_index = idx.into();
+            for log in receipt.logs.iter_mut() {
+                log.block_hash = Some(hash);
+                log.block_number = Some(number);
+                log.transaction_hash = Some(tx.hash);
+                log.transaction_index = Some(idx.into());
+                log.log_index = Some(log_index.into());
+                log.removed = Some(false);
+                log_index += 1;
+            }
+            self.receipts.insert(tx.hash, receipt);
+        }
+        for tx in &block.transactions {
+            self.transactions.insert(tx.hash, tx.clone());
+        }
+
+        self.blocks.push(block);
+        hash
+    }
+
+    /// Returns the logs of the mined blocks that match the filter
+    pub fn logs(&self, filter: &LogFilter) -> Vec<Log> {
+        let (from, to) = match filter.block_hash {
+            Some(hash) => match self.block_by_hash(hash) {
+                Some(block) => {
+                    let number = block.number.expect("mined blocks have a number").as_u64();
+                    (number, number)
+                }
+                None => return Vec::new(),
+            },
+            None => (
+                self.resolve(filter.from_block.unwrap_or(BlockTag::Latest)),
+                self.resolve(filter.to_block.unwrap_or(BlockTag::Latest)),
+            ),
+        };
+
+        (from..=to)
+            .filter_map(|number| self.block_by_number(number))
+            .flat_map(|block| block.transactions.iter())
+            .filter_map(|tx| self.receipts.get(&tx.hash))
+            .flat_map(|receipt| receipt.logs.iter())
+            .filter(|log| filter.matches(log))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Computes the hash of a block mined by the node.
+///
+/// Blocks mined by the node have no state root or proof of work, so the hash commits to the
+/// parent block, the number, the timestamp and the transactions of the block only.
+fn block_hash(block: &Block<Transaction>) -> H256 {
+    let mut stream = RlpStream::new_list(4);
+    stream.append(&block.parent_hash);
+    stream.append(&block.number.unwrap_or_default());
+    stream.append(&block.timestamp);
+    stream.begin_list(block.transactions.len());
+    for tx in &block.transactions {
+        stream.append(&tx.hash);
+    }
+    H256::from(keccak256(stream.out()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OneOrMany;
+    use ethers::types::Address;
+
+    fn mined_tx(nonce: u64, logs: Vec<Log>) -> (Transaction, TransactionReceipt) {
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(nonce + 1),
+            nonce: nonce.into(),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_hash: tx.hash,
+            gas_used: Some(21000.into()),
+            logs,
+            ..Default::default()
+        };
+        (tx, receipt)
+    }
+
+    #[test]
+    fn can_mine_blocks() {
+        let mut chain = Blockchain::new(10, 100, 30_000_000.into());
+        let genesis = chain.latest().hash.unwrap();
+
+        let hash = chain.mine(101, 30_000_000.into(), vec![mined_tx(0, Vec::new())]);
+        assert_eq!(chain.latest_number(), 11);
+        assert_eq!(chain.block_by_number(11).unwrap().parent_hash, genesis);
+        assert_eq!(chain.block_by_hash(hash).unwrap().gas_used, 21000.into());
+        assert!(chain.block_by_number(9).is_none());
+        assert_eq!(chain.resolve(BlockTag::Earliest), 10);
+
+        let tx = chain.transaction(H256::from_low_u64_be(1)).unwrap();
+        assert_eq!(tx.block_hash, Some(hash));
+        assert_eq!(chain.receipt(tx.hash).unwrap().block_number, Some(11.into()));
+    }
+
+    #[test]
+    fn can_filter_logs() {
+        let log =
+            |address: u8| Log { address: Address::repeat_byte(address), ..Default::default() };
+
+        let mut chain = Blockchain::new(0, 0, 30_000_000.into());
+        chain.mine(1, 30_000_000.into(), vec![mined_tx(0, vec![log(1), log(2)])]);
+        chain.mine(2, 30_000_000.into(), vec![mined_tx(1, vec![log(1)])]);
+
+        let all = LogFilter { from_block: Some(BlockTag::Earliest), ..Default::default() };
+        let logs = chain.logs(&all);
+        assert_eq!(logs.len(), 3);
+        assert_eq!(logs[1].log_index, Some(1.into()));
+        assert_eq!(logs[2].block_number, Some(2.into()));
+
+        let latest = LogFilter::default();
+        assert_eq!(chain.logs(&latest).len(), 1);
+
+        let by_address = LogFilter {
+            from_block: Some(BlockTag::Number(1)),
+            address: Some(OneOrMany::One(Address::repeat_byte(2))),
+            ..Default::default()
+        };
+        assert_eq!(chain.logs(&by_address).len(), 1);
+    }
+}
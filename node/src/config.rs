@@ -0,0 +1,107 @@
+use clap::Parser;
+use ethers::types::U256;
+use std::net::{IpAddr, SocketAddr};
+
+/// The mnemonic the development accounts are derived from by default
+pub const DEFAULT_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+/// The chain ID of the node if it is not forking a live chain
+pub const DEFAULT_CHAIN_ID: u64 = 31337;
+
+/// Configuration of a local node
+#[derive(Debug, Clone, Parser)]
+#[clap(name = "foundry-node", version, about = "A local Ethereum development node")]
+pub struct NodeConfig {
+    #[clap(long, help = "The address to listen on.", default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    #[clap(long, short, help = "The port to listen on.", default_value = "8545")]
+    pub port: u16,
+
+    #[clap(
+        long,
+        short,
+        help = "The number of development accounts to generate.",
+        default_value = "10"
+    )]
+    pub accounts: usize,
+
+    #[clap(
+        long,
+        help = "The balance of every development account, in ether.",
+        default_value = "10000"
+    )]
+    pub balance: u64,
+
+    #[clap(
+        long,
+        short,
+        help = "The BIP39 mnemonic the development accounts are derived from.",
+        default_value = DEFAULT_MNEMONIC
+    )]
+    pub mnemonic: String,
+
+    #[clap(
+        long,
+        help = "The chain ID of the node. Defaults to 31337, or to the chain ID of the forked chain."
+    )]
+    pub chain_id: Option<u64>,
+
+    #[clap(long, help = "The block gas limit.", default_value = "30000000")]
+    pub gas_limit: u64,
+
+    #[clap(long, help = "The gas price reported by eth_gasPrice, in wei.", default_value = "0")]
+    pub gas_price: u64,
+
+    #[clap(
+        long,
+        short,
+        help = "Fetch state over a remote endpoint instead of starting from an empty state.",
+        value_name = "URL",
+        env = "ETH_RPC_URL"
+    )]
+    pub fork_url: Option<String>,
+
+    #[clap(
+        long,
+        help = "Fetch state from a specific block number over a remote endpoint.",
+        long_help = "Fetch state from a specific block number over a remote endpoint. The state fetched from a pinned block is cached on disk.",
+        value_name = "BLOCK",
+        requires = "fork-url"
+    )]
+    pub fork_block_number: Option<u64>,
+
+    #[clap(long, help = "Do not cache the state fetched from the remote endpoint on disk.")]
+    pub no_storage_caching: bool,
+}
+
+impl Default for NodeConfig {
+    /// The same defaults as on the command line, without reading any environment variables
+    fn default() -> Self {
+        Self {
+            host: IpAddr::from([127, 0, 0, 1]),
+            port: 8545,
+            accounts: 10,
+            balance: 10000,
+            mnemonic: DEFAULT_MNEMONIC.to_string(),
+            chain_id: None,
+            gas_limit: 30_000_000,
+            gas_price: 0,
+            fork_url: None,
+            fork_block_number: None,
+            no_storage_caching: false,
+        }
+    }
+}
+
+impl NodeConfig {
+    /// The address the JSON-RPC server listens on
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    /// The balance of every development account, in wei
+    pub fn balance_wei(&self) -> U256 {
+        U256::from(self.balance) * U256::exp10(18)
+    }
+}
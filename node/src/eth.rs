@@ -0,0 +1,192 @@
+use crate::{
+    config::NodeConfig,
+    state::NodeState,
+    types::{LogFilter, RpcError},
+};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Block, Transaction, U64},
+};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The Ethereum JSON-RPC API of a local node.
+///
+/// The API is cheap to clone; all clones share the same node.
+#[derive(Clone)]
+pub struct EthApi {
+    state: Arc<Mutex<NodeState>>,
+}
+
+impl EthApi {
+    /// Sets up a node as configured.
+    ///
+    /// See [NodeState::new] for why this must not be called from within an async runtime.
+    pub fn new(config: &NodeConfig) -> eyre::Result<Self> {
+        Ok(Self { state: Arc::new(Mutex::new(NodeState::new(config)?)) })
+    }
+
+    /// The development accounts of the node
+    pub fn accounts(&self) -> Vec<LocalWallet> {
+        self.state.lock().accounts().to_vec()
+    }
+
+    /// Executes a JSON-RPC method with the given parameters and returns its result
+    pub fn execute(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let params = Params::new(params)?;
+        let mut state = self.state.lock();
+
+        match method {
+            "web3_clientVersion" => to_json(format!("foundry-node/v{}", env!("CARGO_PKG_VERSION"))),
+            "net_version" => to_json(state.chain_id().to_string()),
+            "eth_chainId" => to_json(U64::from(state.chain_id())),
+            "eth_blockNumber" => to_json(U64::from(state.chain().latest_number())),
+            "eth_accounts" => to_json(
+                state.accounts().iter().map(|account| account.address()).collect::<Vec<_>>(),
+            ),
+            "eth_gasPrice" => to_json(state.gas_price()),
+            "eth_getBalance" => to_json(state.balance(params.get(0)?, params.opt(1)?)?),
+            "eth_getTransactionCount" => to_json(state.nonce(params.get(0)?, params.opt(1)?)?),
+            "eth_getCode" => to_json(state.code(params.get(0)?, params.opt(1)?)?),
+            "eth_getStorageAt" => {
+                to_json(state.storage(params.get(0)?, params.get(1)?, params.opt(2)?)?)
+            }
+            "eth_call" => to_json(state.call(&params.get(0)?, params.opt(1)?)?),
+            "eth_estimateGas" => to_json(state.estimate_gas(&params.get(0)?)?),
+            "eth_sendTransaction" => to_json(state.send_transaction(params.get(0)?)?),
+            "eth_sendRawTransaction" => to_json(state.send_raw_transaction(params.get(0)?)?),
+            "eth_getTransactionByHash" => {
+                to_json(state.chain().transaction(params.get(0)?).cloned())
+            }
+            "eth_getTransactionReceipt" => to_json(state.chain().receipt(params.get(0)?).cloned()),
+            "eth_getBlockByNumber" => {
+                let number = state.chain().resolve(params.get(0)?);
+                block_json(state.chain().block_by_number(number), params.opt(1)?)
+            }
+            "eth_getBlockByHash" => {
+                block_json(state.chain().block_by_hash(params.get(0)?), params.opt(1)?)
+            }
+            "eth_getLogs" => to_json(state.chain().logs(&params.get::<LogFilter>(0)?)),
+            "evm_mine" => {
+                state.mine(None)?;
+                to_json("0x0")
+            }
+            "evm_snapshot" => to_json(state.snapshot()),
+            "evm_revert" => to_json(state.revert(params.get(0)?)),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+}
+
+/// The positional parameters of a JSON-RPC request
+struct Params(Vec<Value>);
+
+impl Params {
+    fn new(params: Value) -> Result<Self, RpcError> {
+        match params {
+            Value::Null => Ok(Self(Vec::new())),
+            Value::Array(params) => Ok(Self(params)),
+            _ => Err(RpcError::invalid_params("expected an array of parameters")),
+        }
+    }
+
+    /// Returns the parameter at `idx`, which is required
+    fn get<T: DeserializeOwned>(&self, idx: usize) -> Result<T, RpcError> {
+        self.opt(idx)?.ok_or_else(|| RpcError::invalid_params(format!("missing parameter {}", idx)))
+    }
+
+    /// Returns the parameter at `idx`, or `None` if it is missing or null
+    fn opt<T: DeserializeOwned>(&self, idx: usize) -> Result<Option<T>, RpcError> {
+        match self.0.get(idx) {
+            None | Some(Value::Null) => Ok(None),
+            Some(param) => serde_json::from_value(param.clone())
+                .map(Some)
+                .map_err(|err| RpcError::invalid_params(format!("parameter {}: {}", idx, err))),
+        }
+    }
+}
+
+/// Serializes the result of a method
+fn to_json(value: impl Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(RpcError::internal)
+}
+
+/// Serializes a block, with either its full transactions or only their hashes
+fn block_json(block: Option<&Block<Transaction>>, full: Option<bool>) -> Result<Value, RpcError> {
+    let block = match block {
+        Some(block) => block,
+        None => return Ok(Value::Null),
+    };
+
+    let mut json = to_json(block)?;
+    if !full.unwrap_or_default() {
+        json["transactions"] =
+            to_json(block.transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>())?;
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn api() -> EthApi {
+        EthApi::new(&NodeConfig { accounts: 2, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn can_execute_methods() {
+        let api = api();
+        let accounts = api.accounts();
+
+        assert_eq!(api.execute("eth_chainId", Value::Null).unwrap(), json!("0x7a69"));
+        assert_eq!(api.execute("net_version", json!([])).unwrap(), json!("31337"));
+        assert_eq!(api.execute("eth_blockNumber", json!([])).unwrap(), json!("0x0"));
+
+        let hash = api
+            .execute(
+                "eth_sendTransaction",
+                json!([{
+                    "from": accounts[0].address(),
+                    "to": accounts[1].address(),
+                    "value": "0x3e8",
+                }]),
+            )
+            .unwrap();
+        assert_eq!(api.execute("eth_blockNumber", json!([])).unwrap(), json!("0x1"));
+
+        let receipt = api.execute("eth_getTransactionReceipt", json!([hash])).unwrap();
+        assert_eq!(receipt["status"], json!("0x1"));
+
+        let block = api.execute("eth_getBlockByNumber", json!(["latest", false])).unwrap();
+        assert_eq!(block["transactions"], json!([hash]));
+        let block = api.execute("eth_getBlockByNumber", json!(["latest", true])).unwrap();
+        assert_eq!(block["transactions"][0]["hash"], hash);
+
+        let nonce = api
+            .execute("eth_getTransactionCount", json!([accounts[0].address(), "latest"]))
+            .unwrap();
+        assert_eq!(nonce, json!("0x1"));
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let api = api();
+        let address = api.accounts()[0].address();
+
+        assert_eq!(
+            api.execute("eth_foo", json!([])).unwrap_err(),
+            RpcError::method_not_found("eth_foo")
+        );
+        assert_eq!(api.execute("eth_getBalance", json!([])).unwrap_err().code, -32602);
+        assert_eq!(api.execute("eth_getBalance", json!({ "a": 1 })).unwrap_err().code, -32602);
+
+        // Only the state of the latest block is kept
+        api.execute("evm_mine", json!([])).unwrap();
+        assert!(api.execute("eth_getBalance", json!([address, "0x1"])).is_ok());
+        assert!(api.execute("eth_getBalance", json!([address, "0x0"])).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+//! A local Ethereum development node.
+//!
+//! The node serves the Ethereum JSON-RPC API on top of the [foundry_evm] executor. It either
+//! starts from an empty state or forks a live chain, and mines every transaction it receives into
+//! a new block right away.
+
+/// Node configuration
+pub mod config;
+pub use config::NodeConfig;
+
+/// The Ethereum JSON-RPC API
+pub mod eth;
+pub use eth::EthApi;
+
+/// The JSON-RPC HTTP server
+pub mod server;
+pub use server::serve;
+
+/// Types used in JSON-RPC requests and responses
+pub mod types;
+
+/// The blocks mined by the node
+pub mod chain;
+
+/// The state of the node
+pub mod state;
@@ -0,0 +1,33 @@
+use clap::Parser;
+use ethers::signers::Signer;
+use foundry_node::{serve, EthApi, NodeConfig};
+use tracing_subscriber::EnvFilter;
+
+fn main() -> eyre::Result<()> {
+    tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env()).init();
+
+    let config = NodeConfig::parse();
+
+    // Forking blocks on requests to the remote endpoint, so the node is set up before the runtime
+    // is started
+    let api = EthApi::new(&config)?;
+
+    println!("Available accounts");
+    println!("==================");
+    for (idx, account) in api.accounts().iter().enumerate() {
+        println!(
+            "({}) {:?} ({} ETH) 0x{}",
+            idx,
+            account.address(),
+            config.balance,
+            hex::encode(account.signer().to_bytes())
+        );
+    }
+    println!();
+    println!("Listening on {}", config.socket_addr());
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(serve(config.socket_addr(), api))
+}
@@ -0,0 +1,117 @@
+use crate::{eth::EthApi, types::RpcError};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{convert::Infallible, net::SocketAddr};
+
+/// Serves the JSON-RPC API over HTTP on the given address until the server fails
+pub async fn serve(addr: SocketAddr, api: EthApi) -> eyre::Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let api = api.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(api.clone(), req))) }
+    });
+
+    Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}
+
+/// Handles a single HTTP request
+async fn handle(api: EthApi, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty())
+            .expect("the response is valid"))
+    }
+
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(err) => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(err.to_string()))
+                .expect("the response is valid"))
+        }
+    };
+
+    // Executing a request can block on requests to the forked chain
+    let response = tokio::task::spawn_blocking(move || handle_body(&api, &body))
+        .await
+        .unwrap_or_else(|err| error_response(Value::Null, RpcError::internal(err)));
+
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(response.to_string()))
+        .expect("the response is valid"))
+}
+
+/// A JSON-RPC request
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Handles the body of an HTTP request, which is either a single JSON-RPC request or a batch of
+/// requests, and returns the body of the response
+pub fn handle_body(api: &EthApi, body: &[u8]) -> Value {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(requests)) => {
+            Value::Array(requests.into_iter().map(|request| handle_request(api, request)).collect())
+        }
+        Ok(request) => handle_request(api, request),
+        Err(_) => error_response(Value::Null, RpcError::parse_error()),
+    }
+}
+
+/// Handles a single JSON-RPC request
+fn handle_request(api: &EthApi, request: Value) -> Value {
+    let RpcRequest { id, method, params } = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(_) => return error_response(Value::Null, RpcError::invalid_request()),
+    };
+
+    tracing::trace!(target: "node", ?method, ?params, "request");
+    match api.execute(&method, params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => {
+            tracing::debug!(target: "node", ?method, %err, "request failed");
+            error_response(id, err)
+        }
+    }
+}
+
+/// Builds the response to a request that failed
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": err })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeConfig;
+
+    #[test]
+    fn can_handle_batches() {
+        let api = EthApi::new(&NodeConfig { accounts: 1, ..Default::default() }).unwrap();
+        let body = json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": [] },
+            { "jsonrpc": "2.0", "id": 2, "method": "eth_foo" },
+        ]);
+
+        let response = handle_body(&api, body.to_string().as_bytes());
+        assert_eq!(response[0], json!({ "jsonrpc": "2.0", "id": 1, "result": "0x7a69" }));
+        assert_eq!(response[1]["id"], json!(2));
+        assert_eq!(response[1]["error"]["code"], json!(-32601));
+
+        let response = handle_body(&api, b"{");
+        assert_eq!(response["error"]["code"], json!(-32700));
+    }
+}
@@ -0,0 +1,611 @@
+use crate::{
+    chain::Blockchain,
+    config::{NodeConfig, DEFAULT_CHAIN_ID},
+    types::{BlockTag, RpcError},
+};
+use ethers::{
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, Log, NameOrAddress, Transaction,
+        TransactionReceipt, TransactionRequest, H256, U256,
+    },
+    utils::{get_contract_address, keccak256, rlp},
+};
+use foundry_config::Config;
+use foundry_evm::{
+    executor::{
+        builder::Backend,
+        opts::{Env as EvmEnv, EvmOpts},
+        Executor, ExecutorBuilder, Fork, RawCallResult,
+    },
+    trace::{CallTraceArena, LogCallOrder, RawOrDecodedLog},
+    CallKind,
+};
+use revm::{CreateScheme, TransactTo, TxEnv};
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The state of a local node: the state of all accounts, the mined blocks and the development
+/// accounts.
+///
+/// Every transaction is mined into its own block as soon as it is received.
+pub struct NodeState {
+    /// The executor that holds the state of all accounts
+    executor: Executor<Backend>,
+    /// The mined blocks
+    chain: Blockchain,
+    /// The development accounts, which the node signs transactions for
+    accounts: Vec<LocalWallet>,
+    /// The gas price reported to clients
+    gas_price: U256,
+    /// The snapshots taken with `evm_snapshot`, keyed by their ID
+    snapshots: BTreeMap<U256, (Executor<Backend>, Blockchain)>,
+    /// The ID of the next snapshot
+    next_snapshot_id: U256,
+}
+
+impl NodeState {
+    /// Sets up the node as configured.
+    ///
+    /// If the node forks a live chain, this blocks on requests to the remote endpoint, so it must
+    /// not be called from within an async runtime.
+    pub fn new(config: &NodeConfig) -> eyre::Result<Self> {
+        let mut evm_opts = EvmOpts {
+            env: EvmEnv {
+                gas_limit: config.gas_limit,
+                block_gas_limit: Some(config.gas_limit),
+                chain_id: config.chain_id,
+                gas_price: config.gas_price,
+                block_timestamp: now(),
+                ..Default::default()
+            },
+            fork_url: config.fork_url.clone(),
+            fork_block_number: config.fork_block_number,
+            no_storage_caching: config.no_storage_caching,
+            ..Default::default()
+        };
+        let chain_id = match (config.chain_id, &config.fork_url) {
+            (Some(chain_id), _) => chain_id,
+            (None, Some(_)) => evm_opts.get_chain_id(),
+            (None, None) => DEFAULT_CHAIN_ID,
+        };
+        evm_opts.env.chain_id = Some(chain_id);
+
+        let accounts = (0..config.accounts)
+            .map(|idx| {
+                MnemonicBuilder::<English>::default()
+                    .phrase(config.mnemonic.as_str())
+                    .index(idx as u32)
+                    .and_then(|builder| builder.build())
+                    .map(|wallet| wallet.with_chain_id(chain_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The node does not charge a base fee, so that calls can be made without paying for gas
+        let mut env = evm_opts.evm_env();
+        env.cfg.chain_id = chain_id.into();
+        env.block.basefee = U256::zero();
+        env.block.gas_limit = config.gas_limit.into();
+
+        // The state of a pinned block never changes, so it is cached on disk like the state of
+        // forked tests
+        let fork = config.fork_url.clone().map(|url| Fork {
+            cache_path: config
+                .fork_block_number
+                .filter(|_| !config.no_storage_caching)
+                .and_then(|block| Config::foundry_block_cache_file(chain_id, block)),
+            url,
+            pin_block: config.fork_block_number,
+            chain_id,
+        });
+        let backend = Backend::new(fork, &env);
+
+        let mut executor = ExecutorBuilder::new()
+            .with_config(env.clone())
+            .with_tracing()
+            .with_gas_limit(config.gas_limit.into())
+            .build(backend);
+        for account in &accounts {
+            executor.set_balance(account.address(), config.balance_wei());
+        }
+
+        let chain = Blockchain::new(
+            env.block.number.as_u64(),
+            env.block.timestamp.as_u64(),
+            env.block.gas_limit,
+        );
+
+        Ok(Self {
+            executor,
+            chain,
+            accounts,
+            gas_price: config.gas_price.into(),
+            snapshots: BTreeMap::new(),
+            next_snapshot_id: U256::zero(),
+        })
+    }
+
+    /// The chain ID of the node
+    pub fn chain_id(&self) -> u64 {
+        self.executor.env().cfg.chain_id.as_u64()
+    }
+
+    /// The development accounts
+    pub fn accounts(&self) -> &[LocalWallet] {
+        &self.accounts
+    }
+
+    /// The gas price reported to clients
+    pub fn gas_price(&self) -> U256 {
+        self.gas_price
+    }
+
+    /// The block gas limit
+    pub fn gas_limit(&self) -> U256 {
+        self.executor.env().block.gas_limit
+    }
+
+    /// The mined blocks
+    pub fn chain(&self) -> &Blockchain {
+        &self.chain
+    }
+
+    /// Returns the balance of an account
+    pub fn balance(&self, address: Address, block: Option<BlockTag>) -> Result<U256, RpcError> {
+        self.ensure_latest(block)?;
+        Ok(self.executor.get_balance(address))
+    }
+
+    /// Returns the nonce of an account
+    pub fn nonce(&self, address: Address, block: Option<BlockTag>) -> Result<U256, RpcError> {
+        self.ensure_latest(block)?;
+        Ok(self.executor.get_nonce(address).into())
+    }
+
+    /// Returns the code of an account
+    pub fn code(&self, address: Address, block: Option<BlockTag>) -> Result<Bytes, RpcError> {
+        self.ensure_latest(block)?;
+        Ok(self.executor.get_code(address).to_vec().into())
+    }
+
+    /// Returns the value of a storage slot of an account
+    pub fn storage(
+        &self,
+        address: Address,
+        slot: U256,
+        block: Option<BlockTag>,
+    ) -> Result<H256, RpcError> {
+        self.ensure_latest(block)?;
+        let mut value = [0u8; 32];
+        self.executor.get_storage(address, slot).to_big_endian(&mut value);
+        Ok(H256(value))
+    }
+
+    /// Executes a call without mining it and returns its output
+    pub fn call(
+        &self,
+        request: &TransactionRequest,
+        block: Option<BlockTag>,
+    ) -> Result<Bytes, RpcError> {
+        self.ensure_latest(block)?;
+        let result = self.executor.transact(self.call_tx(request)?).map_err(RpcError::internal)?;
+        if result.reverted {
+            return Err(revert_error(&result))
+        }
+        Ok(result.result.to_vec().into())
+    }
+
+    /// Estimates the gas a transaction needs.
+    ///
+    /// The gas used by a transaction can be lower than the gas it needs, e.g. because of gas
+    /// refunds or because 1/64th of the remaining gas is withheld from every call, so the lowest
+    /// sufficient gas limit is searched for.
+    pub fn estimate_gas(&self, request: &TransactionRequest) -> Result<U256, RpcError> {
+        let mut tx = self.call_tx(request)?;
+        let result = self.executor.transact(tx.clone()).map_err(RpcError::internal)?;
+        if result.reverted {
+            return Err(revert_error(&result))
+        }
+
+        let (mut lowest, mut highest) = (result.gas.saturating_sub(1), tx.gas_limit);
+        while lowest + 1 < highest {
+            let mid = lowest + (highest - lowest) / 2;
+            tx.gas_limit = mid;
+            let result = self.executor.transact(tx.clone()).map_err(RpcError::internal)?;
+            if result.reverted {
+                lowest = mid;
+            } else {
+                highest = mid;
+            }
+        }
+
+        Ok(highest.into())
+    }
+
+    /// Signs a transaction with a development account, mines it and returns its hash.
+    ///
+    /// Missing fields of the transaction are filled in.
+    pub fn send_transaction(&mut self, mut request: TransactionRequest) -> Result<H256, RpcError> {
+        let from = request.from.ok_or_else(|| RpcError::invalid_params("missing `from`"))?;
+        let wallet = self
+            .accounts
+            .iter()
+            .find(|account| account.address() == from)
+            .ok_or_else(|| {
+                RpcError::invalid_params(format!("{:?} is not a development account", from))
+            })?
+            .clone();
+
+        if request.nonce.is_none() {
+            request.nonce = Some(self.executor.get_nonce(from).into());
+        }
+        if request.gas_price.is_none() {
+            request.gas_price = Some(self.gas_price);
+        }
+        if request.gas.is_none() {
+            request.gas = Some(self.estimate_gas(&request)?);
+        }
+
+        let tx = TypedTransaction::Legacy(request.chain_id(self.chain_id()));
+        let signature = wallet.sign_transaction_sync(&tx);
+        self.send_raw_transaction(tx.rlp_signed(&signature))
+    }
+
+    /// Mines a signed transaction and returns its hash
+    pub fn send_raw_transaction(&mut self, raw: Bytes) -> Result<H256, RpcError> {
+        let mut tx: Transaction = rlp::decode(raw.as_ref()).map_err(|err| {
+            RpcError::invalid_params(format!("failed to decode transaction: {}", err))
+        })?;
+        tx.hash = H256::from(keccak256(raw.as_ref()));
+        tx.from = tx.recover_from().map_err(|err| {
+            RpcError::invalid_params(format!("failed to recover the sender: {}", err))
+        })?;
+
+        let hash = tx.hash;
+        self.mine(Some(tx))?;
+        Ok(hash)
+    }
+
+    /// Mines a block, with the given transaction if any, and returns the hash of the block
+    pub fn mine(&mut self, transaction: Option<Transaction>) -> Result<H256, RpcError> {
+        // Every block is at least a second younger than its parent
+        let timestamp = now().max(self.chain.latest().timestamp.as_u64() + 1);
+        let block = &mut self.executor.env_mut().block;
+        let parent = block.clone();
+        block.number = (self.chain.latest_number() + 1).into();
+        block.timestamp = timestamp.into();
+
+        let executed = match transaction {
+            Some(tx) => match self.execute(&tx) {
+                Ok(receipt) => vec![(tx, receipt)],
+                Err(err) => {
+                    // The block is not mined, so calls keep executing in the latest block
+                    self.executor.env_mut().block = parent;
+                    return Err(err)
+                }
+            },
+            None => Vec::new(),
+        };
+
+        Ok(self.chain.mine(timestamp, self.gas_limit(), executed))
+    }
+
+    /// Takes a snapshot of the state and the chain and returns its ID
+    pub fn snapshot(&mut self) -> U256 {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += U256::one();
+        self.snapshots.insert(id, (self.executor.clone(), self.chain.clone()));
+        id
+    }
+
+    /// Reverts the state and the chain to a snapshot.
+    ///
+    /// The snapshot and all snapshots taken after it are discarded. Returns `false` if there is
+    /// no snapshot with the given ID.
+    pub fn revert(&mut self, id: U256) -> bool {
+        if !self.snapshots.contains_key(&id) {
+            return false
+        }
+
+        let mut discarded = self.snapshots.split_off(&id);
+        let (executor, chain) = discarded.remove(&id).expect("the snapshot exists");
+        self.executor = executor;
+        self.chain = chain;
+        true
+    }
+
+    /// Ensures that state is requested at the latest block, the only block the node keeps the
+    /// state of
+    fn ensure_latest(&self, block: Option<BlockTag>) -> Result<(), RpcError> {
+        let latest = self.chain.latest_number();
+        match block.map(|block| self.chain.resolve(block)) {
+            Some(number) if number != latest => Err(RpcError::invalid_params(format!(
+                "the state of block {} is not available, only the state of the latest block ({}) is",
+                number, latest
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the environment of a call that is not mined
+    fn call_tx(&self, request: &TransactionRequest) -> Result<TxEnv, RpcError> {
+        let to = match &request.to {
+            Some(NameOrAddress::Address(to)) => TransactTo::Call(*to),
+            Some(NameOrAddress::Name(_)) => {
+                return Err(RpcError::invalid_params("ENS names are not supported"))
+            }
+            None => TransactTo::Create(CreateScheme::Create),
+        };
+
+        Ok(TxEnv {
+            caller: request.from.unwrap_or_default(),
+            transact_to: to,
+            data: request.data.clone().map(|data| data.0).unwrap_or_default(),
+            value: request.value.unwrap_or_default(),
+            gas_limit: checked_gas_limit(request.gas.unwrap_or_else(|| self.gas_limit()))?,
+            gas_price: request.gas_price.unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
+    /// Executes a transaction on top of the current state and returns its receipt.
+    ///
+    /// Transactions that could not be included in a block are rejected without being executed.
+    fn execute(&mut self, tx: &Transaction) -> Result<TransactionReceipt, RpcError> {
+        if let Some(chain_id) = tx.chain_id {
+            if chain_id != self.chain_id().into() {
+                return Err(RpcError::transaction_rejected(format!(
+                    "invalid chain ID {}, expected {}",
+                    chain_id,
+                    self.chain_id()
+                )))
+            }
+        }
+
+        let nonce = self.executor.get_nonce(tx.from);
+        if tx.nonce != nonce.into() {
+            return Err(RpcError::transaction_rejected(format!(
+                "invalid nonce {} for {:?}, expected {}",
+                tx.nonce, tx.from, nonce
+            )))
+        }
+
+        if tx.gas > self.gas_limit() {
+            return Err(RpcError::transaction_rejected(format!(
+                "gas limit {} exceeds the block gas limit {}",
+                tx.gas,
+                self.gas_limit()
+            )))
+        }
+
+        let gas_price = tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default();
+        let cost = tx.gas.saturating_mul(gas_price).saturating_add(tx.value);
+        let balance = self.executor.get_balance(tx.from);
+        if balance < cost {
+            return Err(RpcError::transaction_rejected(format!(
+                "insufficient funds for gas * price + value: {:?} has {}, but {} is needed",
+                tx.from, balance, cost
+            )))
+        }
+
+        let gas_limit = checked_gas_limit(tx.gas)?;
+        let result = self
+            .executor
+            .transact_committing(TxEnv {
+                caller: tx.from,
+                transact_to: tx
+                    .to
+                    .map(TransactTo::Call)
+                    .unwrap_or(TransactTo::Create(CreateScheme::Create)),
+                data: tx.input.0.clone(),
+                value: tx.value,
+                gas_limit,
+                gas_price,
+                gas_priority_fee: tx.max_priority_fee_per_gas,
+                ..Default::default()
+            })
+            .map_err(RpcError::internal)?;
+
+        let gas_used = U256::from(result.gas);
+        Ok(TransactionReceipt {
+            transaction_hash: tx.hash,
+            from: tx.from,
+            to: tx.to,
+            contract_address: (tx.to.is_none() && !result.reverted)
+                .then(|| get_contract_address(tx.from, tx.nonce)),
+            // Every block has a single transaction
+            cumulative_gas_used: gas_used,
+            gas_used: Some(gas_used),
+            status: Some((!result.reverted as u64).into()),
+            logs: result.traces.as_ref().map(logs).unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Converts a gas limit to the `u64` the EVM expects, rejecting gas limits that do not fit
+fn checked_gas_limit(gas: U256) -> Result<u64, RpcError> {
+    if gas > U256::from(u64::MAX) {
+        return Err(RpcError::invalid_params(format!("gas limit {} is too large", gas)))
+    }
+    Ok(gas.as_u64())
+}
+
+/// Returns the logs of a transaction in the order they were emitted.
+///
+/// The executor does not record which contract emitted a log, so the logs are collected from the
+/// call traces instead. Logs of calls that reverted are dropped.
+fn logs(traces: &CallTraceArena) -> Vec<Log> {
+    fn collect(traces: &CallTraceArena, idx: usize, context: Address, logs: &mut Vec<Log>) {
+        let node = &traces.arena[idx];
+        if !node.trace.success {
+            return
+        }
+
+        // Code that is executed with `DELEGATECALL` or `CALLCODE` emits logs on behalf of the
+        // caller
+        let address = match node.trace.kind {
+            CallKind::DelegateCall | CallKind::CallCode => context,
+            _ => node.trace.address,
+        };
+        for item in &node.ordering {
+            match item {
+                LogCallOrder::Log(log) => {
+                    if let RawOrDecodedLog::Raw(log) = &node.logs[*log] {
+                        logs.push(Log {
+                            address,
+                            topics: log.topics.clone(),
+                            data: log.data.clone().into(),
+                            ..Default::default()
+                        });
+                    }
+                }
+                LogCallOrder::Call(child) => collect(traces, node.children[*child], address, logs),
+            }
+        }
+    }
+
+    let mut logs = Vec::new();
+    collect(traces, 0, Address::zero(), &mut logs);
+    logs
+}
+
+/// Builds the error of a reverted call, decoding the revert reason if possible
+fn revert_error(result: &RawCallResult) -> RpcError {
+    let reason = foundry_utils::decode_revert(result.result.as_ref(), None).ok();
+    RpcError::execution_reverted(reason, result.result.to_vec().into())
+}
+
+/// The current UNIX timestamp
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("time went backwards").as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LogFilter;
+
+    fn state() -> NodeState {
+        NodeState::new(&NodeConfig { accounts: 2, ..Default::default() }).unwrap()
+    }
+
+    fn transfer(state: &NodeState, value: u64) -> TransactionRequest {
+        TransactionRequest::new()
+            .from(state.accounts()[0].address())
+            .to(state.accounts()[1].address())
+            .value(value)
+    }
+
+    #[test]
+    fn can_send_transactions() {
+        let mut state = state();
+        let to = state.accounts()[1].address();
+        let balance = state.balance(to, None).unwrap();
+
+        let hash = state.send_transaction(transfer(&state, 1000)).unwrap();
+        assert_eq!(state.balance(to, None).unwrap(), balance + 1000);
+        assert_eq!(state.nonce(state.accounts()[0].address(), None).unwrap(), 1.into());
+        assert_eq!(state.chain().latest_number(), 1);
+
+        let receipt = state.chain().receipt(hash).unwrap();
+        assert_eq!(receipt.status, Some(1.into()));
+        assert_eq!(receipt.gas_used, Some(21000.into()));
+        assert_eq!(receipt.block_number, Some(1.into()));
+    }
+
+    #[test]
+    fn rejects_too_large_gas_limits() {
+        let mut state = state();
+        let request = transfer(&state, 1000).gas(U256::MAX);
+        assert_eq!(
+            state.call(&request, None),
+            Err(RpcError::invalid_params(format!("gas limit {} is too large", U256::MAX)))
+        );
+        assert!(state.estimate_gas(&request).is_err());
+        assert!(state.send_transaction(request).is_err());
+        assert_eq!(state.chain().latest_number(), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_nonces() {
+        let mut state = state();
+        let request = transfer(&state, 1000).nonce(1);
+        assert!(state.send_transaction(request).is_err());
+        assert_eq!(state.chain().latest_number(), 0);
+    }
+
+    #[test]
+    fn keeps_the_block_of_rejected_transactions() {
+        let mut state = state();
+        let block = state.executor.env().block.clone();
+
+        // The nonce is too high, so the transaction is rejected
+        let request = transfer(&state, 1000).nonce(1).gas(21000).gas_price(0);
+        let tx = TypedTransaction::Legacy(request.chain_id(state.chain_id()));
+        let signature = state.accounts()[0].sign_transaction_sync(&tx);
+        assert!(state.send_raw_transaction(tx.rlp_signed(&signature)).is_err());
+
+        assert_eq!(state.chain().latest_number(), 0);
+        assert_eq!(state.executor.env().block.number, block.number);
+        assert_eq!(state.executor.env().block.timestamp, block.timestamp);
+    }
+
+    #[test]
+    fn can_revert_to_snapshots() {
+        let mut state = state();
+        let to = state.accounts()[1].address();
+        let balance = state.balance(to, None).unwrap();
+
+        let snapshot = state.snapshot();
+        let later_snapshot = state.snapshot();
+        state.send_transaction(transfer(&state, 1000)).unwrap();
+        state.mine(None).unwrap();
+        assert_eq!(state.chain().latest_number(), 2);
+
+        assert!(state.revert(snapshot));
+        assert_eq!(state.balance(to, None).unwrap(), balance);
+        assert_eq!(state.chain().latest_number(), 0);
+
+        // Snapshots are discarded once reverted to, as are the snapshots taken after them
+        assert!(!state.revert(snapshot));
+        assert!(!state.revert(later_snapshot));
+    }
+
+    #[test]
+    fn can_get_logs() {
+        let mut state = state();
+
+        // PUSH1 0x01 PUSH1 0x00 PUSH1 0x00 LOG1 STOP, i.e. a constructor that emits an empty log
+        // with the topic 0x01
+        let init_code = hex::decode("600160006000a100").unwrap();
+        let request = TransactionRequest::new().from(state.accounts()[0].address()).data(init_code);
+        let hash = state.send_transaction(request).unwrap();
+        let receipt = state.chain().receipt(hash).unwrap().clone();
+        let address = receipt.contract_address.unwrap();
+        assert_eq!(receipt.logs.len(), 1);
+        assert_eq!(receipt.logs[0].address, address);
+
+        let filter: LogFilter = serde_json::from_value(serde_json::json!({
+            "fromBlock": "earliest",
+            "topics": [H256::from_low_u64_be(1)],
+        }))
+        .unwrap();
+        assert_eq!(state.chain().logs(&filter), receipt.logs);
+
+        let filter: LogFilter = serde_json::from_value(serde_json::json!({
+            "fromBlock": "earliest",
+            "topics": [H256::from_low_u64_be(2)],
+        }))
+        .unwrap();
+        assert!(state.chain().logs(&filter).is_empty());
+    }
+
+    #[test]
+    fn can_estimate_gas() {
+        let state = state();
+        assert_eq!(state.estimate_gas(&transfer(&state, 1000)).unwrap(), 21000.into());
+    }
+}
@@ -0,0 +1,190 @@
+use ethers::types::{Address, Bytes, Log, H256};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// A block number or tag, as used in the parameters of JSON-RPC requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockTag {
+    /// The genesis block
+    Earliest,
+    /// The latest mined block
+    Latest,
+    /// The block that is currently being built.
+    ///
+    /// Since every transaction is mined immediately, the state of the pending block is the state
+    /// of the latest block.
+    Pending,
+    /// A block number
+    Number(u64),
+}
+
+impl<'de> Deserialize<'de> for BlockTag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(match s.as_str() {
+            "earliest" => BlockTag::Earliest,
+            "latest" => BlockTag::Latest,
+            "pending" => BlockTag::Pending,
+            number => {
+                let number = number.strip_prefix("0x").ok_or_else(|| {
+                    serde::de::Error::custom(format!("invalid block number `{}`", number))
+                })?;
+                BlockTag::Number(u64::from_str_radix(number, 16).map_err(serde::de::Error::custom)?)
+            }
+        })
+    }
+}
+
+/// A single value or a list of values, e.g. the addresses a log filter matches
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> OneOrMany<T> {
+    /// Whether `value` is the value or one of the values
+    pub fn matches(&self, value: &T) -> bool {
+        match self {
+            OneOrMany::One(one) => one == value,
+            OneOrMany::Many(many) => many.contains(value),
+        }
+    }
+}
+
+/// The filter of an `eth_getLogs` request
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// The first block to search, defaults to the latest block
+    pub from_block: Option<BlockTag>,
+    /// The last block to search, defaults to the latest block
+    pub to_block: Option<BlockTag>,
+    /// The only block to search, which replaces `from_block` and `to_block`
+    pub block_hash: Option<H256>,
+    /// The addresses of the contracts whose logs should be returned, if restricted
+    pub address: Option<OneOrMany<Address>>,
+    /// The topics the logs should have, by position.
+    ///
+    /// An empty position matches any topic.
+    #[serde(default)]
+    pub topics: Vec<Option<OneOrMany<H256>>>,
+}
+
+impl LogFilter {
+    /// Whether the filter matches the address and topics of `log`
+    pub fn matches(&self, log: &Log) -> bool {
+        if let Some(address) = &self.address {
+            if !address.matches(&log.address) {
+                return false
+            }
+        }
+
+        self.topics.iter().enumerate().all(|(idx, topic)| match topic {
+            Some(topic) => log.topics.get(idx).map_or(false, |log_topic| topic.matches(log_topic)),
+            None => true,
+        })
+    }
+}
+
+/// A JSON-RPC error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
+
+impl RpcError {
+    /// The request is not a valid JSON-RPC request
+    pub fn invalid_request() -> Self {
+        Self { code: -32600, message: "Invalid request".to_string(), data: None }
+    }
+
+    /// The requested method is not supported by the node
+    pub fn method_not_found(method: &str) -> Self {
+        Self { code: -32601, message: format!("Method not found: {}", method), data: None }
+    }
+
+    /// The parameters of the request are invalid
+    pub fn invalid_params(message: impl fmt::Display) -> Self {
+        Self { code: -32602, message: format!("Invalid params: {}", message), data: None }
+    }
+
+    /// An error occurred in the node
+    pub fn internal(message: impl fmt::Display) -> Self {
+        Self { code: -32603, message: message.to_string(), data: None }
+    }
+
+    /// The request body is not valid JSON
+    pub fn parse_error() -> Self {
+        Self { code: -32700, message: "Parse error".to_string(), data: None }
+    }
+
+    /// A transaction was rejected, e.g. because of a wrong nonce or insufficient funds
+    pub fn transaction_rejected(message: impl fmt::Display) -> Self {
+        Self { code: -32003, message: message.to_string(), data: None }
+    }
+
+    /// A call reverted, with the reason it reverted for and the raw revert data
+    pub fn execution_reverted(reason: Option<String>, data: Bytes) -> Self {
+        let message = match reason {
+            Some(reason) => format!("execution reverted: {}", reason),
+            None => "execution reverted".to_string(),
+        };
+        Self { code: 3, message, data: Some(data) }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_block_tags() {
+        let tag = |s: &str| serde_json::from_value::<BlockTag>(serde_json::json!(s));
+        assert_eq!(tag("latest").unwrap(), BlockTag::Latest);
+        assert_eq!(tag("pending").unwrap(), BlockTag::Pending);
+        assert_eq!(tag("earliest").unwrap(), BlockTag::Earliest);
+        assert_eq!(tag("0x1b4").unwrap(), BlockTag::Number(436));
+        assert!(tag("436").is_err());
+    }
+
+    #[test]
+    fn can_match_logs() {
+        let address = Address::repeat_byte(1);
+        let topic = H256::repeat_byte(2);
+        let log = Log { address, topics: vec![topic, H256::repeat_byte(3)], ..Default::default() };
+
+        let filter: LogFilter = serde_json::from_value(serde_json::json!({
+            "address": [address, Address::repeat_byte(4)],
+            "topics": [topic],
+        }))
+        .unwrap();
+        assert!(filter.matches(&log));
+
+        let filter: LogFilter = serde_json::from_value(serde_json::json!({
+            "topics": [null, [topic]],
+        }))
+        .unwrap();
+        assert!(!filter.matches(&log));
+
+        let filter: LogFilter =
+            serde_json::from_value(serde_json::json!({ "address": Address::repeat_byte(4) }))
+                .unwrap();
+        assert!(!filter.matches(&log));
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract BreakpointTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testBreakpoint() public {
+        uint256 x = 1;
+        cheats.breakpoint("before");
+        x += 1;
+        cheats.breakpoint("after");
+        assertEq(x, 2);
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Counter {
+    uint256 public count;
+
+    function increment() public payable returns (address) {
+        count++;
+        return msg.sender;
+    }
+
+    function fail() public pure {
+        revert("failed");
+    }
+}
+
+contract Deployer {
+    function deploy() public returns (Counter) {
+        return new Counter();
+    }
+}
+
+contract BroadcastTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    address constant ACCOUNT = address(0x1337);
+
+    function testBroadcastSetsSender() public {
+        Counter counter = new Counter();
+
+        cheats.broadcast(ACCOUNT);
+        assertEq(counter.increment(), ACCOUNT, "msg.sender was not set to the broadcaster");
+
+        // The broadcast only applies to the next call
+        assertEq(counter.increment(), address(this), "broadcast was not stopped");
+    }
+
+    function testBroadcastIncrementsNonce() public {
+        Counter counter = new Counter();
+        uint64 nonce = cheats.getNonce(ACCOUNT);
+
+        cheats.startBroadcast(ACCOUNT);
+        counter.increment();
+        counter.increment();
+        cheats.stopBroadcast();
+
+        assertEq(cheats.getNonce(ACCOUNT), nonce + 2, "nonce was not incremented");
+    }
+
+    function testBroadcastDeploysFromSender() public {
+        uint64 nonce = cheats.getNonce(ACCOUNT);
+
+        cheats.startBroadcast(ACCOUNT);
+        Counter counter = new Counter();
+        counter.increment();
+        cheats.stopBroadcast();
+
+        assertEq(cheats.getNonce(ACCOUNT), nonce + 2, "nonce was not incremented");
+        assertEq(counter.count(), 1);
+    }
+
+    function testBroadcastOnlyAtCallDepth() public {
+        Counter counter = new Counter();
+        Deployer deployer = new Deployer();
+
+        cheats.broadcast(ACCOUNT);
+        Counter inner = deployer.deploy();
+
+        // The contract was created by the deployer, not by the broadcaster
+        assertEq(counter.increment(), address(this), "broadcast was not stopped");
+        assertEq(inner.increment(), address(this));
+    }
+
+    function testBroadcastDropsRevertedCalls() public {
+        Counter counter = new Counter();
+        uint64 nonce = cheats.getNonce(ACCOUNT);
+
+        cheats.startBroadcast(ACCOUNT);
+        try counter.fail() {} catch {}
+        counter.increment();
+        cheats.stopBroadcast();
+
+        // The reverted call is not broadcast, so it does not use a nonce
+        assertEq(cheats.getNonce(ACCOUNT), nonce + 1, "nonce of the reverted call was used");
+        assertEq(counter.count(), 1);
+    }
+
+    function testFailStartBroadcastTwice() public {
+        cheats.startBroadcast(ACCOUNT);
+        cheats.startBroadcast(ACCOUNT);
+    }
+}
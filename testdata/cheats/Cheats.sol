@@ -16,6 +16,12 @@ interface Cheats {
     function sign(uint256,bytes32) external returns (uint8,bytes32,bytes32);
     // Gets address for a given private key, (privateKey) => (address)
     function addr(uint256) external returns (address);
+    // Derives a private key from a mnemonic at the index of the default path m/44'/60'/0'/0/, (mnemonic, index) => (privateKey)
+    function deriveKey(string calldata, uint32) external returns (uint256);
+    // Derives a private key from a mnemonic at the index of a derivation path, (mnemonic, path, index) => (privateKey)
+    function deriveKey(string calldata, string calldata, uint32) external returns (uint256);
+    // Remembers a private key so that scripts can broadcast transactions from its address, (privateKey) => (address)
+    function rememberKey(uint256) external returns (address);
     // Performs a foreign function call via terminal, (stringInputs) => (result)
     function ffi(string[] calldata) external returns (bytes memory);
     // Sets the *next* call's msg.sender to be the input address
@@ -40,6 +46,16 @@ interface Cheats {
     function record() external;
     // Gets all accessed reads and write slot from a recording session, for a given address
     function accesses(address) external returns (bytes32[] memory reads, bytes32[] memory writes);
+    // A log emitted while recording logs, see `recordLogs`
+    struct Log {
+        bytes32[] topics;
+        bytes data;
+        address emitter;
+    }
+    // Record all logs emitted from now on
+    function recordLogs() external;
+    // Gets the logs emitted since `recordLogs` was called, or since they were last gotten
+    function getRecordedLogs() external returns (Log[] memory);
     // Prepare an expected log with (bool checkTopic1, bool checkTopic2, bool checkTopic3, bool checkData).
     // Call this function, then emit an event, then call a function. Internally after the call, we check if
     // logs were emitted in the expected order with the expected topics and data (as specified by the booleans)
@@ -49,13 +65,62 @@ interface Cheats {
     // pass a Solidity selector to the expected calldata, then the entire Solidity
     // function will be mocked.
     function mockCall(address,bytes calldata,bytes calldata) external;
+    // Mocks a call to an address with the specified msg.value, returning specified data.
+    function mockCall(address,uint256,bytes calldata,bytes calldata) external;
+    // Mocks a call to an address, reverting with the specified data.
+    function mockCallRevert(address,bytes calldata,bytes calldata) external;
+    // Mocks a call to an address with the specified msg.value, reverting with the specified data.
+    function mockCallRevert(address,uint256,bytes calldata,bytes calldata) external;
     // Clears all mocked calls
     function clearMockedCalls() external;
     // Expect a call to an address with the specified calldata.
     // Calldata can either be strict or a partial match
     function expectCall(address,bytes calldata) external;
+    // Expect a call to an address with the specified msg.value and calldata
+    function expectCall(address,uint256,bytes calldata) external;
+    // Expect a call to an address with the specified msg.value, gas and calldata.
+    // The gas is what is forwarded to the call, excluding the stipend of calls with value
+    function expectCall(address,uint256,uint64,bytes calldata) external;
+    // Expect a number of calls to an address with the specified calldata, where 0 means it must not be called
+    function expectCall(address,bytes calldata,uint64) external;
+    // Expect a number of calls to an address with the specified msg.value and calldata
+    function expectCall(address,uint256,bytes calldata,uint64) external;
+    // Expect a number of calls to an address with the specified msg.value, gas and calldata
+    function expectCall(address,uint256,uint64,bytes calldata,uint64) external;
     // Gets the code from an artifact file. Takes in the relative path to the json file
     function getCode(string calldata) external returns (bytes memory);
+    // Reads the entire file to a string, (path) => (data)
+    function readFile(string calldata) external returns (string memory);
+    // Reads the next line of a file to a string, (path) => (line)
+    function readLine(string calldata) external returns (string memory);
+    // Writes data to a file, creating it if it doesn't exist and replacing its contents if it does, (path, data)
+    function writeFile(string calldata, string calldata) external;
+    // Removes a file, (path)
+    function removeFile(string calldata) external;
+    // Sets an environment variable, (name, value)
+    function setEnv(string calldata, string calldata) external;
+    // Reads an environment variable and parses it as the return type, reverting if it's not set or can't be parsed, (name) => (value)
+    function envBool(string calldata) external returns (bool);
+    function envUint(string calldata) external returns (uint256);
+    function envInt(string calldata) external returns (int256);
+    function envAddress(string calldata) external returns (address);
+    function envBytes32(string calldata) external returns (bytes32);
+    function envString(string calldata) external returns (string memory);
+    function envBytes(string calldata) external returns (bytes memory);
+    // Reads an environment variable as an array of values separated by the delimiter, (name, delimiter) => (values)
+    function envBool(string calldata, string calldata) external returns (bool[] memory);
+    function envUint(string calldata, string calldata) external returns (uint256[] memory);
+    function envInt(string calldata, string calldata) external returns (int256[] memory);
+    function envAddress(string calldata, string calldata) external returns (address[] memory);
+    function envBytes32(string calldata, string calldata) external returns (bytes32[] memory);
+    function envString(string calldata, string calldata) external returns (string[] memory);
+    function envBytes(string calldata, string calldata) external returns (bytes[] memory);
+    // Parses the JSON and returns its value ABI-encoded, inferring its type, (json) => (data)
+    function parseJson(string calldata) external returns (bytes memory);
+    // Parses the value at the JSONPath-like key of the JSON, inferring its type, (json, key) => (data)
+    function parseJson(string calldata, string calldata) external returns (bytes memory);
+    // Parses the value at the JSONPath-like key of the JSON as the type, e.g. `uint256[]`, (json, key, type) => (data)
+    function parseJson(string calldata, string calldata, string calldata) external returns (bytes memory);
     // Labels an address in call traces
     function label(address, string calldata) external;
     // If the condition is false, discard this run's fuzz inputs and generate new ones
@@ -64,4 +129,30 @@ interface Cheats {
     function setNonce(address,uint64) external;
     // Get nonce for an account
     function getNonce(address) external returns(uint64);
+    // Snapshot the current state of the EVM, returning the ID of the snapshot
+    function snapshot() external returns(uint256);
+    // Revert the state of the EVM to a previous snapshot, returning whether the snapshot existed
+    function revertTo(uint256) external returns(bool);
+    // Creates a new fork with the given endpoint and the latest block, returning the ID of the fork
+    function createFork(string calldata) external returns(uint256);
+    // Creates a new fork with the given endpoint and block, returning the ID of the fork
+    function createFork(string calldata,uint256) external returns(uint256);
+    // Takes a fork ID and makes it the active fork. The test contract and sender keep their state
+    function selectFork(uint256) external;
+    // Moves the active fork to the given block
+    function rollFork(uint256) external;
+    // Moves the fork with the given ID to the given block
+    function rollFork(uint256,uint256) external;
+    // Using the address that calls the test contract, has the next call (at this call depth only) create a transaction that can later be signed and sent onchain
+    function broadcast() external;
+    // Has the next call (at this call depth only) create a transaction with the address provided as the sender that can later be signed and sent onchain
+    function broadcast(address) external;
+    // Using the address that calls the test contract, has all subsequent calls (at this call depth only) create transactions that can later be signed and sent onchain
+    function startBroadcast() external;
+    // Has all subsequent calls (at this call depth only) create transactions with the address provided that can later be signed and sent onchain
+    function startBroadcast(address) external;
+    // Stops collecting onchain transactions
+    function stopBroadcast() external;
+    // Sets a breakpoint with the given name, which the debugger can continue to
+    function breakpoint(string calldata) external;
 }
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract EnvTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testSetEnv() public {
+        cheats.setEnv("_foundryCheatcodeSetEnvTestKey", "_foundryCheatcodeSetEnvTestValue");
+        assertEq(cheats.envString("_foundryCheatcodeSetEnvTestKey"), "_foundryCheatcodeSetEnvTestValue");
+    }
+
+    function testEnvBool() public {
+        cheats.setEnv("_foundryCheatcodeEnvBoolTestKey", "true");
+        assertTrue(cheats.envBool("_foundryCheatcodeEnvBoolTestKey"));
+        cheats.setEnv("_foundryCheatcodeEnvBoolTestKey", "false");
+        assertTrue(!cheats.envBool("_foundryCheatcodeEnvBoolTestKey"));
+    }
+
+    function testEnvUint() public {
+        cheats.setEnv("_foundryCheatcodeEnvUintTestKey", "115792089237316195423570985008687907853269984665640564039457584007913129639935");
+        assertEq(cheats.envUint("_foundryCheatcodeEnvUintTestKey"), type(uint256).max);
+        cheats.setEnv("_foundryCheatcodeEnvUintTestKey", "1");
+        assertEq(cheats.envUint("_foundryCheatcodeEnvUintTestKey"), 1);
+    }
+
+    function testEnvInt() public {
+        cheats.setEnv("_foundryCheatcodeEnvIntTestKey", "-57896044618658097711785492504343953926634992332820282019728792003956564819968");
+        assertEq(cheats.envInt("_foundryCheatcodeEnvIntTestKey"), type(int256).min);
+    }
+
+    function testEnvAddress() public {
+        cheats.setEnv("_foundryCheatcodeEnvAddressTestKey", "0x7109709ECfa91a80626fF3989D68f67F5b1DD12D");
+        assertEq(cheats.envAddress("_foundryCheatcodeEnvAddressTestKey"), HEVM_ADDRESS);
+    }
+
+    function testEnvBytes32() public {
+        cheats.setEnv("_foundryCheatcodeEnvBytes32TestKey", "0x7109709ecfa91a80626ff3989d68f67f5b1dd12d000000000000000000000000");
+        assertEq(
+            cheats.envBytes32("_foundryCheatcodeEnvBytes32TestKey"),
+            bytes32(0x7109709ecfa91a80626ff3989d68f67f5b1dd12d000000000000000000000000)
+        );
+    }
+
+    function testEnvBytes() public {
+        cheats.setEnv("_foundryCheatcodeEnvBytesTestKey", "0x7109709ecfa91a80626ff3989d68f67f5b1dd12d");
+        assertEq(
+            keccak256(cheats.envBytes("_foundryCheatcodeEnvBytesTestKey")),
+            keccak256(hex"7109709ecfa91a80626ff3989d68f67f5b1dd12d")
+        );
+    }
+
+    function testEnvUintArray() public {
+        cheats.setEnv("_foundryCheatcodeEnvUintArrayTestKey", "1, 2,3");
+        uint256[] memory values = cheats.envUint("_foundryCheatcodeEnvUintArrayTestKey", ",");
+        assertEq(values.length, 3);
+        assertEq(values[0], 1);
+        assertEq(values[1], 2);
+        assertEq(values[2], 3);
+    }
+
+    function testEnvStringArray() public {
+        cheats.setEnv("_foundryCheatcodeEnvStringArrayTestKey", "hello|world");
+        string[] memory values = cheats.envString("_foundryCheatcodeEnvStringArrayTestKey", "|");
+        assertEq(values.length, 2);
+        assertEq(values[0], "hello");
+        assertEq(values[1], "world");
+    }
+
+    function testFailEnvUnset() public {
+        cheats.envUint("_foundryCheatcodeEnvUnsetTestKey");
+    }
+
+    function testFailEnvInvalid() public {
+        cheats.setEnv("_foundryCheatcodeEnvInvalidTestKey", "not a number");
+        cheats.envUint("_foundryCheatcodeEnvInvalidTestKey");
+    }
+}
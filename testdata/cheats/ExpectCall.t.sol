@@ -18,6 +18,25 @@ contract Contract {
     }
 }
 
+contract Payable {
+    function pay(uint256 a) public payable returns (uint256) {
+        return a;
+    }
+}
+
+contract PayingContract {
+    Payable private inner;
+
+    constructor(Payable _inner) {
+        inner = _inner;
+    }
+
+    function payTwice() public {
+        inner.pay{value: 1, gas: 50_000}(1);
+        inner.pay{value: 2}(2);
+    }
+}
+
 contract NestedContract {
     Contract private inner;
 
@@ -104,4 +123,84 @@ contract ExpectCallTest is DSTest {
         );
         target.add(3, 3);
     }
+
+    function testExpectCallWithValue() public {
+        Payable target = new Payable();
+        cheats.expectCall(address(target), 1, abi.encodeWithSelector(target.pay.selector, 2));
+        target.pay{value: 1}(2);
+    }
+
+    function testFailExpectCallWithValue() public {
+        Payable target = new Payable();
+        cheats.expectCall(address(target), 1, abi.encodeWithSelector(target.pay.selector, 2));
+        target.pay{value: 2}(2);
+    }
+
+    function testExpectCallWithValueAndGas() public {
+        Payable inner = new Payable();
+        PayingContract target = new PayingContract(inner);
+        cheats.deal(address(target), 3);
+
+        cheats.expectCall(address(inner), 1, 50_000, abi.encodeWithSelector(inner.pay.selector, 1));
+        target.payTwice();
+    }
+
+    function testFailExpectCallWithWrongGas() public {
+        Payable inner = new Payable();
+        PayingContract target = new PayingContract(inner);
+        cheats.deal(address(target), 3);
+
+        cheats.expectCall(address(inner), 1, 40_000, abi.encodeWithSelector(inner.pay.selector, 1));
+        target.payTwice();
+    }
+
+    function testExpectCallCount() public {
+        Payable inner = new Payable();
+        PayingContract target = new PayingContract(inner);
+        cheats.deal(address(target), 3);
+
+        cheats.expectCall(address(inner), abi.encodeWithSelector(inner.pay.selector), 2);
+        cheats.expectCall(address(inner), 2, abi.encodeWithSelector(inner.pay.selector), 1);
+        target.payTwice();
+    }
+
+    function testFailExpectCallCount() public {
+        Payable inner = new Payable();
+        PayingContract target = new PayingContract(inner);
+        cheats.deal(address(target), 3);
+
+        cheats.expectCall(address(inner), abi.encodeWithSelector(inner.pay.selector), 1);
+        target.payTwice();
+    }
+
+    function testExpectNoCall() public {
+        Contract inner = new Contract();
+        NestedContract target = new NestedContract(inner);
+
+        cheats.expectCall(address(inner), abi.encodeWithSelector(inner.numberA.selector), 0);
+        target.hello();
+    }
+
+    function testFailExpectNoCall() public {
+        Contract inner = new Contract();
+        NestedContract target = new NestedContract(inner);
+
+        cheats.expectCall(address(inner), abi.encodeWithSelector(inner.numberA.selector), 0);
+        target.sum();
+    }
+
+    function testExpectSameCallTwice() public {
+        Contract target = new Contract();
+        cheats.expectCall(address(target), abi.encodeWithSelector(target.numberA.selector));
+        cheats.expectCall(address(target), abi.encodeWithSelector(target.numberA.selector));
+        target.numberA();
+        target.numberA();
+    }
+
+    function testFailExpectSameCallTwice() public {
+        Contract target = new Contract();
+        cheats.expectCall(address(target), abi.encodeWithSelector(target.numberA.selector));
+        cheats.expectCall(address(target), abi.encodeWithSelector(target.numberA.selector));
+        target.numberA();
+    }
 }
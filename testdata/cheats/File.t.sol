@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract FileTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testReadFile() public {
+        string memory path = "fixtures/File/read.txt";
+
+        assertEq(cheats.readFile(path), "hello readable world\nthis is the second line!");
+    }
+
+    function testReadLine() public {
+        string memory path = "fixtures/File/read.txt";
+
+        assertEq(cheats.readLine(path), "hello readable world");
+        assertEq(cheats.readLine(path), "this is the second line!");
+        assertEq(cheats.readLine(path), "");
+    }
+
+    function testWriteFile() public {
+        string memory path = "fixtures/File/write/write_file.txt";
+        string memory data = "hello writable world\nthis is the second line!";
+        cheats.writeFile(path, data);
+
+        assertEq(cheats.readFile(path), data);
+        assertEq(cheats.readLine(path), "hello writable world");
+
+        // The file is read from the start again after writing it
+        cheats.writeFile(path, "overwritten");
+        assertEq(cheats.readLine(path), "overwritten");
+
+        cheats.removeFile(path);
+    }
+
+    function testRemoveFile() public {
+        string memory path = "fixtures/File/write/remove_file.txt";
+        cheats.writeFile(path, "removed");
+        cheats.removeFile(path);
+
+        try cheats.readFile(path) {
+            fail();
+        } catch {}
+    }
+
+    function testFailReadForbidden() public {
+        cheats.readFile("cheats/Cheats.sol");
+    }
+
+    function testFailReadOutsideOfPermittedPath() public {
+        cheats.readFile("fixtures/File/../../cheats/Cheats.sol");
+    }
+
+    function testFailWriteReadOnly() public {
+        cheats.writeFile("fixtures/File/read.txt", "forbidden");
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract JsonTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    // The fields are declared in the alphabetical order of the JSON keys
+    struct Deployment {
+        address addr;
+        uint256 block;
+        string name;
+    }
+
+    string json =
+        '{"deployments":[{"name":"Token","addr":"0x7109709ECfa91a80626fF3989D68f67F5b1DD12D","block":12}],"chainId":1,"hash":"0x7109709ecfa91a80626ff3989d68f67f5b1dd12d000000000000000000000000","amounts":[1,2,3],"supply":"115792089237316195423570985008687907853269984665640564039457584007913129639935","maxSupply":115792089237316195423570985008687907853269984665640564039457584007913129639935,"minBalance":-57896044618658097711785492504343953926634992332820282019728792003956564819968}';
+
+    function testParseJsonUint() public {
+        uint256 chainId = abi.decode(cheats.parseJson(json, "$.chainId"), (uint256));
+        assertEq(chainId, 1);
+    }
+
+    function testParseJsonBytes32() public {
+        bytes32 hash = abi.decode(cheats.parseJson(json, ".hash"), (bytes32));
+        assertEq(hash, bytes32(0x7109709ecfa91a80626ff3989d68f67f5b1dd12d000000000000000000000000));
+    }
+
+    function testParseJsonArray() public {
+        uint256[] memory amounts = abi.decode(cheats.parseJson(json, ".amounts"), (uint256[]));
+        assertEq(amounts.length, 3);
+        assertEq(amounts[2], 3);
+    }
+
+    function testParseJsonIndex() public {
+        address addr = abi.decode(cheats.parseJson(json, "$.deployments[0].addr"), (address));
+        assertEq(addr, HEVM_ADDRESS);
+        string memory name = abi.decode(cheats.parseJson(json, "$.deployments[0]['name']"), (string));
+        assertEq(name, "Token");
+    }
+
+    function testParseJsonStruct() public {
+        Deployment memory deployment = abi.decode(cheats.parseJson(json, "$.deployments[0]"), (Deployment));
+        assertEq(deployment.addr, HEVM_ADDRESS);
+        assertEq(deployment.block, 12);
+        assertEq(deployment.name, "Token");
+    }
+
+    function testParseJsonExplicitType() public {
+        uint256 supply = abi.decode(cheats.parseJson(json, "$.supply", "uint256"), (uint256));
+        assertEq(supply, type(uint256).max);
+    }
+
+    function testParseJsonLargeIntegers() public {
+        uint256 maxSupply = abi.decode(cheats.parseJson(json, "$.maxSupply"), (uint256));
+        assertEq(maxSupply, type(uint256).max);
+        int256 minBalance = abi.decode(cheats.parseJson(json, "$.minBalance"), (int256));
+        assertEq(minBalance, type(int256).min);
+    }
+
+    function testParseJsonWhole() public {
+        bytes memory data = cheats.parseJson('{"a":true}');
+        bool a = abi.decode(data, (bool));
+        assertTrue(a);
+    }
+
+    function testFailParseJsonMissingKey() public {
+        cheats.parseJson(json, "$.missing");
+    }
+}
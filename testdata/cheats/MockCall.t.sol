@@ -16,6 +16,10 @@ contract Mock {
     function add(uint256 a, uint256 b) public pure returns (uint256) {
         return a + b;
     }
+
+    function pay(uint256 a) public payable returns (uint256) {
+        return a;
+    }
 }
 
 contract NestedMock {
@@ -113,4 +117,72 @@ contract MockCallTest is DSTest {
         assertEq(target.numberA(), 1);
         assertEq(target.numberB(), 2);
     }
+
+    function testMockCallRevert() public {
+        Mock target = new Mock();
+        bytes memory revertData = abi.encodeWithSignature("Error(string)", "oracle is down");
+
+        cheats.mockCallRevert(
+            address(target),
+            abi.encodeWithSelector(target.numberB.selector),
+            revertData
+        );
+
+        assertEq(target.numberA(), 1);
+        (bool success, bytes memory data) =
+            address(target).call(abi.encodeWithSelector(target.numberB.selector));
+        assertTrue(!success);
+        assertEq(data, revertData);
+
+        // Mocking the call again replaces the revert
+        cheats.mockCall(
+            address(target),
+            abi.encodeWithSelector(target.numberB.selector),
+            abi.encode(10)
+        );
+        assertEq(target.numberB(), 10);
+    }
+
+    function testFailMockCallRevert() public {
+        Mock target = new Mock();
+        cheats.mockCallRevert(
+            address(target),
+            abi.encodeWithSelector(target.numberA.selector),
+            "reverted"
+        );
+        target.numberA();
+    }
+
+    function testMockValue() public {
+        Mock target = new Mock();
+
+        cheats.mockCall(
+            address(target),
+            10,
+            abi.encodeWithSelector(target.pay.selector),
+            abi.encode(10)
+        );
+
+        assertEq(target.pay{value: 10}(1), 10);
+        assertEq(target.pay{value: 9}(1), 1);
+        assertEq(target.pay(1), 1);
+    }
+
+    function testMockValueRevert() public {
+        Mock target = new Mock();
+
+        cheats.mockCallRevert(
+            address(target),
+            10,
+            abi.encodeWithSelector(target.pay.selector),
+            "expensive"
+        );
+
+        assertEq(target.pay(1), 1);
+        (bool success, bytes memory data) = address(target).call{value: 10}(
+            abi.encodeWithSelector(target.pay.selector, 1)
+        );
+        assertTrue(!success);
+        assertEq(data, bytes("expensive"));
+    }
 }
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Emitter {
+    event Created(uint256 indexed id, address owner);
+
+    uint256 nonce;
+
+    function create() public returns (uint256 id) {
+        id = uint256(keccak256(abi.encode(nonce++, block.timestamp)));
+        emit Created(id, msg.sender);
+    }
+}
+
+contract RecordLogsTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    event Created(uint256 indexed id, address owner);
+
+    function testRecordLogs() public {
+        Emitter emitter = new Emitter();
+        emitter.create();
+
+        cheats.recordLogs();
+        uint256 id = emitter.create();
+        emit Created(1, address(0));
+
+        Cheats.Log[] memory logs = cheats.getRecordedLogs();
+        assertEq(logs.length, 2);
+
+        assertEq(logs[0].emitter, address(emitter));
+        assertEq(logs[0].topics.length, 2);
+        assertEq(logs[0].topics[0], keccak256("Created(uint256,address)"));
+        assertEq(logs[0].topics[1], bytes32(id));
+        assertEq(abi.decode(logs[0].data, (address)), address(this));
+
+        assertEq(logs[1].emitter, address(this));
+        assertEq(logs[1].topics[1], bytes32(uint256(1)));
+    }
+
+    function testGetRecordedLogsConsumes() public {
+        Emitter emitter = new Emitter();
+        cheats.recordLogs();
+        emitter.create();
+
+        assertEq(cheats.getRecordedLogs().length, 1);
+        assertEq(cheats.getRecordedLogs().length, 0);
+
+        emitter.create();
+        assertEq(cheats.getRecordedLogs().length, 1);
+    }
+
+    function testGetRecordedLogsWithoutRecording() public {
+        Emitter emitter = new Emitter();
+        emitter.create();
+
+        assertEq(cheats.getRecordedLogs().length, 0);
+    }
+}
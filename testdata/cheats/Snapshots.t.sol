@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Counter {
+    uint256 public count;
+
+    function increment() public {
+        count++;
+    }
+}
+
+contract SnapshotTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+    Counter counter;
+    uint256 setupSnapshot;
+
+    function setUp() public {
+        counter = new Counter();
+        setupSnapshot = cheats.snapshot();
+        counter.increment();
+    }
+
+    function testSnapshot() public {
+        uint256 snapshot = cheats.snapshot();
+        counter.increment();
+        counter.increment();
+        assertEq(counter.count(), 3);
+
+        assertTrue(cheats.revertTo(snapshot));
+        assertEq(counter.count(), 1, "snapshot revert for storage failed");
+    }
+
+    function testRevertToSetupSnapshot() public {
+        assertEq(counter.count(), 1);
+        assertTrue(cheats.revertTo(setupSnapshot));
+        assertEq(counter.count(), 0, "snapshot revert across transactions failed");
+    }
+
+    function testRevertToSnapshotMultipleTimes() public {
+        uint256 snapshot = cheats.snapshot();
+        for (uint256 i = 0; i < 3; i++) {
+            counter.increment();
+            assertEq(counter.count(), 2);
+            assertTrue(cheats.revertTo(snapshot));
+            assertEq(counter.count(), 1);
+        }
+    }
+
+    function testSnapshotBalance() public {
+        address who = address(1337);
+        uint256 snapshot = cheats.snapshot();
+        cheats.deal(who, 1 ether);
+        assertEq(who.balance, 1 ether);
+
+        cheats.revertTo(snapshot);
+        assertEq(who.balance, 0, "snapshot revert for balance failed");
+    }
+
+    function testSnapshotBlock() public {
+        uint256 timestamp = block.timestamp;
+        uint256 number = block.number;
+        uint256 snapshot = cheats.snapshot();
+
+        cheats.warp(1337);
+        cheats.roll(99);
+        assertEq(block.timestamp, 1337);
+        assertEq(block.number, 99);
+
+        cheats.revertTo(snapshot);
+        assertEq(block.timestamp, timestamp, "snapshot revert for block timestamp failed");
+        assertEq(block.number, number, "snapshot revert for block number failed");
+    }
+
+    function testRevertToUnknownSnapshot() public {
+        assertTrue(!cheats.revertTo(1337));
+    }
+}
@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Counter {
+    uint256 public count;
+
+    function increment(uint256 by) public returns (uint256) {
+        count += by;
+        return count;
+    }
+
+    function unused() public pure returns (uint256) {
+        return 42;
+    }
+}
+
+contract NestedCoverageTest is DSTest {
+    Counter counter;
+
+    function setUp() public {
+        counter = new Counter();
+    }
+
+    function testIncrement() public {
+        counter.increment(1);
+        uint256 afterCall = counter.count() + 1;
+        assertEq(afterCall, 2);
+    }
+
+    function testNothing() public {}
+}
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "../cheats/Cheats.sol";
+
+// Forks mainnet at runtime, so it needs `ETH_RPC_URL` to be set
+contract ForkTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    // Deployed at block 8928158
+    address constant DAI = 0x6B175474E89094C44Da98b954EedeAC495271d0F;
+
+    uint256 mainnet;
+    uint256 genesis;
+
+    function setUp() public {
+        string memory url = cheats.envString("ETH_RPC_URL");
+        mainnet = cheats.createFork(url, 15_000_000);
+        genesis = cheats.createFork(url, 1);
+    }
+
+    function testCreateSelectFork() public {
+        cheats.selectFork(mainnet);
+        assertEq(block.number, 15_000_000);
+        assertEq(block.chainid, 1);
+        assertGt(DAI.code.length, 0);
+    }
+
+    function testSwitchForks() public {
+        cheats.selectFork(genesis);
+        assertEq(block.number, 1);
+        assertEq(DAI.code.length, 0);
+
+        cheats.selectFork(mainnet);
+        assertEq(block.number, 15_000_000);
+        assertGt(DAI.code.length, 0);
+    }
+
+    function testRollFork() public {
+        cheats.selectFork(mainnet);
+        assertGt(DAI.code.length, 0);
+
+        cheats.rollFork(8_000_000);
+        assertEq(block.number, 8_000_000);
+        assertEq(DAI.code.length, 0);
+    }
+
+    function testRollForkById() public {
+        cheats.selectFork(mainnet);
+        cheats.rollFork(genesis, 15_000_000);
+        assertEq(block.number, 15_000_000);
+
+        cheats.selectFork(genesis);
+        assertEq(block.number, 15_000_000);
+        assertGt(DAI.code.length, 0);
+    }
+
+    function testRevertToAcrossForks() public {
+        cheats.selectFork(mainnet);
+        uint256 snapshot = cheats.snapshot();
+
+        cheats.selectFork(genesis);
+        assertEq(block.number, 1);
+        assertEq(DAI.code.length, 0);
+
+        // Reverting selects the fork the snapshot was taken on again
+        assertTrue(cheats.revertTo(snapshot));
+        assertEq(block.number, 15_000_000);
+        assertGt(DAI.code.length, 0);
+    }
+
+    function testFailRollUnselectedFork() public {
+        cheats.rollFork(15_000_000);
+    }
+
+    function testFailBlockNumberTooLarge() public {
+        cheats.createFork(cheats.envString("ETH_RPC_URL"), uint256(type(uint64).max) + 1);
+    }
+}
+
+contract Marker {}
+
+// Forks mainnet after changing accounts, so it needs `ETH_RPC_URL` to be set
+contract ForkBaseStateTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    address constant DAI = 0x6B175474E89094C44Da98b954EedeAC495271d0F;
+
+    Marker marker;
+
+    // Committed before any fork is selected, so the state of these accounts is cached by the
+    // executor
+    constructor() {
+        marker = new Marker();
+        cheats.deal(DAI, 1 ether);
+    }
+
+    function setUp() public {
+        cheats.selectFork(cheats.createFork(cheats.envString("ETH_RPC_URL"), 15_000_000));
+        // Committed to the fork
+        cheats.deal(DAI, 2 ether);
+    }
+
+    function testForkHidesBaseState() public {
+        assertEq(address(marker).code.length, 0);
+        assertGt(DAI.code.length, 0);
+        assertEq(DAI.balance, 2 ether);
+    }
+}
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract InvariantBreaker {
+  bool public flag0 = true;
+  bool public flag1 = true;
+
+  function set0(uint256 val) public {
+    if (val % 2 == 0) flag0 = false;
+  }
+
+  function set1(uint256 val) public {
+    if (val % 2 == 0 && !flag0) flag1 = false;
+  }
+}
+
+contract InvariantTest is DSTest {
+  InvariantBreaker breaker;
+
+  function setUp() public {
+    breaker = new InvariantBreaker();
+  }
+
+  function invariantAlwaysTrue() public returns (bool) {
+    return true;
+  }
+
+  function invariantFlag0() public returns (bool) {
+    return breaker.flag0();
+  }
+
+  function invariantFlag1() public {
+    require(breaker.flag1(), "flag1 was unset");
+  }
+}
@@ -0,0 +1,34 @@
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Counter {
+    uint256 public number;
+
+    function set(uint256 value) public {
+        number = value;
+        number = value + 1;
+    }
+
+    function setAndFail(uint256 value) public {
+        set(value);
+        revert("failed");
+    }
+}
+
+contract StorageChangesTest is DSTest {
+    Counter counter;
+
+    function setUp() public {
+        counter = new Counter();
+    }
+
+    function testStorageChanges() public {
+        counter.set(1);
+    }
+
+    function testRevertedStorageChanges() public {
+        try counter.setAndFail(5) {} catch {}
+        counter.set(1);
+    }
+}
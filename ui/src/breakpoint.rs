@@ -0,0 +1,207 @@
+//! Breakpoints to continue the execution to
+
+use ethers::{abi::AbiDecode, types::Address};
+use forge::{
+    abi::{HEVMCalls, CHEATCODE_ADDRESS},
+    debug::{DebugStep, Instruction},
+    CallKind,
+};
+use revm::{opcode, OpCode};
+use std::{fmt, str::FromStr};
+
+/// A breakpoint set in the debugger.
+///
+/// The `vm.breakpoint(name)` cheatcode is always a breakpoint, see [breakpoint_name].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stops at every step at the program counter, e.g. `pc 0x1a`
+    Pc(usize),
+    /// Stops at every execution of the opcode, e.g. `op SSTORE`
+    OpCode(u8),
+    /// Stops at the start of every call to the address, e.g. `call 0x...`
+    Call(Address),
+}
+
+impl Breakpoint {
+    /// Whether the step is a stop of the breakpoint.
+    ///
+    /// `call_start` is whether the step is the first step of a call to `address`, rather than of
+    /// a call that continues after a subcall returned.
+    pub fn matches(&self, address: Address, call_start: bool, step: &DebugStep) -> bool {
+        match (self, step.instruction) {
+            (Breakpoint::Pc(pc), Instruction::OpCode(_)) => step.pc == *pc,
+            (Breakpoint::OpCode(op), Instruction::OpCode(step_op)) => step_op == *op,
+            (Breakpoint::Call(call), _) => call_start && address == *call,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.trim().split_once(' ').ok_or_else(|| {
+            format!("Expected `pc <pc>`, `op <opcode>` or `call <address>`: `{}`", s)
+        })?;
+        let value = value.trim();
+        match kind {
+            "pc" => {
+                let pc = match value.strip_prefix("0x") {
+                    Some(hex) => usize::from_str_radix(hex, 16),
+                    None => value.parse(),
+                };
+                pc.map(Breakpoint::Pc).map_err(|_| format!("Invalid program counter `{}`", value))
+            }
+            "op" => (0..=u8::MAX)
+                .find(|op| {
+                    OpCode::try_from_u8(*op)
+                        .map_or(false, |opcode| opcode.as_str().eq_ignore_ascii_case(value))
+                })
+                .map(Breakpoint::OpCode)
+                .ok_or_else(|| format!("Unknown opcode `{}`", value)),
+            "call" => value
+                .parse()
+                .map(Breakpoint::Call)
+                .map_err(|_| format!("Invalid address `{}`", value)),
+            _ => Err(format!("Unknown breakpoint kind `{}`", kind)),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(pc) => write!(f, "pc 0x{:x}", pc),
+            Breakpoint::OpCode(op) => write!(f, "op {}", Instruction::OpCode(*op)),
+            Breakpoint::Call(address) => write!(f, "call {:?}", address),
+        }
+    }
+}
+
+/// Returns the name of the `vm.breakpoint(name)` cheatcode if the step calls it.
+///
+/// The name is decoded from the calldata of the call in memory, so the breakpoint stops in the
+/// calling contract, at the line of the cheatcode.
+pub fn breakpoint_name(step: &DebugStep) -> Option<String> {
+    // The offset and size of the calldata on the stack, counted from the top
+    let (offset, size) = match step.instruction {
+        Instruction::OpCode(opcode::CALL | opcode::CALLCODE) => (3, 4),
+        Instruction::OpCode(opcode::DELEGATECALL | opcode::STATICCALL) => (2, 3),
+        _ => return None,
+    };
+    let stack = &step.stack;
+    let word = |idx: usize| stack.len().checked_sub(idx + 1).map(|idx| stack[idx]);
+
+    let mut address = [0u8; 32];
+    word(1)?.to_big_endian(&mut address);
+    if Address::from_slice(&address[12..]) != CHEATCODE_ADDRESS {
+        return None
+    }
+
+    let (offset, size) = (word(offset)?, word(size)?);
+    if offset.bits() > 64 || size.bits() > 64 {
+        return None
+    }
+    let (offset, size) = (offset.as_usize(), size.as_usize());
+    let calldata = step.memory.data().get(offset..offset.checked_add(size)?)?;
+    match HEVMCalls::decode(calldata).ok()? {
+        HEVMCalls::Breakpoint(inner) => Some(inner.0),
+        _ => None,
+    }
+}
+
+/// Returns the position (the index of the call and of the step in the call) of the first step
+/// after `from`, or the last step before it if not `forward`, that is a stop of a breakpoint,
+/// together with a description of the breakpoint.
+pub fn find_breakpoint(
+    calls: &[(Address, Vec<DebugStep>, CallKind, usize)],
+    breakpoints: &[Breakpoint],
+    from: (usize, usize),
+    forward: bool,
+) -> Option<((usize, usize), String)> {
+    let mut steps = calls.iter().enumerate().flat_map(|(call, (address, steps, _, depth))| {
+        // The calls that continue after a subcall returned are less deep than the call before
+        let call_start = call == 0 || *depth > calls[call - 1].3;
+        steps
+            .iter()
+            .enumerate()
+            .map(move |(idx, step)| ((call, idx), *address, call_start && idx == 0, step))
+    });
+    let stop = |(position, address, call_start, step): ((usize, usize), Address, bool, _)| {
+        let description = match breakpoint_name(step) {
+            Some(name) => format!("Breakpoint \"{}\"", name),
+            None => format!(
+                "Breakpoint: {}",
+                breakpoints
+                    .iter()
+                    .find(|breakpoint| breakpoint.matches(address, call_start, step))?
+            ),
+        };
+        Some((position, description))
+    };
+
+    if forward {
+        steps.find_map(|step| if step.0 > from { stop(step) } else { None })
+    } else {
+        steps.rev().find_map(|step| if step.0 < from { stop(step) } else { None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_breakpoints() {
+        assert_eq!("pc 0x1a".parse(), Ok(Breakpoint::Pc(0x1a)));
+        assert_eq!("pc 26".parse(), Ok(Breakpoint::Pc(0x1a)));
+        assert_eq!("op sstore".parse(), Ok(Breakpoint::OpCode(opcode::SSTORE)));
+        assert_eq!(
+            "call 0x7109709ECfa91a80626fF3989D68f67F5b1DD12D".parse(),
+            Ok(Breakpoint::Call(CHEATCODE_ADDRESS))
+        );
+        assert!("op FOO".parse::<Breakpoint>().is_err());
+        assert!("jump 1".parse::<Breakpoint>().is_err());
+
+        assert_eq!(Breakpoint::OpCode(opcode::SSTORE).to_string(), "op SSTORE");
+        assert_eq!(Breakpoint::Pc(0x1a).to_string(), "pc 0x1a");
+    }
+
+    #[test]
+    fn can_match_breakpoints() {
+        let step = DebugStep {
+            pc: 0x1a,
+            instruction: Instruction::OpCode(opcode::SSTORE),
+            ..Default::default()
+        };
+        let address = Address::random();
+
+        assert!(Breakpoint::Pc(0x1a).matches(address, false, &step));
+        assert!(Breakpoint::OpCode(opcode::SSTORE).matches(address, false, &step));
+        assert!(!Breakpoint::OpCode(opcode::SLOAD).matches(address, false, &step));
+        assert!(Breakpoint::Call(address).matches(address, true, &step));
+        assert!(!Breakpoint::Call(address).matches(address, false, &step));
+        assert!(breakpoint_name(&step).is_none());
+    }
+
+    #[test]
+    fn can_find_breakpoints() {
+        let step = |op| DebugStep { instruction: Instruction::OpCode(op), ..Default::default() };
+        let (caller, callee) = (Address::random(), Address::random());
+        let calls = vec![
+            (caller, vec![step(opcode::SSTORE), step(opcode::CALL)], CallKind::Call, 0),
+            (callee, vec![step(opcode::SSTORE)], CallKind::Call, 1),
+            (caller, vec![step(opcode::SLOAD), step(opcode::SSTORE)], CallKind::Call, 0),
+        ];
+        let breakpoints = [Breakpoint::OpCode(opcode::SSTORE), Breakpoint::Call(caller)];
+
+        let find = |from, forward| find_breakpoint(&calls, &breakpoints, from, forward);
+        assert_eq!(find((0, 0), true), Some(((1, 0), "Breakpoint: op SSTORE".to_string())));
+        assert_eq!(find((1, 0), true).unwrap().0, (2, 1));
+        assert_eq!(find((2, 1), true), None);
+        assert_eq!(find((2, 1), false).unwrap().0, (1, 0));
+        // Returning to the caller is not the start of a call
+        assert_eq!(find((1, 0), false).unwrap(), ((0, 0), "Breakpoint: op SSTORE".to_string()));
+    }
+}
@@ -6,7 +6,13 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ethers::{solc::artifacts::ContractBytecodeSome, types::Address};
+use ethers::{
+    solc::{
+        artifacts::ContractBytecodeSome,
+        sourcemap::{SourceMap, SyntaxError},
+    },
+    types::Address,
+};
 use eyre::Result;
 use forge::{
     debug::{DebugStep, Instruction},
@@ -47,8 +53,20 @@ pub enum TUIExitReason {
 mod op_effects;
 use op_effects::stack_indices_affected;
 
+mod stepping;
+use stepping::{next_source_step, step_locations, SourceStep};
+
+mod breakpoint;
+use breakpoint::{find_breakpoint, Breakpoint};
+
+mod navigation;
+use navigation::{call_segments, last_memory_write, last_stack_write};
+
+/// The bytecode of a contract and its sources, keyed by their index in the source maps
+type ContractSources<'a> = (&'a ContractBytecodeSome, &'a BTreeMap<u32, String>);
+
 pub struct Tui {
-    debug_arena: Vec<(Address, Vec<DebugStep>, CallKind)>,
+    debug_arena: Vec<(Address, Vec<DebugStep>, CallKind, usize)>,
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     /// Buffer for keys prior to execution, i.e. '10' + 'k' => move up 10 operations
     key_buffer: String,
@@ -57,17 +75,21 @@ pub struct Tui {
     identified_contracts: BTreeMap<Address, String>,
     known_contracts: BTreeMap<String, ContractBytecodeSome>,
     source_code: BTreeMap<u32, String>,
+    /// Contracts that are not part of the project, e.g. verified contracts of a fork, with their
+    /// own sources
+    external_contracts: BTreeMap<Address, (ContractBytecodeSome, BTreeMap<u32, String>)>,
 }
 
 impl Tui {
     /// Create a tui
     #[allow(unused_must_use)]
     pub fn new(
-        debug_arena: Vec<(Address, Vec<DebugStep>, CallKind)>,
+        debug_arena: Vec<(Address, Vec<DebugStep>, CallKind, usize)>,
         current_step: usize,
         identified_contracts: BTreeMap<Address, String>,
         known_contracts: BTreeMap<String, ContractBytecodeSome>,
         source_code: BTreeMap<u32, String>,
+        external_contracts: BTreeMap<Address, (ContractBytecodeSome, BTreeMap<u32, String>)>,
     ) -> Result<Self> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
@@ -83,9 +105,39 @@ impl Tui {
             identified_contracts,
             known_contracts,
             source_code,
+            external_contracts,
         })
     }
 
+    /// Returns the bytecode and the sources of the contract at `address`.
+    ///
+    /// Contracts of the project are preferred over external contracts.
+    fn contract_sources<'a>(
+        address: &Address,
+        identified_contracts: &BTreeMap<Address, String>,
+        known_contracts: &'a BTreeMap<String, ContractBytecodeSome>,
+        source_code: &'a BTreeMap<u32, String>,
+        external_contracts: &'a BTreeMap<Address, (ContractBytecodeSome, BTreeMap<u32, String>)>,
+    ) -> Option<ContractSources<'a>> {
+        identified_contracts
+            .get(address)
+            .and_then(|name| known_contracts.get(name))
+            .map(|known| (known, source_code))
+            .or_else(|| external_contracts.get(address).map(|(known, sources)| (known, sources)))
+    }
+
+    /// Returns the source map of the contract for the kind of call
+    fn source_map(
+        known: &ContractBytecodeSome,
+        call_kind: CallKind,
+    ) -> Option<Result<SourceMap, SyntaxError>> {
+        if matches!(call_kind, CallKind::Create) {
+            known.bytecode.source_map()
+        } else {
+            known.deployed_bytecode.bytecode.as_ref().expect("no bytecode").source_map()
+        }
+    }
+
     /// Grab number from buffer. Used for something like '10k' to move up 10 operations
     fn buffer_as_number(buffer: &str, default_value: usize) -> usize {
         if let Ok(num) = buffer.parse() {
@@ -104,9 +156,7 @@ impl Tui {
     fn draw_layout<B: Backend>(
         f: &mut Frame<B>,
         address: Address,
-        identified_contracts: &BTreeMap<Address, String>,
-        known_contracts: &BTreeMap<String, ContractBytecodeSome>,
-        source_code: &BTreeMap<u32, String>,
+        contract_sources: Option<ContractSources>,
         debug_steps: &[DebugStep],
         opcode_list: &[String],
         current_step: usize,
@@ -114,15 +164,14 @@ impl Tui {
         draw_memory: &mut DrawMemory,
         stack_labels: bool,
         mem_utf: bool,
+        message: Option<&str>,
     ) {
         let total_size = f.size();
         if total_size.width < 225 {
             Tui::vertical_layout(
                 f,
                 address,
-                identified_contracts,
-                known_contracts,
-                source_code,
+                contract_sources,
                 debug_steps,
                 opcode_list,
                 current_step,
@@ -130,14 +179,13 @@ impl Tui {
                 draw_memory,
                 stack_labels,
                 mem_utf,
+                message,
             );
         } else {
             Tui::square_layout(
                 f,
                 address,
-                identified_contracts,
-                known_contracts,
-                source_code,
+                contract_sources,
                 debug_steps,
                 opcode_list,
                 current_step,
@@ -145,6 +193,7 @@ impl Tui {
                 draw_memory,
                 stack_labels,
                 mem_utf,
+                message,
             );
         }
     }
@@ -153,9 +202,7 @@ impl Tui {
     fn vertical_layout<B: Backend>(
         f: &mut Frame<B>,
         address: Address,
-        identified_contracts: &BTreeMap<Address, String>,
-        known_contracts: &BTreeMap<String, ContractBytecodeSome>,
-        source_code: &BTreeMap<u32, String>,
+        contract_sources: Option<ContractSources>,
         debug_steps: &[DebugStep],
         opcode_list: &[String],
         current_step: usize,
@@ -163,6 +210,7 @@ impl Tui {
         draw_memory: &mut DrawMemory,
         stack_labels: bool,
         mem_utf: bool,
+        message: Option<&str>,
     ) {
         let total_size = f.size();
         if let [app, footer] = Layout::default()
@@ -183,13 +231,11 @@ impl Tui {
                 )
                 .split(app)[..]
             {
-                Tui::draw_footer(f, footer);
+                Tui::draw_footer(f, footer, message);
                 Tui::draw_src(
                     f,
                     address,
-                    identified_contracts,
-                    known_contracts,
-                    source_code,
+                    contract_sources,
                     debug_steps[current_step].ic,
                     call_kind,
                     src_pane,
@@ -224,9 +270,7 @@ impl Tui {
     fn square_layout<B: Backend>(
         f: &mut Frame<B>,
         address: Address,
-        identified_contracts: &BTreeMap<Address, String>,
-        known_contracts: &BTreeMap<String, ContractBytecodeSome>,
-        source_code: &BTreeMap<u32, String>,
+        contract_sources: Option<ContractSources>,
         debug_steps: &[DebugStep],
         opcode_list: &[String],
         current_step: usize,
@@ -234,6 +278,7 @@ impl Tui {
         draw_memory: &mut DrawMemory,
         stack_labels: bool,
         mem_utf: bool,
+        message: Option<&str>,
     ) {
         let total_size = f.size();
 
@@ -260,13 +305,11 @@ impl Tui {
                         .constraints([Constraint::Ratio(1, 4), Constraint::Ratio(3, 4)].as_ref())
                         .split(right_pane)[..]
                     {
-                        Tui::draw_footer(f, footer);
+                        Tui::draw_footer(f, footer, message);
                         Tui::draw_src(
                             f,
                             address,
-                            identified_contracts,
-                            known_contracts,
-                            source_code,
+                            contract_sources,
                             debug_steps[current_step].ic,
                             call_kind,
                             src_pane,
@@ -308,13 +351,20 @@ impl Tui {
         }
     }
 
-    fn draw_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
+    /// Draws the key bindings, or the message if there is one
+    fn draw_footer<B: Backend>(f: &mut Frame<B>, area: Rect, message: Option<&str>) {
         let block_controls = Block::default();
 
-        let text_output = Text::from(Span::styled(
-            "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [g/G]: start/end | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
-            Style::default().add_modifier(Modifier::DIM)
-        ));
+        let text_output = match message {
+            Some(message) => Text::from(Span::styled(
+                message.to_string(),
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            )),
+            None => Text::from(Span::styled(
+                "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [g/G]: start/end | [n/i/o]: next line/step into/step out | [b/B]: add/clear breakpoints | [r/R]: next/prev breakpoint | [</>]: start/return of call | [w/W]: last write of top stack item/memory word | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
+                Style::default().add_modifier(Modifier::DIM)
+            )),
+        };
         let paragraph = Paragraph::new(text_output)
             .block(block_controls)
             .alignment(Alignment::Center)
@@ -322,13 +372,10 @@ impl Tui {
         f.render_widget(paragraph, area);
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn draw_src<B: Backend>(
         f: &mut Frame<B>,
         address: Address,
-        identified_contracts: &BTreeMap<Address, String>,
-        known_contracts: &BTreeMap<String, ContractBytecodeSome>,
-        source_code: &BTreeMap<u32, String>,
+        contract_sources: Option<ContractSources>,
         ic: usize,
         call_kind: CallKind,
         area: Rect,
@@ -345,96 +392,113 @@ impl Tui {
 
         let mut text_output: Text = Text::from("");
 
-        if let Some(contract_name) = identified_contracts.get(&address) {
-            if let Some(known) = known_contracts.get(contract_name) {
-                // grab either the creation source map or runtime sourcemap
-                if let Some(sourcemap) = if matches!(call_kind, CallKind::Create) {
-                    known.bytecode.source_map()
-                } else {
-                    known.deployed_bytecode.bytecode.as_ref().expect("no bytecode").source_map()
-                } {
-                    match sourcemap {
-                        Ok(sourcemap) => {
-                            // we are handed a vector of SourceElements that give
-                            // us a span of sourcecode that is currently being executed
-                            // This includes an offset and length. This vector is in
-                            // instruction pointer order, meaning the location of
-                            // the instruction - sum(push_bytes[..pc])
-                            if let Some(source_idx) = sourcemap[ic].index {
-                                if let Some(source) = source_code.get(&source_idx) {
-                                    let offset = sourcemap[ic].offset;
-                                    let len = sourcemap[ic].length;
-
-                                    // split source into before, relevant, and after chunks
-                                    // split by line as well to do some formatting stuff
-                                    let mut before = source[..offset]
-                                        .split_inclusive('\n')
-                                        .collect::<Vec<&str>>();
-                                    let actual = source[offset..offset + len]
-                                        .split_inclusive('\n')
-                                        .map(|s| s.to_string())
-                                        .collect::<Vec<String>>();
-                                    let mut after = source[offset + len..]
-                                        .split_inclusive('\n')
-                                        .collect::<VecDeque<&str>>();
-
-                                    let mut line_number = 0;
-
-                                    let num_lines = before.len() + actual.len() + after.len();
-                                    let height = area.height as usize;
-                                    let needed_highlight = actual.len();
-                                    let mid_len = before.len() + actual.len();
-
-                                    // adjust what text we show of the source code
-                                    let (start_line, end_line) = if needed_highlight > height {
-                                        // highlighted section is more lines than we have avail
-                                        (before.len(), before.len() + needed_highlight)
-                                    } else if height > num_lines {
-                                        // we can fit entire source
-                                        (0, num_lines)
+        if let Some((known, source_code)) = contract_sources {
+            // grab either the creation source map or runtime sourcemap
+            if let Some(sourcemap) = Tui::source_map(known, call_kind) {
+                match sourcemap {
+                    Ok(sourcemap) => {
+                        // we are handed a vector of SourceElements that give
+                        // us a span of sourcecode that is currently being executed
+                        // This includes an offset and length. This vector is in
+                        // instruction pointer order, meaning the location of
+                        // the instruction - sum(push_bytes[..pc])
+                        if let Some(source_idx) = sourcemap[ic].index {
+                            if let Some(source) = source_code.get(&source_idx) {
+                                let offset = sourcemap[ic].offset;
+                                let len = sourcemap[ic].length;
+
+                                // split source into before, relevant, and after chunks
+                                // split by line as well to do some formatting stuff
+                                let mut before =
+                                    source[..offset].split_inclusive('\n').collect::<Vec<&str>>();
+                                let actual = source[offset..offset + len]
+                                    .split_inclusive('\n')
+                                    .map(|s| s.to_string())
+                                    .collect::<Vec<String>>();
+                                let mut after = source[offset + len..]
+                                    .split_inclusive('\n')
+                                    .collect::<VecDeque<&str>>();
+
+                                let mut line_number = 0;
+
+                                let num_lines = before.len() + actual.len() + after.len();
+                                let height = area.height as usize;
+                                let needed_highlight = actual.len();
+                                let mid_len = before.len() + actual.len();
+
+                                // adjust what text we show of the source code
+                                let (start_line, end_line) = if needed_highlight > height {
+                                    // highlighted section is more lines than we have avail
+                                    (before.len(), before.len() + needed_highlight)
+                                } else if height > num_lines {
+                                    // we can fit entire source
+                                    (0, num_lines)
+                                } else {
+                                    let remaining = height - needed_highlight;
+                                    let mut above = remaining / 2;
+                                    let mut below = remaining / 2;
+                                    if below > after.len() {
+                                        // unused space below the highlight
+                                        above += below - after.len();
+                                    } else if above > before.len() {
+                                        // we have unused space above the highlight
+                                        below += above - before.len();
                                     } else {
-                                        let remaining = height - needed_highlight;
-                                        let mut above = remaining / 2;
-                                        let mut below = remaining / 2;
-                                        if below > after.len() {
-                                            // unused space below the highlight
-                                            above += below - after.len();
-                                        } else if above > before.len() {
-                                            // we have unused space above the highlight
-                                            below += above - before.len();
-                                        } else {
-                                            // no unused space
-                                        }
+                                        // no unused space
+                                    }
 
-                                        (before.len().saturating_sub(above), mid_len + below)
-                                    };
-
-                                    let max_line_num = num_lines.to_string().len();
-                                    // We check if there is other text on the same line before the
-                                    // highlight starts
-                                    if let Some(last) = before.pop() {
-                                        if !last.ends_with('\n') {
-                                            before.iter().skip(start_line).for_each(|line| {
-                                                text_output.lines.push(Spans::from(vec![
-                                                    Span::styled(
-                                                        format!(
-                                                            "{: >max_line_num$}",
-                                                            line_number.to_string(),
-                                                            max_line_num = max_line_num
-                                                        ),
-                                                        Style::default()
-                                                            .fg(Color::Gray)
-                                                            .bg(Color::DarkGray),
-                                                    ),
-                                                    Span::styled(
-                                                        "\u{2800} ".to_string() + line,
-                                                        Style::default()
-                                                            .add_modifier(Modifier::DIM),
+                                    (before.len().saturating_sub(above), mid_len + below)
+                                };
+
+                                let max_line_num = num_lines.to_string().len();
+                                // We check if there is other text on the same line before the
+                                // highlight starts
+                                if let Some(last) = before.pop() {
+                                    if !last.ends_with('\n') {
+                                        before.iter().skip(start_line).for_each(|line| {
+                                            text_output.lines.push(Spans::from(vec![
+                                                Span::styled(
+                                                    format!(
+                                                        "{: >max_line_num$}",
+                                                        line_number.to_string(),
+                                                        max_line_num = max_line_num
                                                     ),
-                                                ]));
-                                                line_number += 1;
-                                            });
+                                                    Style::default()
+                                                        .fg(Color::Gray)
+                                                        .bg(Color::DarkGray),
+                                                ),
+                                                Span::styled(
+                                                    "\u{2800} ".to_string() + line,
+                                                    Style::default().add_modifier(Modifier::DIM),
+                                                ),
+                                            ]));
+                                            line_number += 1;
+                                        });
+
+                                        text_output.lines.push(Spans::from(vec![
+                                            Span::styled(
+                                                format!(
+                                                    "{: >max_line_num$}",
+                                                    line_number.to_string(),
+                                                    max_line_num = max_line_num
+                                                ),
+                                                Style::default()
+                                                    .fg(Color::Cyan)
+                                                    .bg(Color::DarkGray)
+                                                    .add_modifier(Modifier::BOLD),
+                                            ),
+                                            Span::raw("\u{2800} "),
+                                            Span::raw(last),
+                                            Span::styled(
+                                                actual[0].to_string(),
+                                                Style::default()
+                                                    .fg(Color::Cyan)
+                                                    .add_modifier(Modifier::BOLD),
+                                            ),
+                                        ]));
+                                        line_number += 1;
 
+                                        actual.iter().skip(1).for_each(|s| {
                                             text_output.lines.push(Spans::from(vec![
                                                 Span::styled(
                                                     format!(
@@ -448,97 +512,43 @@ impl Tui {
                                                         .add_modifier(Modifier::BOLD),
                                                 ),
                                                 Span::raw("\u{2800} "),
-                                                Span::raw(last),
                                                 Span::styled(
-                                                    actual[0].to_string(),
+                                                    // this is a hack to add coloring
+                                                    // because tui does weird trimming
+                                                    if s.is_empty() || s == "\n" {
+                                                        "\u{2800} \n".to_string()
+                                                    } else {
+                                                        s.to_string()
+                                                    },
                                                     Style::default()
                                                         .fg(Color::Cyan)
                                                         .add_modifier(Modifier::BOLD),
                                                 ),
                                             ]));
                                             line_number += 1;
-
-                                            actual.iter().skip(1).for_each(|s| {
-                                                text_output.lines.push(Spans::from(vec![
-                                                    Span::styled(
-                                                        format!(
-                                                            "{: >max_line_num$}",
-                                                            line_number.to_string(),
-                                                            max_line_num = max_line_num
-                                                        ),
-                                                        Style::default()
-                                                            .fg(Color::Cyan)
-                                                            .bg(Color::DarkGray)
-                                                            .add_modifier(Modifier::BOLD),
-                                                    ),
-                                                    Span::raw("\u{2800} "),
-                                                    Span::styled(
-                                                        // this is a hack to add coloring
-                                                        // because tui does weird trimming
-                                                        if s.is_empty() || s == "\n" {
-                                                            "\u{2800} \n".to_string()
-                                                        } else {
-                                                            s.to_string()
-                                                        },
-                                                        Style::default()
-                                                            .fg(Color::Cyan)
-                                                            .add_modifier(Modifier::BOLD),
-                                                    ),
-                                                ]));
-                                                line_number += 1;
-                                            });
-                                        } else {
-                                            before.push(last);
-                                            before.iter().skip(start_line).for_each(|line| {
-                                                text_output.lines.push(Spans::from(vec![
-                                                    Span::styled(
-                                                        format!(
-                                                            "{: >max_line_num$}",
-                                                            line_number.to_string(),
-                                                            max_line_num = max_line_num
-                                                        ),
-                                                        Style::default()
-                                                            .fg(Color::Gray)
-                                                            .bg(Color::DarkGray),
-                                                    ),
-                                                    Span::styled(
-                                                        "\u{2800} ".to_string() + line,
-                                                        Style::default()
-                                                            .add_modifier(Modifier::DIM),
-                                                    ),
-                                                ]));
-
-                                                line_number += 1;
-                                            });
-                                            actual.iter().for_each(|s| {
-                                                text_output.lines.push(Spans::from(vec![
-                                                    Span::styled(
-                                                        format!(
-                                                            "{: >max_line_num$}",
-                                                            line_number.to_string(),
-                                                            max_line_num = max_line_num
-                                                        ),
-                                                        Style::default()
-                                                            .fg(Color::Cyan)
-                                                            .bg(Color::DarkGray)
-                                                            .add_modifier(Modifier::BOLD),
-                                                    ),
-                                                    Span::raw("\u{2800} "),
-                                                    Span::styled(
-                                                        if s.is_empty() || s == "\n" {
-                                                            "\u{2800} \n".to_string()
-                                                        } else {
-                                                            s.to_string()
-                                                        },
-                                                        Style::default()
-                                                            .fg(Color::Cyan)
-                                                            .add_modifier(Modifier::BOLD),
-                                                    ),
-                                                ]));
-                                                line_number += 1;
-                                            });
-                                        }
+                                        });
                                     } else {
+                                        before.push(last);
+                                        before.iter().skip(start_line).for_each(|line| {
+                                            text_output.lines.push(Spans::from(vec![
+                                                Span::styled(
+                                                    format!(
+                                                        "{: >max_line_num$}",
+                                                        line_number.to_string(),
+                                                        max_line_num = max_line_num
+                                                    ),
+                                                    Style::default()
+                                                        .fg(Color::Gray)
+                                                        .bg(Color::DarkGray),
+                                                ),
+                                                Span::styled(
+                                                    "\u{2800} ".to_string() + line,
+                                                    Style::default().add_modifier(Modifier::DIM),
+                                                ),
+                                            ]));
+
+                                            line_number += 1;
+                                        });
                                         actual.iter().for_each(|s| {
                                             text_output.lines.push(Spans::from(vec![
                                                 Span::styled(
@@ -567,23 +577,8 @@ impl Tui {
                                             line_number += 1;
                                         });
                                     }
-
-                                    // fill in the rest of the line as unhighlighted
-                                    if let Some(last) = actual.last() {
-                                        if !last.ends_with('\n') {
-                                            if let Some(post) = after.pop_front() {
-                                                if let Some(last) = text_output.lines.last_mut() {
-                                                    last.0.push(Span::raw(post));
-                                                }
-                                            }
-                                        }
-                                    }
-
-                                    // add after highlighted text
-                                    while mid_len + after.len() > end_line {
-                                        after.pop_back();
-                                    }
-                                    after.iter().for_each(|line| {
+                                } else {
+                                    actual.iter().for_each(|s| {
                                         text_output.lines.push(Spans::from(vec![
                                             Span::styled(
                                                 format!(
@@ -592,33 +587,72 @@ impl Tui {
                                                     max_line_num = max_line_num
                                                 ),
                                                 Style::default()
-                                                    .fg(Color::Gray)
-                                                    .bg(Color::DarkGray),
+                                                    .fg(Color::Cyan)
+                                                    .bg(Color::DarkGray)
+                                                    .add_modifier(Modifier::BOLD),
                                             ),
+                                            Span::raw("\u{2800} "),
                                             Span::styled(
-                                                "\u{2800} ".to_string() + line,
-                                                Style::default().add_modifier(Modifier::DIM),
+                                                if s.is_empty() || s == "\n" {
+                                                    "\u{2800} \n".to_string()
+                                                } else {
+                                                    s.to_string()
+                                                },
+                                                Style::default()
+                                                    .fg(Color::Cyan)
+                                                    .add_modifier(Modifier::BOLD),
                                             ),
                                         ]));
                                         line_number += 1;
                                     });
-                                } else {
-                                    text_output.extend(Text::from("No source for srcmap index"));
                                 }
+
+                                // fill in the rest of the line as unhighlighted
+                                if let Some(last) = actual.last() {
+                                    if !last.ends_with('\n') {
+                                        if let Some(post) = after.pop_front() {
+                                            if let Some(last) = text_output.lines.last_mut() {
+                                                last.0.push(Span::raw(post));
+                                            }
+                                        }
+                                    }
+                                }
+
+                                // add after highlighted text
+                                while mid_len + after.len() > end_line {
+                                    after.pop_back();
+                                }
+                                after.iter().for_each(|line| {
+                                    text_output.lines.push(Spans::from(vec![
+                                        Span::styled(
+                                            format!(
+                                                "{: >max_line_num$}",
+                                                line_number.to_string(),
+                                                max_line_num = max_line_num
+                                            ),
+                                            Style::default().fg(Color::Gray).bg(Color::DarkGray),
+                                        ),
+                                        Span::styled(
+                                            "\u{2800} ".to_string() + line,
+                                            Style::default().add_modifier(Modifier::DIM),
+                                        ),
+                                    ]));
+                                    line_number += 1;
+                                });
                             } else {
-                                text_output.extend(Text::from("No srcmap index"));
+                                text_output.extend(Text::from("No source for srcmap index"));
                             }
+                        } else {
+                            text_output.extend(Text::from("No srcmap index"));
                         }
-                        Err(e) => text_output.extend(Text::from(format!(
-                            "Error in source map parsing: '{}', please open an issue",
-                            e
-                        ))),
                     }
-                } else {
-                    text_output.extend(Text::from("No sourcemap for contract"));
+                    Err(e) => text_output.extend(Text::from(format!(
+                        "Error in source map parsing: '{}', please open an issue",
+                        e
+                    ))),
                 }
             } else {
-                text_output.extend(Text::from(format!("Unknown contract at address {}", address)));
+                text_output.extend(Text::from("No sourcemap for contract"));
             }
         } else {
             text_output.extend(Text::from(format!("Unknown contract at address {}", address)));
@@ -953,11 +987,39 @@ impl Ui for Tui {
         self.terminal.clear()?;
         let mut draw_memory: DrawMemory = DrawMemory::default();
 
-        let debug_call: Vec<(Address, Vec<DebugStep>, CallKind)> = self.debug_arena.clone();
+        let debug_call: Vec<(Address, Vec<DebugStep>, CallKind, usize)> = self.debug_arena.clone();
         let mut opcode_list: Vec<String> =
             debug_call[0].1.iter().map(|step| step.pretty_opcode()).collect();
         let mut last_index = 0;
 
+        let call_sources: Vec<Option<ContractSources>> = debug_call
+            .iter()
+            .map(|(address, ..)| {
+                Tui::contract_sources(
+                    address,
+                    &self.identified_contracts,
+                    &self.known_contracts,
+                    &self.source_code,
+                    &self.external_contracts,
+                )
+            })
+            .collect();
+        // The source lines of the steps, to step through the source code
+        let step_locations = step_locations(debug_call.iter().zip(&call_sources).map(
+            |((_, steps, call_kind, depth), &sources)| {
+                let sources = sources.and_then(|(known, source_code)| {
+                    Some((Tui::source_map(known, *call_kind)?.ok()?, source_code))
+                });
+                (*depth, &steps[..], sources)
+            },
+        ));
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        // The breakpoint being typed, while the breakpoint prompt is open
+        let mut breakpoint_input: Option<String> = None;
+        // Shown instead of the key bindings until the next key is pressed
+        let mut message: Option<String> = None;
+
         let mut stack_labels = false;
         let mut mem_utf = false;
         // UI thread that manages drawing
@@ -971,7 +1033,40 @@ impl Ui for Tui {
                 last_index = draw_memory.inner_call_index;
             }
             // Grab interrupt
-            match rx.recv()? {
+            let interrupt = rx.recv()?;
+            if let Interrupt::KeyPressed(_) = interrupt {
+                message = None;
+            }
+            match interrupt {
+                // Type a breakpoint
+                Interrupt::KeyPressed(event) if breakpoint_input.is_some() => {
+                    let input = breakpoint_input.as_mut().expect("the breakpoint prompt is open");
+                    match event.code {
+                        KeyCode::Char(c) => input.push(c),
+                        KeyCode::Backspace => {
+                            input.pop();
+                        }
+                        KeyCode::Enter => {
+                            message = Some(match input.parse::<Breakpoint>() {
+                                Ok(breakpoint) => {
+                                    breakpoints.push(breakpoint);
+                                    format!(
+                                        "Breakpoints: {}",
+                                        breakpoints
+                                            .iter()
+                                            .map(|breakpoint| breakpoint.to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    )
+                                }
+                                Err(err) => err,
+                            });
+                            breakpoint_input = None;
+                        }
+                        KeyCode::Esc => breakpoint_input = None,
+                        _ => {}
+                    }
+                }
                 // Key press
                 Interrupt::KeyPressed(event) => match event.code {
                     // Exit
@@ -1123,6 +1218,97 @@ impl Ui for Tui {
                         }
                         self.key_buffer.clear();
                     }
+                    // Step into, over or out of the current source line
+                    KeyCode::Char(key @ ('i' | 'n' | 'o')) => {
+                        let source_step = match key {
+                            'i' => SourceStep::Into,
+                            'n' => SourceStep::Over,
+                            _ => SourceStep::Out,
+                        };
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let from = (draw_memory.inner_call_index, self.current_step);
+                            // Without a next line, move to the end of the execution
+                            let (call, step) = next_source_step(&step_locations, from, source_step)
+                                .unwrap_or((
+                                    debug_call.len() - 1,
+                                    debug_call[debug_call.len() - 1].1.len() - 1,
+                                ));
+                            draw_memory.enter_call(call);
+                            self.current_step = step;
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Go to the start of the current call
+                    KeyCode::Char('<') => {
+                        let segments = call_segments(&debug_call, draw_memory.inner_call_index);
+                        draw_memory.enter_call(segments[0]);
+                        self.current_step = 0;
+                        self.key_buffer.clear();
+                    }
+                    // Go to the return of the current call
+                    KeyCode::Char('>') => {
+                        let segments = call_segments(&debug_call, draw_memory.inner_call_index);
+                        let last = segments[segments.len() - 1];
+                        draw_memory.enter_call(last);
+                        self.current_step = debug_call[last].1.len() - 1;
+                        self.key_buffer.clear();
+                    }
+                    // Go to the step that last wrote the stack item or memory word at the top of
+                    // the stack or memory pane
+                    KeyCode::Char(key @ ('w' | 'W')) => {
+                        let from = (draw_memory.inner_call_index, self.current_step);
+                        let (write, what) = if key == 'w' {
+                            let index = draw_memory.current_stack_startline;
+                            (
+                                last_stack_write(&debug_call, from, index),
+                                format!("Stack item {}", index),
+                            )
+                        } else {
+                            let word = draw_memory.current_mem_startline;
+                            (
+                                last_memory_write(&debug_call, from, word),
+                                format!("Memory word 0x{:x}", word * 32),
+                            )
+                        };
+                        message = Some(match write {
+                            Some((call, step)) => {
+                                draw_memory.enter_call(call);
+                                self.current_step = step;
+                                format!("{} was last written here", what)
+                            }
+                            None => format!("{} was not written before in this call", what),
+                        });
+                        self.key_buffer.clear();
+                    }
+                    // Open the breakpoint prompt
+                    KeyCode::Char('b') => {
+                        breakpoint_input = Some(String::new());
+                        self.key_buffer.clear();
+                    }
+                    // Clear the breakpoints
+                    KeyCode::Char('B') => {
+                        breakpoints.clear();
+                        message = Some("Cleared the breakpoints".to_string());
+                        self.key_buffer.clear();
+                    }
+                    // Continue to the next or previous breakpoint
+                    KeyCode::Char(key @ ('r' | 'R')) => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let from = (draw_memory.inner_call_index, self.current_step);
+                            match find_breakpoint(&debug_call, &breakpoints, from, key == 'r') {
+                                Some(((call, step), description)) => {
+                                    draw_memory.enter_call(call);
+                                    self.current_step = step;
+                                    message = Some(description);
+                                }
+                                None => {
+                                    message = Some("No more breakpoints".to_string());
+                                    break
+                                }
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
                     // toggle stack labels
                     KeyCode::Char('t') => {
                         stack_labels = !stack_labels;
@@ -1172,13 +1358,18 @@ impl Ui for Tui {
             }
             // Draw
             let current_step = self.current_step;
+            let footer_message = match &breakpoint_input {
+                Some(input) => Some(format!(
+                    "Breakpoint (`pc <pc>`, `op <opcode>` or `call <address>`): {}",
+                    input
+                )),
+                None => message.clone(),
+            };
             self.terminal.draw(|f| {
                 Tui::draw_layout(
                     f,
                     debug_call[draw_memory.inner_call_index].0,
-                    &self.identified_contracts,
-                    &self.known_contracts,
-                    &self.source_code,
+                    call_sources[draw_memory.inner_call_index],
                     &debug_call[draw_memory.inner_call_index].1[..],
                     &opcode_list,
                     current_step,
@@ -1186,6 +1377,7 @@ impl Ui for Tui {
                     &mut draw_memory,
                     stack_labels,
                     mem_utf,
+                    footer_message.as_deref(),
                 )
             })?;
         }
@@ -1217,4 +1409,13 @@ impl DrawMemory {
             current_stack_startline: 0,
         }
     }
+
+    /// Moves to the call at `call_index`, scrolling the stack and memory back up if it changed
+    fn enter_call(&mut self, call_index: usize) {
+        if call_index != self.inner_call_index {
+            self.inner_call_index = call_index;
+            self.current_mem_startline = 0;
+            self.current_stack_startline = 0;
+        }
+    }
 }
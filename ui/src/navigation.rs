@@ -0,0 +1,122 @@
+//! Jumping through the execution of a call, backwards and forwards
+
+use ethers::types::Address;
+use forge::{debug::DebugStep, CallKind};
+
+/// Returns the indices of the flattened calls that make up the call that `call` is part of, in
+/// the order they were executed.
+///
+/// A call is split into several flattened calls by its subcalls, so the first one starts the call
+/// and the others continue it after a subcall returned.
+pub fn call_segments(
+    calls: &[(Address, Vec<DebugStep>, CallKind, usize)],
+    call: usize,
+) -> Vec<usize> {
+    let depth = calls[call].3;
+    let starts_call = |idx: usize| idx == 0 || calls[idx].3 > calls[idx - 1].3;
+
+    // Skip the subcalls and continuations before `call` to find the start of the call
+    let mut first = call;
+    while calls[first].3 != depth || !starts_call(first) {
+        first -= 1;
+    }
+
+    let mut segments = vec![first];
+    for idx in first + 1..calls.len() {
+        let segment_depth = calls[idx].3;
+        if segment_depth < depth || (segment_depth == depth && starts_call(idx)) {
+            break
+        }
+        if segment_depth == depth {
+            segments.push(idx);
+        }
+    }
+    segments
+}
+
+/// Returns the position (the index of the call and of the step in the call) of the step before
+/// `from` that last wrote the stack item at `index`, counted from the top of the stack at `from`.
+///
+/// The stack item may also have been written with the same value, which is not detected.
+pub fn last_stack_write(
+    calls: &[(Address, Vec<DebugStep>, CallKind, usize)],
+    from: (usize, usize),
+    index: usize,
+) -> Option<(usize, usize)> {
+    let position = calls[from.0].1[from.1].stack.len().checked_sub(index + 1)?;
+    last_write(calls, from, |prev, next| {
+        next.stack.len() > position && prev.stack.get(position) != next.stack.get(position)
+    })
+}
+
+/// Returns the position (the index of the call and of the step in the call) of the step before
+/// `from` that last wrote the memory word at `word`, including expanding the memory to it.
+pub fn last_memory_write(
+    calls: &[(Address, Vec<DebugStep>, CallKind, usize)],
+    from: (usize, usize),
+    word: usize,
+) -> Option<(usize, usize)> {
+    let range = word * 32..word * 32 + 32;
+    calls[from.0].1[from.1].memory.data().get(range.clone())?;
+    last_write(calls, from, |prev, next| {
+        let next = next.memory.data().get(range.clone());
+        next.is_some() && prev.memory.data().get(range.clone()) != next
+    })
+}
+
+/// Returns the position of the last step of the current call before `from` whose execution
+/// `changed` the value, given the steps before and after it
+fn last_write(
+    calls: &[(Address, Vec<DebugStep>, CallKind, usize)],
+    from: (usize, usize),
+    changed: impl Fn(&DebugStep, &DebugStep) -> bool,
+) -> Option<(usize, usize)> {
+    let steps: Vec<((usize, usize), &DebugStep)> = call_segments(calls, from.0)
+        .into_iter()
+        .flat_map(|call| {
+            calls[call].1.iter().enumerate().map(move |(idx, step)| ((call, idx), step))
+        })
+        .take_while(|(position, _)| *position <= from)
+        .collect();
+
+    steps.windows(2).rev().find(|pair| changed(pair[0].1, pair[1].1)).map(|pair| pair[0].0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+
+    fn step(stack: &[u64]) -> DebugStep {
+        DebugStep { stack: stack.iter().copied().map(U256::from).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn can_find_call_segments() {
+        let address = Address::random();
+        let call = |depth| (address, vec![step(&[])], CallKind::Call, depth);
+        let calls = vec![call(0), call(1), call(2), call(1), call(0), call(1), call(0)];
+
+        assert_eq!(call_segments(&calls, 0), vec![0, 4, 6]);
+        assert_eq!(call_segments(&calls, 4), vec![0, 4, 6]);
+        assert_eq!(call_segments(&calls, 3), vec![1, 3]);
+        assert_eq!(call_segments(&calls, 5), vec![5]);
+    }
+
+    #[test]
+    fn can_find_stack_writes() {
+        let address = Address::random();
+        let calls = vec![
+            (address, vec![step(&[]), step(&[1]), step(&[1, 2])], CallKind::Call, 0),
+            (address, vec![step(&[])], CallKind::Call, 1),
+            (address, vec![step(&[1, 0]), step(&[1, 0, 3]), step(&[1, 3])], CallKind::Call, 0),
+        ];
+
+        // The `0` was written by the call, whose last step is the one before the subcall
+        assert_eq!(last_stack_write(&calls, (2, 1), 1), Some((0, 2)));
+        // The `3` was moved by the last step
+        assert_eq!(last_stack_write(&calls, (2, 2), 0), Some((2, 1)));
+        assert_eq!(last_stack_write(&calls, (2, 2), 1), Some((0, 0)));
+        assert_eq!(last_stack_write(&calls, (2, 2), 2), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! Stepping through the debugged calls line by line
+
+use ethers::solc::sourcemap::{Jump, SourceMap};
+use forge::debug::{DebugStep, Instruction};
+use revm::opcode;
+use std::collections::BTreeMap;
+
+/// Where a step is in the source code and in the call stack
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepLocation {
+    /// The index of the source file and the line of the step, if the contract has a source map
+    pub line: Option<(u32, usize)>,
+    /// The depth of the call the step is in
+    pub call_depth: usize,
+    /// The number of internal functions the step is in, according to the jumps of the source map
+    pub function_depth: usize,
+}
+
+impl StepLocation {
+    /// The frame of the step, which is lower the further out the frame is
+    fn frame(&self) -> (usize, usize) {
+        (self.call_depth, self.function_depth)
+    }
+}
+
+/// How to move to the next source line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceStep {
+    /// Move to the next line, which may be in a called function
+    Into,
+    /// Move to the next line of the current function, or of its caller if it returns
+    Over,
+    /// Move to the first line after the current function returned to its caller
+    Out,
+}
+
+/// Returns the location of every step of the calls.
+///
+/// The calls are given in the order they were executed, with their depth, their steps and the
+/// source map and sources of their contract, if they are known.
+pub fn step_locations<'a>(
+    calls: impl IntoIterator<
+        Item = (usize, &'a [DebugStep], Option<(SourceMap, &'a BTreeMap<u32, String>)>),
+    >,
+) -> Vec<Vec<StepLocation>> {
+    // The function depth of every call of the call stack
+    let mut function_depths: Vec<usize> = Vec::new();
+
+    let mut locations = Vec::new();
+    for (call_depth, steps, sources) in calls {
+        // A new call starts outside of any function, while a call that continues after a subcall
+        // returned keeps its function depth
+        function_depths.truncate(call_depth + 1);
+        function_depths.resize(call_depth + 1, 0);
+        let function_depth = &mut function_depths[call_depth];
+
+        let mut line_starts: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        let mut call_locations = Vec::with_capacity(steps.len());
+        for step in steps {
+            let mut location =
+                StepLocation { line: None, call_depth, function_depth: *function_depth };
+            if let Some((source_map, source_code)) = &sources {
+                if let Some(element) = source_map.get(step.ic) {
+                    location.line = element.index.and_then(|index| {
+                        let source = source_code.get(&index)?;
+                        let starts = line_starts.entry(index).or_insert_with(|| {
+                            std::iter::once(0)
+                                .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+                                .collect()
+                        });
+                        Some((index, starts.partition_point(|start| *start <= element.offset) - 1))
+                    });
+
+                    if matches!(step.instruction, Instruction::OpCode(op) if op == opcode::JUMP) {
+                        match element.jump {
+                            Jump::In => *function_depth += 1,
+                            Jump::Out => *function_depth = function_depth.saturating_sub(1),
+                            Jump::Regular => {}
+                        }
+                    }
+                }
+            }
+            call_locations.push(location);
+        }
+        locations.push(call_locations);
+    }
+    locations
+}
+
+/// Returns the position (the index of the call and of the step in the call) to move to from the
+/// step at `from`, or `None` if there is no such step after it.
+pub fn next_source_step(
+    locations: &[Vec<StepLocation>],
+    from: (usize, usize),
+    step: SourceStep,
+) -> Option<(usize, usize)> {
+    let current = locations.get(from.0)?.get(from.1)?;
+    locations
+        .iter()
+        .enumerate()
+        .skip(from.0)
+        .flat_map(|(call, steps)| {
+            steps.iter().enumerate().map(move |(idx, location)| ((call, idx), location))
+        })
+        .filter(|(position, _)| *position > from)
+        .find(|(_, location)| {
+            let new_line = location.line.is_some() &&
+                (location.line, location.frame()) != (current.line, current.frame());
+            // Returning to the calling contract always stops, since it might have no source map
+            let returned = location.call_depth < current.call_depth;
+            match step {
+                SourceStep::Into => new_line,
+                SourceStep::Over => returned || (location.frame() <= current.frame() && new_line),
+                SourceStep::Out => {
+                    returned || (location.frame() < current.frame() && location.line.is_some())
+                }
+            }
+        })
+        .map(|(position, _)| position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: usize, call_depth: usize, function_depth: usize) -> StepLocation {
+        StepLocation { line: Some((0, line)), call_depth, function_depth }
+    }
+
+    #[test]
+    fn can_step_by_source_line() {
+        let locations = vec![
+            vec![location(1, 0, 0), location(1, 0, 0), location(2, 0, 0), location(5, 0, 1)],
+            // A call to a contract without a source map
+            vec![StepLocation { call_depth: 1, ..Default::default() }],
+            vec![location(6, 0, 1), location(3, 0, 0), location(4, 0, 0)],
+        ];
+
+        assert_eq!(next_source_step(&locations, (0, 0), SourceStep::Into), Some((0, 2)));
+        assert_eq!(next_source_step(&locations, (0, 2), SourceStep::Into), Some((0, 3)));
+        assert_eq!(next_source_step(&locations, (0, 2), SourceStep::Over), Some((2, 1)));
+        assert_eq!(next_source_step(&locations, (0, 3), SourceStep::Out), Some((2, 1)));
+        assert_eq!(next_source_step(&locations, (1, 0), SourceStep::Over), Some((2, 0)));
+        assert_eq!(next_source_step(&locations, (2, 2), SourceStep::Into), None);
+    }
+}
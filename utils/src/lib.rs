@@ -23,6 +23,9 @@ use std::{
 
 use tokio::runtime::{Handle, Runtime};
 
+mod signatures;
+pub use signatures::SignatureDb;
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum RuntimeOrHandle {
@@ -645,28 +648,38 @@ pub async fn fourbyte_event(topic: &str) -> Result<Vec<(String, i32)>> {
 
 /// Pretty print calldata and if available, fetch possible function signatures
 ///
+/// The signatures are looked up in the signature database first, and on 4byte.directory if they
+/// are not known and `offline` is not set.
+///
 /// ```no_run
 /// 
-/// use foundry_utils::pretty_calldata;
+/// use foundry_utils::{pretty_calldata, SignatureDb};
 ///
 /// # async fn foo() -> eyre::Result<()> {
-///   let pretty_data = pretty_calldata("0x70a08231000000000000000000000000d0074f4e6490ae3f888d1d4f7e3e43326bd3f0f5".to_string(), false).await?;
+///   let mut db = SignatureDb::default();
+///   let pretty_data = pretty_calldata("0x70a08231000000000000000000000000d0074f4e6490ae3f888d1d4f7e3e43326bd3f0f5".to_string(), false, &mut db).await?;
 ///   println!("{}",pretty_data);
 /// # Ok(())
 /// # }
 /// ```
 
-pub async fn pretty_calldata(calldata: impl AsRef<str>, offline: bool) -> Result<PossibleSigs> {
+pub async fn pretty_calldata(
+    calldata: impl AsRef<str>,
+    offline: bool,
+    db: &mut SignatureDb,
+) -> Result<PossibleSigs> {
     let mut possible_info = PossibleSigs::new();
     let calldata = calldata.as_ref().trim_start_matches("0x");
 
     let selector =
         calldata.get(..8).ok_or_else(|| eyre::eyre!("calldata cannot be less that 4 bytes"))?;
 
-    let sigs = if offline {
-        vec![]
+    let known: Vec<String> =
+        db.functions(hex::decode(selector)?).into_iter().map(str::to_string).collect();
+    let sigs = if offline || !known.is_empty() {
+        known
     } else {
-        fourbyte(selector).await.unwrap_or_default().into_iter().map(|sig| sig.0).collect()
+        db.identify_function(selector).await.unwrap_or_default()
     };
     let (_, data) = calldata.split_at(8);
 
@@ -0,0 +1,234 @@
+//! A local database of function, error and event signatures
+
+use crate::{fourbyte, fourbyte_event, fourbyte_possible_sigs};
+use ethers_core::{
+    abi::{Abi, AbiParser, Event, Function, ParamType},
+    utils::keccak256,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+/// A local database of function, error and event signatures.
+///
+/// The database is filled with the ABIs of compiled contracts and with the results of 4byte
+/// lookups, so that selectors and topics that were seen once can be decoded offline, without
+/// sending them to a third party.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SignatureDb {
+    /// Function and error signatures by their selector, e.g. `0xa9059cbb`
+    functions: BTreeMap<String, BTreeSet<String>>,
+    /// Event signatures by their topic
+    events: BTreeMap<String, BTreeSet<String>>,
+    /// The file the database is stored in
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Whether signatures were added since the database was loaded
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl SignatureDb {
+    /// Loads the database stored in `path`.
+    ///
+    /// The database is only a cache, so it is empty if the file does not exist or can not be read.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let db = std::fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<Self>(&content).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), ..db }
+    }
+
+    /// Writes the database to its file, if signatures were added
+    pub fn save(&mut self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) if self.changed => path,
+            _ => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Returns the known function and error signatures with the given selector
+    pub fn functions(&self, selector: impl AsRef<[u8]>) -> Vec<&str> {
+        let selector = selector.as_ref();
+        self.functions
+            .get(&format!("0x{}", hex::encode(&selector[..selector.len().min(4)])))
+            .map(|sigs| sigs.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the known event signatures with the given topic
+    pub fn events(&self, topic: impl AsRef<[u8]>) -> Vec<&str> {
+        self.events
+            .get(&format!("0x{}", hex::encode(topic.as_ref())))
+            .map(|sigs| sigs.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the known functions with the given selector
+    pub fn decode_functions(&self, selector: impl AsRef<[u8]>) -> Vec<Function> {
+        self.functions(selector)
+            .into_iter()
+            .filter_map(|sig| AbiParser::default().parse_function(sig).ok())
+            .collect()
+    }
+
+    /// Returns the known events with the given topic and number of indexed inputs.
+    ///
+    /// The signatures do not say which inputs are indexed, so the first inputs are assumed to be
+    /// the indexed ones.
+    pub fn decode_events(&self, topic: impl AsRef<[u8]>, indexed: usize) -> Vec<Event> {
+        self.events(topic)
+            .into_iter()
+            .filter_map(|sig| AbiParser::default().parse_event(&format!("event {}", sig)).ok())
+            .filter(|event| event.inputs.len() >= indexed)
+            .map(|mut event| {
+                for (i, input) in event.inputs.iter_mut().enumerate() {
+                    input.indexed = i < indexed;
+                }
+                event
+            })
+            .collect()
+    }
+
+    /// Adds a function or error signature, e.g. `transfer(address,uint256)`
+    pub fn insert_function(&mut self, sig: impl Into<String>) {
+        let sig = sig.into();
+        let selector = format!("0x{}", hex::encode(&keccak256(sig.as_bytes())[..4]));
+        self.changed |= self.functions.entry(selector).or_default().insert(sig);
+    }
+
+    /// Adds an event signature, e.g. `Transfer(address,address,uint256)`
+    pub fn insert_event(&mut self, sig: impl Into<String>) {
+        let sig = sig.into();
+        let topic = format!("0x{}", hex::encode(keccak256(sig.as_bytes())));
+        self.changed |= self.events.entry(topic).or_default().insert(sig);
+    }
+
+    /// Adds all functions, errors and events of the ABI
+    pub fn insert_abi(&mut self, abi: &Abi) {
+        for func in abi.functions() {
+            self.insert_function(signature(&func.name, func.inputs.iter().map(|p| &p.kind)));
+        }
+        for error in abi.errors() {
+            self.insert_function(signature(&error.name, error.inputs.iter().map(|p| &p.kind)));
+        }
+        for event in abi.events() {
+            self.insert_event(signature(&event.name, event.inputs.iter().map(|p| &p.kind)));
+        }
+    }
+
+    /// Returns the function signatures with the selector, looking them up on 4byte.directory if
+    /// they are not known yet
+    pub async fn identify_function(&mut self, selector: &str) -> Result<Vec<String>> {
+        let known = self.functions(decode_hex(selector)?);
+        if !known.is_empty() {
+            return Ok(known.into_iter().map(str::to_string).collect())
+        }
+
+        let sigs: Vec<String> = fourbyte(selector).await?.into_iter().map(|sig| sig.0).collect();
+        sigs.iter().for_each(|sig| self.insert_function(sig.as_str()));
+        self.save()?;
+        Ok(sigs)
+    }
+
+    /// Returns the signatures of the functions that can decode the calldata, looking them up on
+    /// 4byte.directory if they are not known yet.
+    ///
+    /// If an `id` is given, the signature is always looked up on 4byte.directory, since the ids
+    /// are specific to it. See [fourbyte_possible_sigs].
+    pub async fn identify_calldata(
+        &mut self,
+        calldata: &str,
+        id: Option<String>,
+    ) -> Result<Vec<String>> {
+        if id.is_none() {
+            let known = self.functions(decode_hex(calldata)?);
+            if !known.is_empty() {
+                return Ok(known
+                    .into_iter()
+                    .filter(|sig| crate::abi_decode(sig, calldata, true).is_ok())
+                    .map(str::to_string)
+                    .collect())
+            }
+        }
+
+        let sigs = fourbyte_possible_sigs(calldata, id).await?;
+        sigs.iter().for_each(|sig| self.insert_function(sig.as_str()));
+        self.save()?;
+        Ok(sigs)
+    }
+
+    /// Returns the event signatures with the topic, looking them up on 4byte.directory if they
+    /// are not known yet
+    pub async fn identify_event(&mut self, topic: &str) -> Result<Vec<String>> {
+        let topic_bytes = decode_hex(topic)?;
+        let known = self.events(&topic_bytes);
+        if !known.is_empty() {
+            return Ok(known.into_iter().map(str::to_string).collect())
+        }
+
+        // 4byte.directory matches events by the first 4 bytes of the topic only
+        let sigs: Vec<String> = fourbyte_event(topic)
+            .await?
+            .into_iter()
+            .map(|sig| sig.0)
+            .filter(|sig| keccak256(sig.as_bytes())[..] == topic_bytes[..])
+            .collect();
+        sigs.iter().for_each(|sig| self.insert_event(sig.as_str()));
+        self.save()?;
+        Ok(sigs)
+    }
+}
+
+/// Returns the signature of a function, error or event, e.g. `transfer(address,uint256)`
+fn signature<'a>(name: &str, kinds: impl Iterator<Item = &'a ParamType>) -> String {
+    format!("{}({})", name, kinds.map(|kind| kind.to_string()).collect::<Vec<_>>().join(","))
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(s.strip_prefix("0x").unwrap_or(s))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::abi::parse_abi;
+
+    #[test]
+    fn can_store_abi_signatures() {
+        let abi = parse_abi(&[
+            "function transfer(address to, uint256 amount) returns (bool)",
+            "event Transfer(address indexed from, address indexed to, uint256 amount)",
+        ])
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signatures.json");
+
+        let mut db = SignatureDb::load(&path);
+        db.insert_abi(&abi);
+        db.save().unwrap();
+
+        let db = SignatureDb::load(&path);
+        let selector = hex::decode("a9059cbb").unwrap();
+        assert_eq!(db.functions(&selector), vec!["transfer(address,uint256)"]);
+        assert_eq!(db.decode_functions(&selector)[0].name, "transfer");
+        assert!(db.functions([0u8; 4]).is_empty());
+
+        let topic = keccak256("Transfer(address,address,uint256)");
+        assert_eq!(db.events(topic), vec!["Transfer(address,address,uint256)"]);
+        let events = db.decode_events(topic, 2);
+        assert!(events[0].inputs[1].indexed);
+        assert!(!events[0].inputs[2].indexed);
+    }
+}